@@ -0,0 +1,7 @@
+pub const BASE: &str = "org.Xetibo.ReSet.Daemon";
+
+pub const MONITORS_PATH: &str = "/org/Xetibo/ReSet/Plugins/Monitors";
+pub const MONITORS_INTERFACE: &str = reset_common::monitors::INTERFACE;
+
+pub const KEYBOARD_PATH: &str = "/org/Xetibo/ReSet/Plugins/Keyboard";
+pub const KEYBOARD_INTERFACE: &str = "org.Xetibo.ReSet.Keyboard";
@@ -0,0 +1,65 @@
+// This file handles the keyboard subcommands via the DBus interface of the keyboard plugin
+use reset_common::keyboard::KeyboardLayout;
+
+use crate::{
+    r#const::{KEYBOARD_INTERFACE, KEYBOARD_PATH},
+    utils::{format_table, with_proxy, CliError},
+};
+
+/// Finds the layouts in the xkb notation, e.g. us or us(intl), the order is kept.
+pub fn find_layouts(
+    available: &[KeyboardLayout],
+    ids: &[String],
+) -> Result<Vec<KeyboardLayout>, CliError> {
+    ids.iter()
+        .map(|id| {
+            available
+                .iter()
+                .find(|layout| layout.id() == *id)
+                .cloned()
+                .ok_or_else(|| CliError::NotFound(format!("keyboard layout {}", id)))
+        })
+        .collect()
+}
+
+pub fn layouts_table(layouts: &[KeyboardLayout]) -> String {
+    let rows: Vec<Vec<String>> = layouts
+        .iter()
+        .map(|layout| vec![layout.id(), layout.description.clone()])
+        .collect();
+    format_table(&["layout", "description"], &rows)
+}
+
+fn get_layouts(method: &str) -> Result<Vec<KeyboardLayout>, CliError> {
+    with_proxy(KEYBOARD_PATH, |proxy| {
+        let (layouts,): (Vec<KeyboardLayout>,) =
+            proxy.method_call(KEYBOARD_INTERFACE, method, ())?;
+        Ok(layouts)
+    })
+}
+
+fn print_layouts(layouts: &[KeyboardLayout], json: bool) -> Result<String, CliError> {
+    if json {
+        return Ok(serde_json::to_string_pretty(layouts)? + "\n");
+    }
+    Ok(layouts_table(layouts))
+}
+
+/// Lists all layouts that can be used.
+pub fn list(json: bool) -> Result<String, CliError> {
+    print_layouts(&get_layouts("GetKeyboardLayouts")?, json)
+}
+
+/// Lists the configured layouts in their order.
+pub fn saved(json: bool) -> Result<String, CliError> {
+    print_layouts(&get_layouts("GetSavedLayouts")?, json)
+}
+
+/// Replaces the configured layouts, the first layout is the default one.
+pub fn set(ids: &[String]) -> Result<String, CliError> {
+    let layouts = find_layouts(&get_layouts("GetKeyboardLayouts")?, ids)?;
+    with_proxy(KEYBOARD_PATH, |proxy| {
+        proxy.method_call::<(), _, _, _>(KEYBOARD_INTERFACE, "SaveLayoutOrder", (layouts,))?;
+        Ok(String::new())
+    })
+}
@@ -0,0 +1,153 @@
+use std::process::ExitCode;
+
+use utils::CliError;
+
+mod r#const;
+mod keyboard;
+mod monitors;
+#[cfg(test)]
+mod tests;
+mod utils;
+
+const USAGE: &str = "Usage: reset-cli [--json] <command>
+
+Commands:
+  monitors list                 show the connected monitors
+  monitors apply <file|->       apply monitors in the format of monitors list --json
+  monitors save <file|->        apply and persistently save monitors
+  monitors confirm              keep the configuration of monitors profile apply
+  monitors profile list         show the stored profiles
+  monitors profile apply <name> apply a stored profile, reverted unless confirmed
+  monitors profile save <name>  store the current monitors as profile
+  monitors profile delete <name>
+  keyboard list                 show all available layouts
+  keyboard saved                show the configured layouts
+  keyboard set <layout>...      configure layouts in xkb notation, e.g. us or us(intl)
+
+Options:
+  --json                        print JSON instead of a table
+  -h, --help                    show this help";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Help,
+    MonitorsList,
+    MonitorsApply(String),
+    MonitorsSave(String),
+    MonitorsConfirm,
+    ProfileList,
+    ProfileApply(String),
+    ProfileSave(String),
+    ProfileDelete(String),
+    KeyboardList,
+    KeyboardSaved,
+    KeyboardSet(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub json: bool,
+    pub command: Command,
+}
+
+fn single_argument(args: &[&str], what: &str) -> Result<String, CliError> {
+    match args {
+        [value] => Ok(value.to_string()),
+        _ => Err(CliError::Usage(format!("expected {}", what))),
+    }
+}
+
+pub fn parse_args(args: &[String]) -> Result<Options, CliError> {
+    let mut json = false;
+    let mut positional = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            "-h" | "--help" => {
+                return Ok(Options {
+                    json,
+                    command: Command::Help,
+                })
+            }
+            // - stands for stdin
+            val if val.starts_with('-') && val != "-" => {
+                return Err(CliError::Usage(format!("unknown option {}", val)))
+            }
+            val => positional.push(val),
+        }
+    }
+    let command = match positional.as_slice() {
+        [] => Command::Help,
+        ["monitors", "list"] => Command::MonitorsList,
+        ["monitors", "apply", rest @ ..] => {
+            Command::MonitorsApply(single_argument(rest, "a file or - for stdin")?)
+        }
+        ["monitors", "save", rest @ ..] => {
+            Command::MonitorsSave(single_argument(rest, "a file or - for stdin")?)
+        }
+        ["monitors", "confirm"] => Command::MonitorsConfirm,
+        ["monitors", "profile", "list"] => Command::ProfileList,
+        ["monitors", "profile", "apply", rest @ ..] => {
+            Command::ProfileApply(single_argument(rest, "a profile name")?)
+        }
+        ["monitors", "profile", "save", rest @ ..] => {
+            Command::ProfileSave(single_argument(rest, "a profile name")?)
+        }
+        ["monitors", "profile", "delete", rest @ ..] => {
+            Command::ProfileDelete(single_argument(rest, "a profile name")?)
+        }
+        ["keyboard", "list"] => Command::KeyboardList,
+        ["keyboard", "saved"] => Command::KeyboardSaved,
+        ["keyboard", "set", rest @ ..] if !rest.is_empty() => {
+            Command::KeyboardSet(rest.iter().map(|val| val.to_string()).collect())
+        }
+        ["keyboard", "set"] => {
+            return Err(CliError::Usage(String::from(
+                "expected at least one keyboard layout",
+            )))
+        }
+        _ => {
+            return Err(CliError::Usage(format!(
+                "unknown command {}",
+                positional.join(" ")
+            )))
+        }
+    };
+    Ok(Options { json, command })
+}
+
+fn run(options: Options) -> Result<String, CliError> {
+    let json = options.json;
+    match options.command {
+        Command::Help => Ok(format!("{}\n", USAGE)),
+        Command::MonitorsList => monitors::list(json),
+        Command::MonitorsApply(path) => monitors::apply(&path, false),
+        Command::MonitorsSave(path) => monitors::apply(&path, true),
+        Command::MonitorsConfirm => monitors::confirm(),
+        Command::ProfileList => monitors::list_profiles(json),
+        Command::ProfileApply(name) => monitors::apply_profile(&name),
+        Command::ProfileSave(name) => monitors::save_profile(&name),
+        Command::ProfileDelete(name) => monitors::delete_profile(&name),
+        Command::KeyboardList => keyboard::list(json),
+        Command::KeyboardSaved => keyboard::saved(json),
+        Command::KeyboardSet(layouts) => keyboard::set(&layouts),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match parse_args(&args).and_then(run) {
+        Ok(output) => {
+            print!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(error @ CliError::Usage(_)) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            ExitCode::from(2)
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
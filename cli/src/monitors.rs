@@ -0,0 +1,171 @@
+// This file handles the monitors subcommands via the DBus interface of the monitors plugin
+use reset_common::monitors::{format_refresh_rate, Monitor};
+
+use crate::{
+    r#const::{MONITORS_INTERFACE, MONITORS_PATH},
+    utils::{format_table, read_input, with_proxy, CliError},
+};
+
+fn format_scale(scale: f64) -> String {
+    format!("{:.2}", scale)
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+pub fn monitors_table(monitors: &[Monitor]) -> String {
+    let rows: Vec<Vec<String>> = monitors
+        .iter()
+        .map(|monitor| {
+            let (width, height, refresh_rate) = if monitor.custom_mode.is_set() {
+                (
+                    monitor.custom_mode.width,
+                    monitor.custom_mode.height,
+                    format!("{}", monitor.custom_mode.refresh_rate),
+                )
+            } else {
+                (
+                    monitor.size.0,
+                    monitor.size.1,
+                    format_refresh_rate(monitor.refresh_rate),
+                )
+            };
+            vec![
+                monitor.name.clone(),
+                if monitor.enabled { "yes" } else { "no" }.into(),
+                format!("{}x{}@{}", width, height, refresh_rate),
+                format_scale(monitor.scale),
+                format!("{},{}", monitor.offset.0, monitor.offset.1),
+                monitor.transform.to_string(),
+                if monitor.primary { "yes" } else { "no" }.into(),
+                monitor.mirror.clone(),
+                format!("{} {}", monitor.make, monitor.model)
+                    .trim()
+                    .to_string(),
+            ]
+        })
+        .collect();
+    format_table(
+        &[
+            "name",
+            "enabled",
+            "mode",
+            "scale",
+            "position",
+            "transform",
+            "primary",
+            "mirror",
+            "model",
+        ],
+        &rows,
+    )
+}
+
+/// Parses monitors in the format of monitors list --json.
+pub fn parse_monitors(input: &str) -> Result<Vec<Monitor>, CliError> {
+    let monitors: Vec<Monitor> = serde_json::from_str(input)?;
+    if monitors.is_empty() {
+        return Err(CliError::Input(String::from("no monitors given")));
+    }
+    Ok(monitors)
+}
+
+pub fn get_monitors() -> Result<Vec<Monitor>, CliError> {
+    with_proxy(MONITORS_PATH, |proxy| {
+        let (monitors,): (Vec<Monitor>,) =
+            proxy.method_call(MONITORS_INTERFACE, "GetMonitors", ())?;
+        Ok(monitors)
+    })
+}
+
+pub fn list(json: bool) -> Result<String, CliError> {
+    let monitors = get_monitors()?;
+    if json {
+        return Ok(serde_json::to_string_pretty(&monitors)? + "\n");
+    }
+    Ok(monitors_table(&monitors))
+}
+
+/// Applies the monitors read from the path, SaveMonitors is used for persistent configurations.
+pub fn apply(path: &str, persistent: bool) -> Result<String, CliError> {
+    let monitors = parse_monitors(&read_input(path)?)?;
+    let method = if persistent {
+        "SaveMonitors"
+    } else {
+        "SetMonitors"
+    };
+    with_proxy(MONITORS_PATH, |proxy| {
+        proxy.method_call::<(), _, _, _>(MONITORS_INTERFACE, method, (monitors,))?;
+        Ok(String::new())
+    })
+}
+
+pub fn list_profiles(json: bool) -> Result<String, CliError> {
+    let profiles = with_proxy(MONITORS_PATH, |proxy| {
+        let (profiles,): (Vec<String>,) =
+            proxy.method_call(MONITORS_INTERFACE, "ListProfiles", ())?;
+        Ok(profiles)
+    })?;
+    if json {
+        return Ok(serde_json::to_string_pretty(&profiles)? + "\n");
+    }
+    let rows: Vec<Vec<String>> = profiles.into_iter().map(|name| vec![name]).collect();
+    Ok(format_table(&["profile"], &rows))
+}
+
+/// Calls a profile method and returns its result, see the individual methods for its meaning.
+fn call_profile_method<A: dbus::arg::AppendAll>(method: &str, args: A) -> Result<bool, CliError> {
+    let (result,): (bool,) = with_proxy(MONITORS_PATH, |proxy| {
+        Ok(proxy.method_call(MONITORS_INTERFACE, method, args)?)
+    })?;
+    Ok(result)
+}
+
+/// Applies the profile, it is reverted unless it is confirmed with monitors confirm in time.
+pub fn apply_profile(name: &str) -> Result<String, CliError> {
+    // 0 uses the timeout configured for the daemon
+    let (found, timeout): (bool, u32) = with_proxy(MONITORS_PATH, |proxy| {
+        Ok(proxy.method_call(MONITORS_INTERFACE, "ApplyProfile", (name, 0u32))?)
+    })?;
+    if !found {
+        return Err(CliError::NotFound(format!("profile {}", name)));
+    }
+    Ok(format!(
+        "Run reset-cli monitors confirm within {} seconds to keep the profile\n",
+        timeout
+    ))
+}
+
+/// Keeps the configuration applied by profile apply.
+pub fn confirm() -> Result<String, CliError> {
+    let (confirmed,): (bool,) = with_proxy(MONITORS_PATH, |proxy| {
+        Ok(proxy.method_call(MONITORS_INTERFACE, "ConfirmMonitors", ())?)
+    })?;
+    if !confirmed {
+        return Err(CliError::NotFound(String::from(
+            "configuration to confirm, it was already reverted",
+        )));
+    }
+    Ok(String::new())
+}
+
+pub fn save_profile(name: &str) -> Result<String, CliError> {
+    if name.trim().is_empty() {
+        return Err(CliError::Usage(String::from(
+            "profile names can't be empty",
+        )));
+    }
+    let monitors = get_monitors()?;
+    // failures are reported as DBus errors, older daemons return false instead
+    if !call_profile_method("SaveProfile", (name, monitors))? {
+        return Err(CliError::Failed(format!("could not save profile {}", name)));
+    }
+    Ok(String::new())
+}
+
+pub fn delete_profile(name: &str) -> Result<String, CliError> {
+    if !call_profile_method("DeleteProfile", (name,))? {
+        return Err(CliError::NotFound(format!("profile {}", name)));
+    }
+    Ok(String::new())
+}
@@ -0,0 +1,183 @@
+use dbus::Message;
+use reset_common::{
+    keyboard::KeyboardLayout,
+    monitors::{format_refresh_rate, AvailableMode, CustomMode, Monitor, Offset, Size},
+};
+
+use crate::{
+    keyboard::{find_layouts, layouts_table},
+    monitors::{monitors_table, parse_monitors},
+    parse_args,
+    utils::{format_table, CliError},
+    Command, Options,
+};
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+fn create_monitors() -> Vec<Monitor> {
+    let laptop = Monitor {
+        id: 0,
+        enabled: true,
+        name: "eDP-1".into(),
+        make: "BOE".into(),
+        model: "NE135FBM-N41".into(),
+        refresh_rate: 59999,
+        scale: 1.5,
+        primary: true,
+        size: Size(2256, 1504),
+        available_modes: vec![AvailableMode {
+            id: "0".into(),
+            size: Size(2256, 1504),
+            refresh_rates: vec![(59999, "0".into())],
+            supported_scales: vec![1.0, 1.5, 2.0],
+        }],
+        ..Default::default()
+    };
+    let external = Monitor {
+        id: 1,
+        enabled: true,
+        name: "DP-3".into(),
+        refresh_rate: 60000,
+        scale: 1.0,
+        transform: 1,
+        offset: Offset(1504, -600),
+        size: Size(2560, 1440),
+        custom_mode: CustomMode {
+            width: 2560,
+            height: 1440,
+            refresh_rate: 75.0,
+            modeline: "".into(),
+        },
+        ..Default::default()
+    };
+    vec![laptop, external]
+}
+
+#[test]
+fn parse_commands() {
+    assert_eq!(
+        parse_args(&args(&["monitors", "list", "--json"])).unwrap(),
+        Options {
+            json: true,
+            command: Command::MonitorsList
+        }
+    );
+    assert_eq!(
+        parse_args(&args(&["monitors", "apply", "-"]))
+            .unwrap()
+            .command,
+        Command::MonitorsApply("-".into())
+    );
+    assert_eq!(
+        parse_args(&args(&["monitors", "profile", "save", "desk"]))
+            .unwrap()
+            .command,
+        Command::ProfileSave("desk".into())
+    );
+    assert_eq!(
+        parse_args(&args(&["monitors", "confirm"])).unwrap().command,
+        Command::MonitorsConfirm
+    );
+    assert_eq!(
+        parse_args(&args(&["keyboard", "set", "us", "ch(de)"]))
+            .unwrap()
+            .command,
+        Command::KeyboardSet(vec!["us".into(), "ch(de)".into()])
+    );
+    assert_eq!(parse_args(&[]).unwrap().command, Command::Help);
+}
+
+#[test]
+fn parse_invalid_commands() {
+    for invalid in [
+        &["monitors"][..],
+        &["monitors", "apply"],
+        &["monitors", "profile", "apply", "a", "b"],
+        &["keyboard", "set"],
+        &["keyboard", "list", "--verbose"],
+    ] {
+        assert!(matches!(
+            parse_args(&args(invalid)),
+            Err(CliError::Usage(_))
+        ));
+    }
+}
+
+#[test]
+fn table_alignment() {
+    let table = format_table(
+        &["name", "mode"],
+        &[
+            vec!["eDP-1".into(), "2256x1504@60".into()],
+            vec!["HDMI-A-1".into(), "".into()],
+        ],
+    );
+    assert_eq!(table, "NAME      MODE\neDP-1     2256x1504@60\nHDMI-A-1\n");
+}
+
+#[test]
+fn monitors_output() {
+    let table = monitors_table(&create_monitors());
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("NAME"));
+    assert!(lines[1].contains("2256x1504@59.999") && lines[1].contains("BOE NE135FBM-N41"));
+    // custom modes are shown instead of the mode
+    assert!(lines[2].contains("2560x1440@75") && lines[2].contains("1504,-600"));
+    assert_eq!(format_refresh_rate(59940), "59.94");
+    assert_eq!(format_refresh_rate(60000), "60");
+}
+
+#[test]
+fn monitors_json_and_dbus() {
+    let monitors = create_monitors();
+    let json = serde_json::to_string(&monitors).unwrap();
+    let parsed = parse_monitors(&json).unwrap();
+    assert_eq!(parsed, monitors);
+    assert!(matches!(parse_monitors("[]"), Err(CliError::Input(_))));
+    assert!(matches!(parse_monitors("{"), Err(CliError::Input(_))));
+
+    // the same signature as the monitors plugin is used
+    let msg = Message::new_method_call(
+        "org.Xetibo.ReSet.Daemon",
+        "/org/Xetibo/ReSet/Plugins/Monitors",
+        "org.Xetibo.ReSet.Monitors",
+        "SetMonitors",
+    )
+    .unwrap()
+    .append1(parsed);
+    assert_eq!(
+        msg.iter_init().signature().to_string(),
+        "a(ub(ssss)(udu)bb(ii)(ii)sa(s(ii)a(us)ad)b(bbbbbbb)sus(iids)(bbud)((ii)(ii)udddasas))"
+    );
+    let read: Vec<Monitor> = msg.read1().unwrap();
+    assert_eq!(read, monitors);
+}
+
+#[test]
+fn keyboard_layouts() {
+    let available = vec![
+        KeyboardLayout {
+            description: "English (US)".into(),
+            name: "us".into(),
+            variant: None,
+        },
+        KeyboardLayout {
+            description: "German (Switzerland)".into(),
+            name: "ch".into(),
+            variant: Some("de".into()),
+        },
+    ];
+    let layouts = find_layouts(&available, &args(&["ch(de)", "us"])).unwrap();
+    assert_eq!(layouts, vec![available[1].clone(), available[0].clone()]);
+    assert!(matches!(
+        find_layouts(&available, &args(&["de"])),
+        Err(CliError::NotFound(_))
+    ));
+    assert_eq!(
+        layouts_table(&layouts),
+        "LAYOUT  DESCRIPTION\nch(de)  German (Switzerland)\nus      English (US)\n"
+    );
+}
@@ -0,0 +1,101 @@
+use std::{
+    fmt::Display,
+    fs,
+    io::{self, Read},
+    time::Duration,
+};
+
+use dbus::blocking::{Connection, Proxy};
+
+use crate::r#const::BASE;
+
+// applying a configuration may take a few seconds, e.g. with kscreen-doctor
+pub const DBUS_TIMEOUT: Duration = Duration::from_millis(5000);
+
+#[derive(Debug)]
+pub enum CliError {
+    // invalid arguments, the usage is shown as well
+    Usage(String),
+    // the daemon is not running or rejected the request
+    DBus(dbus::Error),
+    // the input could not be read or parsed
+    Input(String),
+    // a requested profile or layout does not exist
+    NotFound(String),
+    // the daemon could not complete the request
+    Failed(String),
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{}", message),
+            CliError::DBus(error) => write!(
+                f,
+                "{}",
+                error
+                    .message()
+                    .unwrap_or("Could not reach the ReSet daemon")
+            ),
+            CliError::Input(message) => write!(f, "Invalid input: {}", message),
+            CliError::NotFound(message) => write!(f, "Not found: {}", message),
+            CliError::Failed(message) => write!(f, "Failed: {}", message),
+        }
+    }
+}
+
+impl From<dbus::Error> for CliError {
+    fn from(error: dbus::Error) -> Self {
+        CliError::DBus(error)
+    }
+}
+
+impl From<serde_json::Error> for CliError {
+    fn from(error: serde_json::Error) -> Self {
+        CliError::Input(error.to_string())
+    }
+}
+
+pub fn with_proxy<T>(
+    path: &'static str,
+    call: impl FnOnce(&Proxy<&Connection>) -> Result<T, CliError>,
+) -> Result<T, CliError> {
+    let conn = Connection::new_session()?;
+    let proxy = conn.with_proxy(BASE, path, DBUS_TIMEOUT);
+    call(&proxy)
+}
+
+/// Reads the file at the path, - reads from stdin instead.
+pub fn read_input(path: &str) -> Result<String, CliError> {
+    if path == "-" {
+        let mut input = String::new();
+        io::stdin()
+            .read_to_string(&mut input)
+            .map_err(|error| CliError::Input(error.to_string()))?;
+        return Ok(input);
+    }
+    fs::read_to_string(path).map_err(|error| CliError::Input(format!("{}: {}", path, error)))
+}
+
+/// Formats the rows as table with left aligned columns, the header is written in upper case.
+pub fn format_table(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|val| val.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let header: Vec<String> = header.iter().map(|val| val.to_uppercase()).collect();
+    let mut table = String::new();
+    for row in std::iter::once(&header).chain(rows) {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<String>>()
+            .join("  ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
@@ -0,0 +1,176 @@
+// This file handles host commands like hyprctl, kscreen-doctor or kreadconfig6.
+// Commands are wrapped with flatpak-spawn inside of a flatpak, the runner can be replaced in
+// order to test the backends without the real desktop.
+use std::{
+    env,
+    io::{self, Read},
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "fixtures")]
+use std::sync::Mutex;
+
+pub const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandOutput {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+pub trait CommandRunner: Send + Sync {
+    /// Runs the program and waits for it to exit, stdout and stderr are captured.
+    /// Programs that are not installed result in an error of the kind NotFound.
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<CommandOutput>;
+}
+
+/// Runs commands on the host, commands that don't exit within the timeout are killed.
+#[derive(Debug)]
+pub struct HostCommandRunner {
+    pub timeout: Duration,
+}
+
+impl Default for HostCommandRunner {
+    fn default() -> Self {
+        Self {
+            timeout: COMMAND_TIMEOUT,
+        }
+    }
+}
+
+impl CommandRunner for HostCommandRunner {
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<CommandOutput> {
+        // flatpak sets the container variable inside of the sandbox
+        let mut command = if env::var_os("container").is_some() {
+            let mut command = Command::new("flatpak-spawn");
+            command.args(["--host", program]);
+            command
+        } else {
+            Command::new(program)
+        };
+        let mut child = command
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // pipes are read while waiting, otherwise commands with a lot of output never exit
+        let mut stdout = child.stdout.take().unwrap();
+        let mut stderr = child.stderr.take().unwrap();
+        let stdout = thread::spawn(move || {
+            let mut output = Vec::new();
+            let _ = stdout.read_to_end(&mut output);
+            output
+        });
+        let stderr = thread::spawn(move || {
+            let mut output = Vec::new();
+            let _ = stderr.read_to_end(&mut output);
+            output
+        });
+
+        let start = Instant::now();
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if start.elapsed() > self.timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("{} did not exit within {:?}", program, self.timeout),
+                ));
+            }
+            thread::sleep(POLL_INTERVAL);
+        };
+        Ok(CommandOutput {
+            success: status.success(),
+            stdout: String::from_utf8_lossy(&stdout.join().unwrap_or_default()).into(),
+            stderr: String::from_utf8_lossy(&stderr.join().unwrap_or_default()).into(),
+        })
+    }
+}
+
+/// Replays recorded outputs instead of running commands.
+/// Commands without a fixture behave like programs that are not installed.
+#[cfg(feature = "fixtures")]
+#[derive(Debug, Default)]
+pub struct FixtureCommandRunner {
+    fixtures: Vec<(String, Vec<String>, CommandOutput)>,
+    calls: Mutex<Vec<Vec<String>>>,
+}
+
+#[cfg(feature = "fixtures")]
+impl FixtureCommandRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_output(self, program: &str, args: &[&str], stdout: &str) -> Self {
+        self.with_fixture(
+            program,
+            args,
+            CommandOutput {
+                success: true,
+                stdout: stdout.into(),
+                stderr: "".into(),
+            },
+        )
+    }
+
+    pub fn with_failure(self, program: &str, args: &[&str], stderr: &str) -> Self {
+        self.with_fixture(
+            program,
+            args,
+            CommandOutput {
+                success: false,
+                stdout: "".into(),
+                stderr: stderr.into(),
+            },
+        )
+    }
+
+    pub fn with_fixture(mut self, program: &str, args: &[&str], output: CommandOutput) -> Self {
+        self.fixtures.push((
+            program.into(),
+            args.iter().map(|arg| arg.to_string()).collect(),
+            output,
+        ));
+        self
+    }
+
+    /// Returns the program and arguments of every command that was run.
+    pub fn calls(&self) -> Vec<Vec<String>> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+#[cfg(feature = "fixtures")]
+impl CommandRunner for FixtureCommandRunner {
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<CommandOutput> {
+        let mut call = vec![program.to_string()];
+        call.extend(args.iter().map(|arg| arg.to_string()));
+        self.calls.lock().unwrap().push(call);
+        self.fixtures
+            .iter()
+            .find(|(fixture_program, fixture_args, _)| {
+                fixture_program == program
+                    && fixture_args
+                        .iter()
+                        .map(String::as_str)
+                        .eq(args.iter().copied())
+            })
+            .map(|(_, _, output)| output.clone())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no fixture for {} {}", program, args.join(" ")),
+                )
+            })
+    }
+}
@@ -0,0 +1,4 @@
+// Code shared by the plugins and reset-cli
+pub mod command;
+pub mod keyboard;
+pub mod monitors;
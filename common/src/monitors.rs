@@ -0,0 +1,752 @@
+// Monitor types of the DBus interface of the monitors plugin, shared with reset-cli
+use dbus::{
+    arg::{self, Append, Arg, ArgType, Get},
+    Signature,
+};
+#[cfg(feature = "log")]
+use re_set_lib::ERROR;
+#[cfg(all(feature = "log", debug_assertions))]
+use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
+
+pub const INTERFACE: &str = "org.Xetibo.ReSet.Monitors";
+
+#[repr(C)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DragInformation {
+    pub drag_x: i32,
+    pub drag_y: i32,
+    pub border_offset_x: i32,
+    pub border_offset_y: i32,
+    pub origin_x: i32,
+    pub origin_y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub factor: i32,
+    pub drag_active: bool,
+    pub clicked: bool,
+    pub changed: bool,
+    pub prev_scale: f64,
+    pub drag_allowed: bool,
+    pub resolution_changed: bool,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Default, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MonitorFeatures {
+    pub vrr: bool,
+    pub primary: bool,
+    pub fractional_scaling: bool,
+    pub hdr: bool,
+    pub subpixel: bool,
+    pub max_render_time: bool,
+    pub custom_modes: bool,
+}
+
+impl<'a> Get<'a> for MonitorFeatures {
+    fn get(i: &mut arg::Iter<'a>) -> Option<Self> {
+        let (vrr, primary, fractional_scaling, hdr, subpixel, max_render_time, custom_modes) =
+            <(bool, bool, bool, bool, bool, bool, bool)>::get(i)?;
+        Some(Self {
+            vrr,
+            primary,
+            fractional_scaling,
+            hdr,
+            subpixel,
+            max_render_time,
+            custom_modes,
+        })
+    }
+}
+
+impl Append for MonitorFeatures {
+    fn append_by_ref(&self, iter: &mut arg::IterAppend) {
+        iter.append_struct(|i| {
+            i.append(self.vrr);
+            i.append(self.primary);
+            i.append(self.fractional_scaling);
+            i.append(self.hdr);
+            i.append(self.subpixel);
+            i.append(self.max_render_time);
+            i.append(self.custom_modes);
+        });
+    }
+}
+
+impl Arg for MonitorFeatures {
+    const ARG_TYPE: arg::ArgType = ArgType::Struct;
+    fn signature() -> Signature<'static> {
+        unsafe { Signature::from_slice_unchecked("(bbbbbbb)\0") }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+// layout documents written by older versions do not contain all values
+#[serde(default)]
+pub struct Monitor {
+    pub id: u32,
+    pub enabled: bool,
+    pub name: String,
+    pub make: String,
+    pub model: String,
+    pub serial: String,
+    // millihertz, e.g. 59940 for 59.94 Hz
+    pub refresh_rate: u32,
+    pub scale: f64,
+    pub transform: u32,
+    pub vrr: bool,
+    pub primary: bool,
+    pub offset: Offset,
+    pub size: Size,
+    // only used by the UI, hence not part of layout documents
+    #[serde(skip)]
+    pub drag_information: DragInformation,
+    pub mode: String,
+    pub available_modes: Vec<AvailableMode>,
+    pub uses_mode_id: bool,
+    pub features: MonitorFeatures,
+    // subpixel layout, e.g. rgb, bgr, vrgb, vbgr or none, empty when not supported
+    pub subpixel: String,
+    // milliseconds, 0 disables the max render time
+    pub max_render_time: u32,
+    // name of the monitor whose content is shown, empty when not mirroring
+    pub mirror: String,
+    // used instead of the mode when set
+    pub custom_mode: CustomMode,
+    // only used when the monitor supports hdr, see MonitorFeatures
+    pub hdr: HdrSettings,
+    // read from the EDID of the connector, empty when the EDID is not available
+    pub edid: EdidInfo,
+}
+
+impl Monitor {
+    // unlucky
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        id: u32,
+        enabled: bool,
+        name: impl Into<String>,
+        make: impl Into<String>,
+        model: impl Into<String>,
+        serial: impl Into<String>,
+        refresh_rate: u32,
+        scale: f64,
+        transform: u32,
+        vrr: bool,
+        primary: bool,
+        offset_x: i32,
+        offset_y: i32,
+        width: i32,
+        height: i32,
+        available_modes: Vec<AvailableMode>,
+        uses_mode_id: bool,
+        features: MonitorFeatures,
+    ) -> Self {
+        Self {
+            id,
+            enabled,
+            name: name.into(),
+            make: make.into(),
+            model: model.into(),
+            serial: serial.into(),
+            refresh_rate,
+            scale,
+            transform,
+            vrr,
+            primary,
+            offset: Offset(offset_x, offset_y),
+            size: Size(width, height),
+            mode: "".into(),
+            drag_information: DragInformation::default(),
+            available_modes,
+            uses_mode_id,
+            features,
+            subpixel: "".into(),
+            max_render_time: 0,
+            mirror: "".into(),
+            custom_mode: CustomMode::default(),
+            hdr: HdrSettings::default(),
+            edid: EdidInfo::default(),
+        }
+    }
+
+    /// Returns the custom mode if one is set and valid, invalid custom modes are ignored in order
+    /// to not break the configuration of the monitor.
+    pub fn valid_custom_mode(&self) -> Option<&CustomMode> {
+        if !self.custom_mode.is_set() {
+            return None;
+        }
+        if self.custom_mode.validate().is_err() {
+            return None;
+        }
+        Some(&self.custom_mode)
+    }
+
+    pub fn handle_transform(&self) -> (i32, i32) {
+        match self.transform {
+            0 => (self.size.0, self.size.1),
+            1 => (self.size.1, self.size.0),
+            2 => (self.size.0, self.size.1),
+            3 => (self.size.1, self.size.0),
+            4 => (self.size.0, self.size.1),
+            5 => (self.size.1, self.size.0),
+            6 => (self.size.0, self.size.1),
+            7 => (self.size.1, self.size.0),
+            // unsupported transforms are treated as normal
+            _ => {
+                #[cfg(feature = "log")]
+                ERROR!("Received unsupported transform", ErrorLevel::Recoverable);
+                (self.size.0, self.size.1)
+            }
+        }
+    }
+
+    pub fn handle_scaled_transform(&self) -> (i32, i32) {
+        let (width, height) = self.handle_transform();
+        let (scaled_width, scaled_height) = if self.scale <= 0.0 {
+            (width as f64, height as f64)
+        } else {
+            (
+                (width as f64 / self.scale).round(),
+                (height as f64 / self.scale).round(),
+            )
+        };
+        (scaled_width as i32, scaled_height as i32)
+    }
+}
+
+impl Append for Monitor {
+    fn append_by_ref(&self, iter: &mut arg::IterAppend) {
+        iter.append_struct(|i| {
+            i.append(self.id);
+            i.append(self.enabled);
+            i.append((
+                self.name.clone(),
+                self.make.clone(),
+                self.model.clone(),
+                self.serial.clone(),
+            ));
+            i.append((self.refresh_rate, self.scale, self.transform));
+            i.append(self.vrr);
+            i.append(self.primary);
+            i.append(self.offset);
+            i.append(self.size);
+            i.append(self.mode.clone());
+            i.append(self.available_modes.clone());
+            i.append(self.uses_mode_id);
+            i.append(self.features);
+            i.append(self.subpixel.clone());
+            i.append(self.max_render_time);
+            i.append(self.mirror.clone());
+            i.append(self.custom_mode.clone());
+            i.append(self.hdr);
+            i.append(self.edid.clone());
+        });
+    }
+}
+
+// tuples are limited to 12 elements, hence the struct is read field by field
+impl<'a> Get<'a> for Monitor {
+    fn get(i: &mut arg::Iter<'a>) -> Option<Self> {
+        let mut i = i.recurse(ArgType::Struct)?;
+        let id = i.read().ok()?;
+        let enabled = i.read().ok()?;
+        let (name, make, model, serial): (String, String, String, String) = i.read().ok()?;
+        let (refresh_rate, scale, transform): (u32, f64, u32) = i.read().ok()?;
+        let vrr = i.read().ok()?;
+        let primary = i.read().ok()?;
+        let offset = i.read().ok()?;
+        let size = i.read().ok()?;
+        let mode = i.read().ok()?;
+        let available_modes = i.read().ok()?;
+        let uses_mode_id = i.read().ok()?;
+        let features = i.read().ok()?;
+        let subpixel = i.read().ok()?;
+        let max_render_time = i.read().ok()?;
+        let mirror = i.read().ok()?;
+        let custom_mode = i.read().ok()?;
+        let hdr = i.read().ok()?;
+        let edid = i.read().ok()?;
+        Some(Self {
+            id,
+            enabled,
+            name,
+            make,
+            model,
+            serial,
+            refresh_rate,
+            scale,
+            transform,
+            vrr,
+            primary,
+            offset,
+            size,
+            mode,
+            drag_information: DragInformation::default(),
+            available_modes,
+            uses_mode_id,
+            features,
+            subpixel,
+            max_render_time,
+            mirror,
+            custom_mode,
+            hdr,
+            edid,
+        })
+    }
+}
+
+impl Arg for Monitor {
+    const ARG_TYPE: arg::ArgType = ArgType::Struct;
+    fn signature() -> Signature<'static> {
+        unsafe {
+            Signature::from_slice_unchecked(
+                "(ub(ssss)(udu)bb(ii)(ii)sa(s(ii)a(us)ad)b(bbbbbbb)sus(iids)(bbud)((ii)(ii)udddasas))\0",
+            )
+        }
+    }
+}
+
+impl Monitor {
+    /// These coordinates are calculated from the edge of the drawing box. Ensure the rest of the
+    /// window is also taken into account when passing parameters as it will otherwise evaluate to
+    /// false.
+    pub fn is_coordinate_within(&self, x: i32, y: i32) -> bool {
+        let offset_x = self.offset.0 / self.drag_information.factor;
+        let offset_y = self.offset.1 / self.drag_information.factor;
+        let height = self.drag_information.height / self.drag_information.factor;
+        let width = self.drag_information.width / self.drag_information.factor;
+        x >= self.drag_information.border_offset_x + offset_x
+            && x <= self.drag_information.border_offset_x + offset_x + width
+            && y >= self.drag_information.border_offset_y + offset_y
+            && y <= self.drag_information.border_offset_y + offset_y + height
+    }
+
+    /// Checks whether or not the currently dragged monitor has any overlap with existing monitors.
+    /// If this is the case, then the monitor should be reset to original position on drop.
+    pub fn intersect_horizontal(&self, offset_x: i32, other_width: i32) -> bool {
+        let (width, _) = self.handle_scaled_transform();
+        // current monitor left side is right of other right
+        let left = self.drag_information.border_offset_x + self.offset.0 >= offset_x + other_width;
+        // current monitor right is left of other left
+        let right = self.drag_information.border_offset_x + self.offset.0 + width <= offset_x;
+        !left && !right
+    }
+
+    pub fn intersect_vertical(&self, offset_y: i32, other_height: i32) -> bool {
+        let (_, height) = self.handle_scaled_transform();
+        // current monitor bottom is higher than other top
+        let bottom =
+            self.drag_information.border_offset_y + self.offset.1 >= offset_y + other_height;
+        // current monitor top is lower than other bottom
+        let top = self.drag_information.border_offset_y + self.offset.1 + height <= offset_y;
+        !bottom && !top
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Offset(pub i32, pub i32);
+
+impl<'a> Get<'a> for Offset {
+    fn get(i: &mut arg::Iter<'a>) -> Option<Self> {
+        let (x, y) = <(i32, i32)>::get(i)?;
+        Some(Self(x, y))
+    }
+}
+
+impl Append for Offset {
+    fn append_by_ref(&self, iter: &mut arg::IterAppend) {
+        iter.append_struct(|i| {
+            i.append(self.0);
+            i.append(self.1);
+        });
+    }
+}
+
+impl Arg for Offset {
+    const ARG_TYPE: arg::ArgType = ArgType::Struct;
+    fn signature() -> Signature<'static> {
+        unsafe { Signature::from_slice_unchecked("(ii)\0") }
+    }
+}
+
+impl Offset {
+    pub fn from_scale(x: i32, y: i32, scale: f64) -> Offset {
+        let scaled_x = x as f64 / scale;
+        let scaled_y = y as f64 / scale;
+        Offset(scaled_x as i32, scaled_y as i32)
+    }
+}
+
+#[repr(C)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct Size(pub i32, pub i32);
+
+impl<'a> Get<'a> for Size {
+    fn get(i: &mut arg::Iter<'a>) -> Option<Self> {
+        let (width, height) = <(i32, i32)>::get(i)?;
+        Some(Self(width, height))
+    }
+}
+
+impl Append for Size {
+    fn append_by_ref(&self, iter: &mut arg::IterAppend) {
+        iter.append_struct(|i| {
+            i.append(self.0);
+            i.append(self.1);
+        });
+    }
+}
+
+impl Arg for Size {
+    const ARG_TYPE: arg::ArgType = ArgType::Struct;
+    fn signature() -> Signature<'static> {
+        unsafe { Signature::from_slice_unchecked("(ii)\0") }
+    }
+}
+
+/// Converts a refresh rate in Hz to millihertz, which is used for all refresh rates of ReSet.
+pub fn to_millihertz(refresh_rate: f64) -> u32 {
+    (refresh_rate * 1000.0).round() as u32
+}
+
+/// Formats a refresh rate in millihertz as Hz without trailing zeros, e.g. 59.94 or 60.
+pub fn format_refresh_rate(refresh_rate: u32) -> String {
+    let formatted = format!("{}.{:03}", refresh_rate / 1000, refresh_rate % 1000);
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AvailableMode {
+    pub id: String,
+    pub size: Size,
+    // millihertz and the id of the mode for this refresh rate, if the backend uses ids
+    pub refresh_rates: Vec<(u32, String)>,
+    pub supported_scales: Vec<f64>,
+}
+
+impl<'a> Get<'a> for AvailableMode {
+    fn get(i: &mut arg::Iter<'a>) -> Option<Self> {
+        let (id, size, refresh_rates, supported_scales) =
+            <(String, Size, Vec<(u32, String)>, Vec<f64>)>::get(i)?;
+        Some(Self {
+            id,
+            size,
+            refresh_rates,
+            supported_scales,
+        })
+    }
+}
+
+impl Append for AvailableMode {
+    fn append_by_ref(&self, iter: &mut arg::IterAppend) {
+        iter.append_struct(|i| {
+            i.append(self.id.clone());
+            i.append(self.size);
+            let sig = unsafe { Signature::from_slice_unchecked("(us)\0") };
+            i.append_array(&sig, |i| {
+                for refresh_rate in self.refresh_rates.iter() {
+                    i.append(refresh_rate);
+                }
+            });
+            let sig = unsafe { Signature::from_slice_unchecked("d\0") };
+            i.append_array(&sig, |i| {
+                for scale in self.supported_scales.iter() {
+                    i.append(scale);
+                }
+            });
+        });
+    }
+}
+
+impl Arg for AvailableMode {
+    const ARG_TYPE: arg::ArgType = ArgType::Struct;
+    fn signature() -> Signature<'static> {
+        unsafe { Signature::from_slice_unchecked("(s(ii)a(us)ad)\0") }
+    }
+}
+
+pub const MAX_MODE_SIZE: i32 = 16384;
+pub const MAX_REFRESH_RATE: f64 = 1000.0;
+
+/// A mode which is not reported by the monitor, the default value means that no custom mode is
+/// used. The optional modeline uses the xorg format without keyword and name:
+/// clock hdisplay hsync_start hsync_end htotal vdisplay vsync_start vsync_end vtotal flags
+#[repr(C)]
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CustomMode {
+    pub width: i32,
+    pub height: i32,
+    // in Hz, custom modes are not limited to whole numbers
+    pub refresh_rate: f64,
+    pub modeline: String,
+}
+
+impl CustomMode {
+    pub fn is_set(&self) -> bool {
+        *self != CustomMode::default()
+    }
+
+    pub fn refresh_millihertz(&self) -> i32 {
+        (self.refresh_rate * 1000.0).round() as i32
+    }
+
+    /// Creates a custom mode from an xorg modeline, the keyword and the name are optional.
+    /// The refresh rate is calculated from the pixel clock and the total size.
+    pub fn from_modeline(modeline: &str) -> Result<Self, String> {
+        let mut tokens: Vec<&str> = modeline.split_whitespace().collect();
+        if tokens
+            .first()
+            .is_some_and(|val| val.eq_ignore_ascii_case("modeline"))
+        {
+            tokens.remove(0);
+        }
+        if tokens.first().is_some_and(|val| val.starts_with('"')) {
+            // names may contain spaces
+            let end = tokens
+                .iter()
+                .enumerate()
+                .position(|(index, val)| val.ends_with('"') && (index > 0 || val.len() > 1));
+            if end.is_none() {
+                return Err(String::from("the name of the modeline is not terminated"));
+            }
+            tokens.drain(..=end.unwrap());
+        }
+        if tokens.len() < 9 {
+            return Err(String::from(
+                "a modeline requires the clock, 4 horizontal and 4 vertical values",
+            ));
+        }
+        let clock: f64 = tokens[0]
+            .parse()
+            .map_err(|_| format!("invalid pixel clock {}", tokens[0]))?;
+        let mut timings = [0; 8];
+        for (index, timing) in timings.iter_mut().enumerate() {
+            *timing = tokens[index + 1]
+                .parse()
+                .map_err(|_| format!("invalid timing {}", tokens[index + 1]))?;
+        }
+        for flag in tokens[9..].iter() {
+            if !MODELINE_FLAGS.contains(&flag.to_lowercase().as_str()) {
+                return Err(format!("unknown modeline flag {}", flag));
+            }
+        }
+        let [hdisplay, hsync_start, hsync_end, htotal, vdisplay, vsync_start, vsync_end, vtotal] =
+            timings;
+        if clock <= 0.0 {
+            return Err(String::from("the pixel clock has to be positive"));
+        }
+        if hdisplay <= 0 || hdisplay > hsync_start || hsync_start > hsync_end || hsync_end > htotal
+        {
+            return Err(String::from("horizontal timings have to be ascending"));
+        }
+        if vdisplay <= 0 || vdisplay > vsync_start || vsync_start > vsync_end || vsync_end > vtotal
+        {
+            return Err(String::from("vertical timings have to be ascending"));
+        }
+        let refresh_rate = clock * 1_000_000.0 / (htotal as f64 * vtotal as f64);
+        Ok(Self {
+            width: hdisplay,
+            height: vdisplay,
+            refresh_rate: (refresh_rate * 1000.0).round() / 1000.0,
+            modeline: tokens.join(" "),
+        })
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.width <= 0 || self.width > MAX_MODE_SIZE {
+            return Err(format!("width has to be between 1 and {}", MAX_MODE_SIZE));
+        }
+        if self.height <= 0 || self.height > MAX_MODE_SIZE {
+            return Err(format!("height has to be between 1 and {}", MAX_MODE_SIZE));
+        }
+        if self.refresh_rate < 1.0 || self.refresh_rate > MAX_REFRESH_RATE {
+            return Err(format!(
+                "refresh rate has to be between 1 and {}",
+                MAX_REFRESH_RATE
+            ));
+        }
+        if self.modeline.is_empty() {
+            return Ok(());
+        }
+        let modeline = CustomMode::from_modeline(&self.modeline)?;
+        if modeline.width != self.width
+            || modeline.height != self.height
+            || (modeline.refresh_rate - self.refresh_rate).abs() > 0.01
+        {
+            return Err(String::from("the modeline does not match the custom mode"));
+        }
+        Ok(())
+    }
+}
+
+const MODELINE_FLAGS: [&str; 7] = [
+    "+hsync",
+    "-hsync",
+    "+vsync",
+    "-vsync",
+    "interlace",
+    "doublescan",
+    "csync",
+];
+
+impl<'a> Get<'a> for CustomMode {
+    fn get(i: &mut arg::Iter<'a>) -> Option<Self> {
+        let (width, height, refresh_rate, modeline) = <(i32, i32, f64, String)>::get(i)?;
+        Some(Self {
+            width,
+            height,
+            refresh_rate,
+            modeline,
+        })
+    }
+}
+
+impl Append for CustomMode {
+    fn append_by_ref(&self, iter: &mut arg::IterAppend) {
+        iter.append_struct(|i| {
+            i.append(self.width);
+            i.append(self.height);
+            i.append(self.refresh_rate);
+            i.append(self.modeline.clone());
+        });
+    }
+}
+
+impl Arg for CustomMode {
+    const ARG_TYPE: arg::ArgType = ArgType::Struct;
+    fn signature() -> Signature<'static> {
+        unsafe { Signature::from_slice_unchecked("(iids)\0") }
+    }
+}
+
+/// HDR and colour settings of a monitor.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HdrSettings {
+    pub enabled: bool,
+    pub wide_color_gamut: bool,
+    // nits, brightness of SDR content while HDR is enabled
+    pub sdr_brightness: u32,
+    // 0.0 shows SDR content in sRGB, 1.0 uses the full gamut of the monitor
+    pub sdr_gamut_wideness: f64,
+}
+
+pub const MIN_SDR_BRIGHTNESS: u32 = 50;
+pub const MAX_SDR_BRIGHTNESS: u32 = 1000;
+pub const DEFAULT_SDR_BRIGHTNESS: u32 = 200;
+
+impl<'a> Get<'a> for HdrSettings {
+    fn get(i: &mut arg::Iter<'a>) -> Option<Self> {
+        let (enabled, wide_color_gamut, sdr_brightness, sdr_gamut_wideness) =
+            <(bool, bool, u32, f64)>::get(i)?;
+        Some(Self {
+            enabled,
+            wide_color_gamut,
+            sdr_brightness,
+            sdr_gamut_wideness,
+        })
+    }
+}
+
+impl Append for HdrSettings {
+    fn append_by_ref(&self, iter: &mut arg::IterAppend) {
+        iter.append_struct(|i| {
+            i.append(self.enabled);
+            i.append(self.wide_color_gamut);
+            i.append(self.sdr_brightness);
+            i.append(self.sdr_gamut_wideness);
+        });
+    }
+}
+
+impl Arg for HdrSettings {
+    const ARG_TYPE: arg::ArgType = ArgType::Struct;
+    fn signature() -> Signature<'static> {
+        unsafe { Signature::from_slice_unchecked("(bbud)\0") }
+    }
+}
+
+/// Physical properties of a monitor as reported by its EDID.
+#[repr(C)]
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct EdidInfo {
+    // millimeters, 0 when unknown, e.g. for projectors
+    pub physical_size: Size,
+    // preferred mode of the monitor
+    pub native_size: Size,
+    // millihertz
+    pub native_refresh_rate: u32,
+    // cd/m², 0 when the monitor does not report HDR static metadata
+    pub max_luminance: f64,
+    pub max_frame_average_luminance: f64,
+    pub min_luminance: f64,
+    // supported transfer functions, e.g. SDR, PQ or HLG
+    pub eotfs: Vec<String>,
+    // supported colour spaces besides sRGB, e.g. BT2020RGB or DCI-P3
+    pub color_spaces: Vec<String>,
+}
+
+impl<'a> Get<'a> for EdidInfo {
+    fn get(i: &mut arg::Iter<'a>) -> Option<Self> {
+        let (
+            physical_size,
+            native_size,
+            native_refresh_rate,
+            max_luminance,
+            max_frame_average_luminance,
+            min_luminance,
+            eotfs,
+            color_spaces,
+        ) = <(Size, Size, u32, f64, f64, f64, Vec<String>, Vec<String>)>::get(i)?;
+        Some(Self {
+            physical_size,
+            native_size,
+            native_refresh_rate,
+            max_luminance,
+            max_frame_average_luminance,
+            min_luminance,
+            eotfs,
+            color_spaces,
+        })
+    }
+}
+
+impl Append for EdidInfo {
+    fn append_by_ref(&self, iter: &mut arg::IterAppend) {
+        iter.append_struct(|i| {
+            i.append(self.physical_size);
+            i.append(self.native_size);
+            i.append(self.native_refresh_rate);
+            i.append(self.max_luminance);
+            i.append(self.max_frame_average_luminance);
+            i.append(self.min_luminance);
+            i.append(self.eotfs.clone());
+            i.append(self.color_spaces.clone());
+        });
+    }
+}
+
+impl Arg for EdidInfo {
+    const ARG_TYPE: arg::ArgType = ArgType::Struct;
+    fn signature() -> Signature<'static> {
+        unsafe { Signature::from_slice_unchecked("((ii)(ii)udddasas)\0") }
+    }
+}
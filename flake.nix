@@ -54,6 +54,7 @@
             {
               monitor = pkgs.callPackage ./nix/monitor.nix { inherit inputs; };
               keyboard = pkgs.callPackage ./nix/keyboard.nix { inherit inputs; };
+              cli = pkgs.callPackage ./nix/cli.nix { inherit inputs; };
             };
         };
     };
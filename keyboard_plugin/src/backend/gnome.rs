@@ -1,34 +1,30 @@
-use std::process::Command;
-
-use glib::{getenv, Variant, VariantTy};
+use glib::{Variant, VariantTy};
 use re_set_lib::ERROR;
 #[cfg(debug_assertions)]
 use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
 
 use crate::keyboard_layout::KeyboardLayout;
 
+use reset_common::command::{CommandRunner, HostCommandRunner};
+
 pub fn get_saved_layouts_gnome(all_keyboards: &[KeyboardLayout]) -> Vec<KeyboardLayout> {
+    get_saved_layouts_gnome_with(&HostCommandRunner::default(), all_keyboards)
+}
+
+pub fn get_saved_layouts_gnome_with(
+    runner: &dyn CommandRunner,
+    all_keyboards: &[KeyboardLayout],
+) -> Vec<KeyboardLayout> {
     let mut kb = vec![];
 
-    let result = if getenv("container").is_none() {
-        Command::new("dconf")
-            .args(["read", "/org/gnome/desktop/input-sources/sources"])
-            .output()
-    } else {
-        Command::new("flatpak-spawn")
-            .args([
-                "--host",
-                "dconf",
-                "read",
-                "/org/gnome/desktop/input-sources/sources",
-            ])
-            .output()
-    };
+    let result = runner.run(
+        "dconf",
+        &["read", "/org/gnome/desktop/input-sources/sources"],
+    );
     if result.is_err() {
         return kb;
     }
-    let output = result.unwrap();
-    let layout_variant = String::from_utf8(output.stdout).unwrap();
+    let layout_variant = result.unwrap().stdout;
 
     let layout_variant = Variant::parse(Some(VariantTy::new("a(ss)").unwrap()), &layout_variant);
     if layout_variant.is_err() {
@@ -73,29 +69,20 @@ pub fn write_to_config_gnome(layouts: &Vec<KeyboardLayout>) {
     all_layouts.insert(0, '[');
     all_layouts.push(']');
 
-    if getenv("container").is_none() {
-        let result = Command::new("dconf")
-            .args([
-                "write",
-                "/org/gnome/desktop/input-sources/sources",
-                all_layouts.as_str(),
-            ])
-            .output();
-        if result.is_err() {
-            ERROR!("Failed to write layouts", ErrorLevel::PartialBreakage);
-        }
-    } else {
-        let result = Command::new("flatpak-spawn")
-            .args([
-                "--host",
-                "dconf",
-                "write",
-                "/org/gnome/desktop/input-sources/sources",
-                all_layouts.as_str(),
-            ])
-            .output();
-        if result.is_err() {
-            ERROR!("Failed to write layouts", ErrorLevel::PartialBreakage);
-        }
+    let result = HostCommandRunner::default().run(
+        "dconf",
+        &[
+            "write",
+            "/org/gnome/desktop/input-sources/sources",
+            all_layouts.as_str(),
+        ],
+    );
+    match result {
+        Ok(output) if output.success => (),
+        Ok(_output) => ERROR!(
+            format!("Failed to write layouts: {}", _output.stderr.trim()),
+            ErrorLevel::PartialBreakage
+        ),
+        Err(_) => ERROR!("Failed to write layouts", ErrorLevel::PartialBreakage),
     }
 }
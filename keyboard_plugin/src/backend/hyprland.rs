@@ -1,7 +1,6 @@
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::path::PathBuf;
-use std::process::Command;
 
 use re_set_lib::utils::config::CONFIG;
 use re_set_lib::ERROR;
@@ -12,11 +11,17 @@ use crate::keyboard_layout::KeyboardLayout;
 use crate::r#const::HYPRLAND_DEFAULT_FILE;
 use crate::utils::{get_default_path, parse_setting};
 
+use reset_common::command::{CommandRunner, HostCommandRunner};
+
 pub fn get_saved_layouts_hyprland(all_keyboards: &[KeyboardLayout]) -> Vec<KeyboardLayout> {
-    let kb_layout = Command::new("hyprctl")
-        .arg("getoption")
-        .arg("input:kb_layout")
-        .output();
+    get_saved_layouts_hyprland_with(&HostCommandRunner::default(), all_keyboards)
+}
+
+pub fn get_saved_layouts_hyprland_with(
+    runner: &dyn CommandRunner,
+    all_keyboards: &[KeyboardLayout],
+) -> Vec<KeyboardLayout> {
+    let kb_layout = runner.run("hyprctl", &["getoption", "input:kb_layout"]);
     if kb_layout.is_err() {
         ERROR!(
             "Failed to fetch keyboard layouts",
@@ -24,12 +29,9 @@ pub fn get_saved_layouts_hyprland(all_keyboards: &[KeyboardLayout]) -> Vec<Keybo
         );
         return vec![];
     }
-    let kb_layout = parse_setting(kb_layout.unwrap());
+    let kb_layout = parse_setting(&kb_layout.unwrap().stdout);
 
-    let kb_variant = Command::new("hyprctl")
-        .arg("getoption")
-        .arg("input:kb_variant")
-        .output();
+    let kb_variant = runner.run("hyprctl", &["getoption", "input:kb_variant"]);
     if kb_variant.is_err() {
         ERROR!(
             "Failed to fetch keyboard variants",
@@ -37,7 +39,7 @@ pub fn get_saved_layouts_hyprland(all_keyboards: &[KeyboardLayout]) -> Vec<Keybo
         );
         return vec![];
     }
-    let mut kb_variant = parse_setting(kb_variant.unwrap());
+    let mut kb_variant = parse_setting(&kb_variant.unwrap().stdout);
     kb_variant.resize(kb_layout.len(), String::new());
 
     let mut kb = vec![];
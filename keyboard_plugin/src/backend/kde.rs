@@ -7,30 +7,36 @@ use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
 
 use crate::keyboard_layout::KeyboardLayout;
 use crate::utils::parse_setting;
-use std::process::Command;
+
+use reset_common::command::{CommandRunner, HostCommandRunner};
 
 pub fn get_saved_layouts_kde(all_keyboards: &[KeyboardLayout]) -> Vec<KeyboardLayout> {
-    let output = Command::new("kreadconfig6")
-        .arg("--file")
-        .arg("kxkbrc")
-        .arg("--group")
-        .arg("Layout")
-        .arg("--key")
-        .arg("LayoutList")
-        .output()
-        .expect("Failed to get saved layouts");
-    let kb_layout = parse_setting(output);
+    get_saved_layouts_kde_with(&HostCommandRunner::default(), all_keyboards)
+}
 
-    let output = Command::new("kreadconfig6")
-        .arg("--file")
-        .arg("kxkbrc")
-        .arg("--group")
-        .arg("Layout")
-        .arg("--key")
-        .arg("VariantList")
-        .output()
-        .expect("Failed to get saved layouts");
-    let kb_variant = parse_setting(output);
+pub fn get_saved_layouts_kde_with(
+    runner: &dyn CommandRunner,
+    all_keyboards: &[KeyboardLayout],
+) -> Vec<KeyboardLayout> {
+    let output = runner.run("kreadconfig6", &read_layout_args("LayoutList"));
+    if let Err(_error) = output {
+        ERROR!(
+            format!("Failed to get saved layouts: {}", _error),
+            ErrorLevel::PartialBreakage
+        );
+        return vec![];
+    }
+    let kb_layout = parse_setting(&output.unwrap().stdout);
+
+    let output = runner.run("kreadconfig6", &read_layout_args("VariantList"));
+    if let Err(_error) = output {
+        ERROR!(
+            format!("Failed to get saved variants: {}", _error),
+            ErrorLevel::PartialBreakage
+        );
+        return vec![];
+    }
+    let kb_variant = parse_setting(&output.unwrap().stdout);
 
     let mut kb = vec![];
     for (layout, variant) in kb_layout.into_iter().zip(kb_variant.into_iter()) {
@@ -47,6 +53,10 @@ pub fn get_saved_layouts_kde(all_keyboards: &[KeyboardLayout]) -> Vec<KeyboardLa
     kb
 }
 
+fn read_layout_args(key: &str) -> [&str; 6] {
+    ["--file", "kxkbrc", "--group", "Layout", "--key", key]
+}
+
 pub fn write_to_config_kde(layouts: &[KeyboardLayout]) {
     let mut layout_string = String::new();
     let mut variant_string = String::new();
@@ -59,26 +69,52 @@ pub fn write_to_config_kde(layouts: &[KeyboardLayout]) {
         variant_string += ", ";
     }
 
-    Command::new("kwriteconfig6")
-        .arg("--file")
-        .arg("kxkbrc")
-        .arg("--group")
-        .arg("Layout")
-        .arg("--key")
-        .arg("LayoutList")
-        .arg(layout_string)
-        .output()
-        .expect("Could not save layouts");
-    Command::new("kwriteconfig6")
-        .arg("--file")
-        .arg("kxkbrc")
-        .arg("--group")
-        .arg("Layout")
-        .arg("--key")
-        .arg("VariantList")
-        .arg(variant_string)
-        .output()
-        .expect("Could not save variants");
+    let runner = HostCommandRunner::default();
+    let output = runner.run(
+        "kwriteconfig6",
+        &[
+            "--file",
+            "kxkbrc",
+            "--group",
+            "Layout",
+            "--key",
+            "LayoutList",
+            &layout_string,
+        ],
+    );
+    if let Err(_error) = output {
+        ERROR!(
+            format!("Could not save layouts: {}", _error),
+            ErrorLevel::PartialBreakage
+        );
+        return;
+    }
+    let output = output.unwrap();
+    if !output.success {
+        ERROR!(
+            format!("Could not save layouts: {}", output.stderr.trim()),
+            ErrorLevel::PartialBreakage
+        );
+    }
+    let output = runner.run(
+        "kwriteconfig6",
+        &[
+            "--file",
+            "kxkbrc",
+            "--group",
+            "Layout",
+            "--key",
+            "VariantList",
+            &variant_string,
+        ],
+    );
+    if let Err(_error) = output {
+        ERROR!(
+            format!("Could not save variants: {}", _error),
+            ErrorLevel::PartialBreakage
+        );
+        return;
+    }
     let conn = Connection::new_session().unwrap();
     let res =
         conn.send(Message::new_signal("/Layouts", "org.kde.keyboard", "reloadConfig").unwrap());
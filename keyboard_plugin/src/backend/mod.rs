@@ -16,9 +16,9 @@ use crate::keyboard_layout::KeyboardLayout;
 use crate::r#const::{GNOME, HYPRLAND, INTERFACE, KDE};
 use crate::utils::get_environment;
 
-mod gnome;
-mod hyprland;
-mod kde;
+pub mod gnome;
+pub mod hyprland;
+pub mod kde;
 
 #[no_mangle]
 #[allow(improper_ctypes_definitions)]
@@ -5,7 +5,16 @@ use glib::{
 };
 use gtk::{prelude::WidgetExt, ListBox};
 use re_set_lib::utils::plugin::PluginTestError;
+#[cfg(test)]
+use reset_common::command::FixtureCommandRunner;
 
+#[cfg(test)]
+use crate::backend::{
+    gnome::get_saved_layouts_gnome_with, hyprland::get_saved_layouts_hyprland_with,
+    kde::get_saved_layouts_kde_with,
+};
+#[cfg(test)]
+use crate::keyboard_layout::KeyboardLayout;
 use crate::{backend::get_saved_layouts, frontend_data};
 
 #[test]
@@ -57,3 +66,121 @@ pub fn test_can_get_layouts() -> Result<(), PluginTestError> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+fn create_keyboard_layouts() -> Vec<KeyboardLayout> {
+    let layout = |name: &str, variant: Option<&str>| KeyboardLayout {
+        description: format!("{} {}", name, variant.unwrap_or_default()),
+        name: name.into(),
+        variant: variant.map(String::from),
+    };
+    vec![
+        layout("us", None),
+        layout("ch", None),
+        layout("ch", Some("fr")),
+        layout("de", Some("nodeadkeys")),
+    ]
+}
+
+#[cfg(test)]
+fn layout_names(layouts: &[KeyboardLayout]) -> Vec<(String, Option<String>)> {
+    layouts
+        .iter()
+        .map(|layout| (layout.name.clone(), layout.variant.clone()))
+        .collect()
+}
+
+#[test]
+fn test_recorded_kde_layouts() {
+    let runner = FixtureCommandRunner::new()
+        .with_output(
+            "kreadconfig6",
+            &[
+                "--file",
+                "kxkbrc",
+                "--group",
+                "Layout",
+                "--key",
+                "LayoutList",
+            ],
+            "us,ch,de\n",
+        )
+        .with_output(
+            "kreadconfig6",
+            &[
+                "--file",
+                "kxkbrc",
+                "--group",
+                "Layout",
+                "--key",
+                "VariantList",
+            ],
+            ",fr,nodeadkeys\n",
+        );
+    let layouts = get_saved_layouts_kde_with(&runner, &create_keyboard_layouts());
+    assert_eq!(
+        layout_names(&layouts),
+        vec![
+            ("us".into(), None),
+            ("ch".into(), Some("fr".into())),
+            ("de".into(), Some("nodeadkeys".into())),
+        ]
+    );
+    assert_eq!(runner.calls().len(), 2);
+}
+
+#[test]
+fn test_missing_kde_tools() {
+    // kreadconfig6 is not installed
+    let runner = FixtureCommandRunner::new();
+    assert!(get_saved_layouts_kde_with(&runner, &create_keyboard_layouts()).is_empty());
+    assert_eq!(runner.calls().len(), 1);
+}
+
+#[test]
+fn test_recorded_gnome_layouts() {
+    let runner = FixtureCommandRunner::new().with_output(
+        "dconf",
+        &["read", "/org/gnome/desktop/input-sources/sources"],
+        "[('xkb', 'us'), ('xkb', 'ch+fr'), ('ibus', 'mozc-jp')]\n",
+    );
+    let layouts = get_saved_layouts_gnome_with(&runner, &create_keyboard_layouts());
+    // input methods are not keyboard layouts and are skipped
+    assert_eq!(
+        layout_names(&layouts),
+        vec![("us".into(), None), ("ch".into(), Some("fr".into()))]
+    );
+
+    // dconf prints nothing when the key was never written
+    let runner = FixtureCommandRunner::new().with_output(
+        "dconf",
+        &["read", "/org/gnome/desktop/input-sources/sources"],
+        "",
+    );
+    assert!(get_saved_layouts_gnome_with(&runner, &create_keyboard_layouts()).is_empty());
+    let runner = FixtureCommandRunner::new();
+    assert!(get_saved_layouts_gnome_with(&runner, &create_keyboard_layouts()).is_empty());
+}
+
+#[test]
+fn test_recorded_hyprland_layouts() {
+    let runner = FixtureCommandRunner::new()
+        .with_output(
+            "hyprctl",
+            &["getoption", "input:kb_layout"],
+            "str: us,de\nset: true\n",
+        )
+        .with_output(
+            "hyprctl",
+            &["getoption", "input:kb_variant"],
+            "str: ,nodeadkeys\nset: true\n",
+        );
+    let layouts = get_saved_layouts_hyprland_with(&runner, &create_keyboard_layouts());
+    assert_eq!(
+        layout_names(&layouts),
+        vec![
+            ("us".into(), None),
+            ("de".into(), Some("nodeadkeys".into()))
+        ]
+    );
+}
@@ -1,11 +1,9 @@
 use crate::r#const::{BASE, DBUS_PATH, INTERFACE};
 use dbus::blocking::Connection;
 use dbus::Error;
-use std::process::Output;
 use std::time::Duration;
 
-pub fn parse_setting(command_output: Output) -> Vec<String> {
-    let output = String::from_utf8(command_output.stdout).expect("not utf8");
+pub fn parse_setting(output: &str) -> Vec<String> {
     let output = output.lines().next().unwrap();
     let output = output.replace("str:", "");
     let output: Vec<String> = output.split(",").map(|s| s.to_string()).collect();
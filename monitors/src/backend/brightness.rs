@@ -0,0 +1,641 @@
+// This file handles the brightness and gamma of monitors
+// external monitors are changed via DDC/CI, internal panels via their backlight and logind,
+// the gamma control protocol of wlroots based compositors is used as a software fallback
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    os::fd::{AsFd, AsRawFd},
+    path::{Path, PathBuf},
+    process,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use wayland_client::{
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{wl_output, wl_registry},
+    Connection, Dispatch, EventQueue, QueueHandle,
+};
+use wayland_protocols_wlr::gamma_control::v1::client::{
+    zwlr_gamma_control_manager_v1::ZwlrGammaControlManagerV1,
+    zwlr_gamma_control_v1::{self, ZwlrGammaControlV1},
+};
+
+#[cfg(debug_assertions)]
+use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
+use re_set_lib::{ERROR, LOG};
+
+use crate::utils::{
+    MonitorBrightness, BRIGHTNESS_BACKLIGHT, BRIGHTNESS_DDC, BRIGHTNESS_GAMMA, MAX_BRIGHTNESS,
+    MAX_GAMMA, MIN_BRIGHTNESS, MIN_GAMMA, MIN_TEMPERATURE, NEUTRAL_TEMPERATURE,
+};
+
+pub const DRM_PATH: &str = "/sys/class/drm";
+const BACKLIGHT_PATH: &str = "/sys/class/backlight";
+
+const I2C_SLAVE: libc::c_ulong = 0x0703;
+const DDC_ADDRESS: libc::c_ulong = 0x37;
+// checksums of requests start with the destination address, replies with a virtual host address
+const DDC_HOST_ADDRESS: u8 = 0x51;
+const DDC_DISPLAY_ADDRESS: u8 = 0x6E;
+const DDC_REPLY_CHECKSUM: u8 = 0x50;
+const DDC_GET_VCP: u8 = 0x01;
+const DDC_GET_VCP_REPLY: u8 = 0x02;
+const DDC_SET_VCP: u8 = 0x03;
+const VCP_BRIGHTNESS: u8 = 0x10;
+const DDC_REPLY_LENGTH: usize = 11;
+// monitors need some time to process a request before they reply or accept the next one
+const DDC_REPLY_DELAY: Duration = Duration::from_millis(40);
+const DDC_WRITE_DELAY: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Default)]
+pub struct BrightnessData {
+    gamma: Option<(EventQueue<GammaState>, GammaState)>,
+    gamma_unsupported: bool,
+    // probing DDC is slow, hence the method of each monitor is only detected when fetching
+    methods: HashMap<String, String>,
+}
+
+impl BrightnessData {
+    pub fn get_brightness(
+        &mut self,
+        conn: Option<Arc<Connection>>,
+        name: &str,
+    ) -> MonitorBrightness {
+        let mut brightness = MonitorBrightness {
+            method: "".into(),
+            brightness: MAX_BRIGHTNESS,
+            gamma_supported: false,
+            gamma: 1.0,
+        };
+        if let Some(output) = self.gamma_output(conn, name) {
+            brightness.method = BRIGHTNESS_GAMMA.into();
+            brightness.brightness = output.brightness;
+            brightness.gamma_supported = true;
+            brightness.gamma = output.gamma;
+        }
+
+        if let Some(device) = backlight_device(name) {
+            if let Some((current, max)) = backlight_get(&device) {
+                brightness.method = BRIGHTNESS_BACKLIGHT.into();
+                brightness.brightness = to_percent(current, max);
+            }
+        } else if let Some(bus) = ddc_bus(name) {
+            match ddc_get_brightness(&bus) {
+                Ok((current, max)) => {
+                    brightness.method = BRIGHTNESS_DDC.into();
+                    brightness.brightness = to_percent(current as u32, max as u32);
+                }
+                // not every monitor supports DDC/CI or has it enabled
+                Err(_error) => LOG!(format!("No DDC/CI for monitor {}: {}", name, _error)),
+            }
+        }
+
+        self.methods
+            .insert(name.to_string(), brightness.method.clone());
+        brightness
+    }
+
+    pub fn set_brightness(
+        &mut self,
+        conn: Option<Arc<Connection>>,
+        name: &str,
+        brightness: u32,
+    ) -> bool {
+        let brightness = brightness.clamp(MIN_BRIGHTNESS, MAX_BRIGHTNESS);
+        let method = match self.methods.get(name) {
+            Some(method) => method.clone(),
+            None => self.get_brightness(conn.clone(), name).method,
+        };
+        match method.as_str() {
+            BRIGHTNESS_BACKLIGHT => {
+                let device = backlight_device(name);
+                let max = device.as_ref().and_then(|device| backlight_get(device));
+                if let (Some(device), Some((_, max))) = (device, max) {
+                    return backlight_set(&device, from_percent(brightness, max));
+                }
+                false
+            }
+            BRIGHTNESS_DDC => {
+                let bus = ddc_bus(name);
+                if bus.is_none() {
+                    return false;
+                }
+                let bus = bus.unwrap();
+                let result = ddc_get_brightness(&bus).and_then(|(_, max)| {
+                    ddc_set_brightness(&bus, from_percent(brightness, max as u32) as u16)
+                });
+                if let Err(_error) = result {
+                    ERROR!(
+                        format!("Could not set brightness via DDC/CI: {}", _error),
+                        ErrorLevel::Recoverable
+                    );
+                    return false;
+                }
+                true
+            }
+            BRIGHTNESS_GAMMA => {
+                self.update_gamma(conn, name, |output| output.brightness = brightness)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn set_gamma(&mut self, conn: Option<Arc<Connection>>, name: &str, gamma: f64) -> bool {
+        let gamma = gamma.clamp(MIN_GAMMA, MAX_GAMMA);
+        self.update_gamma(conn, name, |output| output.gamma = gamma)
+    }
+
+    fn gamma_state(
+        &mut self,
+        conn: Option<Arc<Connection>>,
+    ) -> Option<&mut (EventQueue<GammaState>, GammaState)> {
+        if self.gamma.is_none() && !self.gamma_unsupported {
+            self.gamma = conn.and_then(|conn| init_gamma(&conn));
+            self.gamma_unsupported = self.gamma.is_none();
+        }
+        let (queue, state) = self.gamma.as_mut()?;
+        // outputs might have been added or removed since the last request
+        if let Err(_error) = queue.roundtrip(state) {
+            ERROR!(
+                format!("Could not update gamma control outputs: {}", _error),
+                ErrorLevel::Recoverable
+            );
+            return None;
+        }
+        self.gamma.as_mut()
+    }
+
+    fn gamma_output(&mut self, conn: Option<Arc<Connection>>, name: &str) -> Option<&GammaOutput> {
+        let (_, state) = self.gamma_state(conn)?;
+        state
+            .outputs
+            .values()
+            .find(|output| output.name == name && output.control.is_some())
+    }
+
+    fn update_gamma(
+        &mut self,
+        conn: Option<Arc<Connection>>,
+        name: &str,
+        update: impl FnOnce(&mut GammaOutput),
+    ) -> bool {
+        let gamma = self.gamma_state(conn);
+        if gamma.is_none() {
+            return false;
+        }
+        let (queue, state) = gamma.unwrap();
+        let output = state
+            .outputs
+            .values_mut()
+            .find(|output| output.name == name && output.control.is_some());
+        if output.is_none() {
+            return false;
+        }
+        let output = output.unwrap();
+        update(output);
+        if !output.apply(state.temperature) {
+            return false;
+        }
+        // the compositor reports a failure when another client controls the gamma
+        queue.roundtrip(state).is_ok()
+            && state
+                .outputs
+                .values()
+                .any(|output| output.name == name && output.control.is_some())
+    }
+
+    /// Sets the colour temperature of all outputs, used for the night light.
+    pub fn set_temperature(&mut self, conn: Option<Arc<Connection>>, temperature: u32) -> bool {
+        let gamma = self.gamma_state(conn);
+        if gamma.is_none() {
+            return false;
+        }
+        let (queue, state) = gamma.unwrap();
+        state.temperature = temperature;
+        let mut applied = true;
+        for output in state.outputs.values() {
+            applied &= output.apply(temperature);
+        }
+        applied && queue.roundtrip(state).is_ok()
+    }
+}
+
+// connectors are listed as card<number>-<connector name>
+fn drm_connector(name: &str) -> Option<PathBuf> {
+    fs::read_dir(DRM_PATH)
+        .ok()?
+        .flatten()
+        .find(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .split_once('-')
+                .is_some_and(|(card, connector)| card.starts_with("card") && connector == name)
+        })
+        .map(|entry| entry.path())
+}
+
+fn is_internal_panel(name: &str) -> bool {
+    ["eDP", "LVDS", "DSI"]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
+
+fn read_sys_value(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+// most drivers link the backlight to the connector, otherwise the backlight of the system belongs
+// to the internal panel
+fn backlight_device(name: &str) -> Option<String> {
+    if let Some(connector) = drm_connector(name) {
+        let device = fs::read_dir(connector)
+            .ok()?
+            .flatten()
+            .find(|entry| entry.path().join("max_brightness").exists());
+        if let Some(device) = device {
+            return Some(device.file_name().to_string_lossy().to_string());
+        }
+    }
+    if !is_internal_panel(name) {
+        return None;
+    }
+    fs::read_dir(BACKLIGHT_PATH)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .min()
+}
+
+fn backlight_get(device: &str) -> Option<(u32, u32)> {
+    let path = PathBuf::from(BACKLIGHT_PATH).join(device);
+    let current = read_sys_value(&path.join("brightness"))?;
+    let max = read_sys_value(&path.join("max_brightness"))?;
+    Some((current, max))
+}
+
+// writing to sysfs requires root, logind allows the user of the active session instead
+fn backlight_set(device: &str, value: u32) -> bool {
+    let conn = dbus::blocking::Connection::new_system();
+    if let Err(_error) = conn {
+        ERROR!(
+            format!("Could not connect to the system bus: {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return false;
+    }
+    let conn = conn.unwrap();
+    let proxy = conn.with_proxy(
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1/session/auto",
+        Duration::from_millis(1000),
+    );
+    let res: Result<(), dbus::Error> = proxy.method_call(
+        "org.freedesktop.login1.Session",
+        "SetBrightness",
+        ("backlight", device, value),
+    );
+    if let Err(_error) = res {
+        ERROR!(
+            format!("Could not set backlight brightness: {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return false;
+    }
+    true
+}
+
+// the i2c bus of the connector is either linked as ddc or listed as i2c-<number>
+fn ddc_bus(name: &str) -> Option<PathBuf> {
+    let connector = drm_connector(name)?;
+    let bus = if let Ok(ddc) = fs::read_link(connector.join("ddc")) {
+        ddc.file_name()?.to_string_lossy().to_string()
+    } else {
+        fs::read_dir(connector)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .find(|entry| entry.starts_with("i2c-"))?
+    };
+    let bus = PathBuf::from("/dev").join(bus);
+    if !bus.exists() {
+        return None;
+    }
+    Some(bus)
+}
+
+fn ddc_open(bus: &Path) -> io::Result<File> {
+    let file = OpenOptions::new().read(true).write(true).open(bus)?;
+    if unsafe { libc::ioctl(file.as_raw_fd(), I2C_SLAVE, DDC_ADDRESS) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(file)
+}
+
+fn ddc_message(payload: &[u8]) -> Vec<u8> {
+    let mut message = vec![DDC_HOST_ADDRESS, 0x80 | payload.len() as u8];
+    message.extend_from_slice(payload);
+    let checksum = message
+        .iter()
+        .fold(DDC_DISPLAY_ADDRESS, |checksum, byte| checksum ^ byte);
+    message.push(checksum);
+    message
+}
+
+pub fn ddc_get_vcp_request(vcp: u8) -> Vec<u8> {
+    ddc_message(&[DDC_GET_VCP, vcp])
+}
+
+pub fn ddc_set_vcp_request(vcp: u8, value: u16) -> Vec<u8> {
+    let [high, low] = value.to_be_bytes();
+    ddc_message(&[DDC_SET_VCP, vcp, high, low])
+}
+
+/// Returns the current and maximum value of a VCP feature reply.
+pub fn ddc_parse_vcp_reply(reply: &[u8], vcp: u8) -> Option<(u16, u16)> {
+    if reply.len() < DDC_REPLY_LENGTH {
+        return None;
+    }
+    let checksum = reply[..DDC_REPLY_LENGTH - 1]
+        .iter()
+        .fold(DDC_REPLY_CHECKSUM, |checksum, byte| checksum ^ byte);
+    // the result code is 0 for supported features
+    if reply[0] != DDC_DISPLAY_ADDRESS
+        || reply[1] != 0x88
+        || reply[2] != DDC_GET_VCP_REPLY
+        || reply[3] != 0
+        || reply[4] != vcp
+        || reply[DDC_REPLY_LENGTH - 1] != checksum
+    {
+        return None;
+    }
+    let max = u16::from_be_bytes([reply[6], reply[7]]);
+    let current = u16::from_be_bytes([reply[8], reply[9]]);
+    Some((current, max))
+}
+
+fn ddc_get_brightness(bus: &Path) -> io::Result<(u16, u16)> {
+    let mut file = ddc_open(bus)?;
+    file.write_all(&ddc_get_vcp_request(VCP_BRIGHTNESS))?;
+    thread::sleep(DDC_REPLY_DELAY);
+    let mut reply = [0; DDC_REPLY_LENGTH];
+    file.read_exact(&mut reply)?;
+    ddc_parse_vcp_reply(&reply, VCP_BRIGHTNESS)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid DDC/CI reply"))
+}
+
+fn ddc_set_brightness(bus: &Path, value: u16) -> io::Result<()> {
+    let mut file = ddc_open(bus)?;
+    thread::sleep(DDC_WRITE_DELAY);
+    file.write_all(&ddc_set_vcp_request(VCP_BRIGHTNESS, value))
+}
+
+pub fn to_percent(value: u32, max: u32) -> u32 {
+    if max == 0 {
+        return MAX_BRIGHTNESS;
+    }
+    ((value as f64 / max as f64) * 100.0).round() as u32
+}
+
+pub fn from_percent(percent: u32, max: u32) -> u32 {
+    ((percent as f64 / 100.0) * max as f64).round() as u32
+}
+
+/// Returns the factors of red, green and blue for a colour temperature in kelvin.
+/// Based on the approximation of the black body by Tanner Helland, temperatures at or above the
+/// neutral temperature leave the colours unchanged.
+pub fn temperature_to_rgb(temperature: u32) -> (f64, f64, f64) {
+    if temperature >= NEUTRAL_TEMPERATURE {
+        return (1.0, 1.0, 1.0);
+    }
+    let temperature = temperature.max(MIN_TEMPERATURE) as f64 / 100.0;
+    let green = 99.470_802_586_1 * temperature.ln() - 161.119_568_166_1;
+    let blue = if temperature <= 19.0 {
+        0.0
+    } else {
+        138.517_731_223_1 * (temperature - 10.0).ln() - 305.044_792_730_7
+    };
+    (
+        1.0,
+        (green / 255.0).clamp(0.0, 1.0),
+        (blue / 255.0).clamp(0.0, 1.0),
+    )
+}
+
+/// Creates the gamma ramps for red, green and blue with brightness and temperature applied.
+pub fn gamma_ramp(size: u32, brightness: u32, gamma: f64, temperature: u32) -> Vec<u16> {
+    let size = size as usize;
+    let (red, green, blue) = temperature_to_rgb(temperature);
+    let mut ramp = Vec::with_capacity(size * 3);
+    for factor in [red, green, blue] {
+        ramp.extend((0..size).map(|index| {
+            let input = if size > 1 {
+                index as f64 / (size - 1) as f64
+            } else {
+                1.0
+            };
+            let value = input.powf(1.0 / gamma) * brightness as f64 / 100.0 * factor;
+            (value.clamp(0.0, 1.0) * u16::MAX as f64).round() as u16
+        }));
+    }
+    ramp
+}
+
+// the ramp is passed as a file descriptor, the file is removed right away and only kept open
+fn set_gamma_ramp(control: &ZwlrGammaControlV1, ramp: &[u16]) -> io::Result<()> {
+    let dir = env::var("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| env::temp_dir());
+    let path = dir.join(format!("reset-gamma-{}", process::id()));
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)?;
+    fs::remove_file(&path)?;
+    let bytes: Vec<u8> = ramp.iter().flat_map(|value| value.to_ne_bytes()).collect();
+    file.write_all(&bytes)?;
+    file.seek(SeekFrom::Start(0))?;
+    control.set_gamma(file.as_fd());
+    Ok(())
+}
+
+#[derive(Debug)]
+struct GammaOutput {
+    name: String,
+    output: wl_output::WlOutput,
+    control: Option<ZwlrGammaControlV1>,
+    size: u32,
+    brightness: u32,
+    gamma: f64,
+}
+
+impl GammaOutput {
+    fn apply(&self, temperature: u32) -> bool {
+        if self.control.is_none() {
+            return false;
+        }
+        let ramp = gamma_ramp(self.size, self.brightness, self.gamma, temperature);
+        if let Err(_error) = set_gamma_ramp(self.control.as_ref().unwrap(), &ramp) {
+            ERROR!(
+                format!("Could not set gamma ramp: {}", _error),
+                ErrorLevel::Recoverable
+            );
+            return false;
+        }
+        true
+    }
+}
+
+#[derive(Debug)]
+pub struct GammaState {
+    manager: ZwlrGammaControlManagerV1,
+    // the global name of the output is used as the key
+    outputs: HashMap<u32, GammaOutput>,
+    // shared by all outputs, set by the night light
+    temperature: u32,
+}
+
+impl GammaState {
+    fn bind_output(
+        &mut self,
+        registry: &wl_registry::WlRegistry,
+        global: u32,
+        version: u32,
+        handle: &QueueHandle<GammaState>,
+    ) {
+        // the name of the output requires version 4
+        if version < 4 {
+            return;
+        }
+        let output = registry.bind::<wl_output::WlOutput, _, _>(global, 4, handle, global);
+        self.outputs.insert(
+            global,
+            GammaOutput {
+                name: "".into(),
+                output,
+                control: None,
+                size: 0,
+                brightness: MAX_BRIGHTNESS,
+                gamma: 1.0,
+            },
+        );
+    }
+}
+
+fn init_gamma(conn: &Connection) -> Option<(EventQueue<GammaState>, GammaState)> {
+    let (globals, mut queue) = registry_queue_init::<GammaState>(conn).ok()?;
+    let handle = queue.handle();
+    let manager = globals
+        .bind::<ZwlrGammaControlManagerV1, _, _>(&handle, 1..=1, ())
+        .ok()?;
+    let mut state = GammaState {
+        manager,
+        outputs: HashMap::new(),
+        temperature: NEUTRAL_TEMPERATURE,
+    };
+    for global in globals.contents().clone_list() {
+        if global.interface == "wl_output" {
+            state.bind_output(globals.registry(), global.name, global.version, &handle);
+        }
+    }
+    // the first roundtrip fetches the names of the outputs, the second one the gamma sizes
+    queue.roundtrip(&mut state).ok()?;
+    queue.roundtrip(&mut state).ok()?;
+    Some((queue, state))
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for GammaState {
+    fn event(
+        state: &mut GammaState,
+        registry: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        handle: &QueueHandle<GammaState>,
+    ) {
+        match event {
+            wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } if interface == "wl_output" => state.bind_output(registry, name, version, handle),
+            wl_registry::Event::GlobalRemove { name } => {
+                if let Some(output) = state.outputs.remove(&name) {
+                    if let Some(control) = output.control {
+                        control.destroy();
+                    }
+                    output.output.release();
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<wl_output::WlOutput, u32> for GammaState {
+    fn event(
+        state: &mut GammaState,
+        _: &wl_output::WlOutput,
+        event: wl_output::Event,
+        global: &u32,
+        _: &Connection,
+        handle: &QueueHandle<GammaState>,
+    ) {
+        if let wl_output::Event::Name { name } = event {
+            if let Some(output) = state.outputs.get_mut(global) {
+                output.name = name;
+                if output.control.is_none() {
+                    output.control = Some(state.manager.get_gamma_control(
+                        &output.output,
+                        handle,
+                        *global,
+                    ));
+                }
+            }
+        }
+    }
+}
+
+impl Dispatch<ZwlrGammaControlManagerV1, ()> for GammaState {
+    fn event(
+        _: &mut GammaState,
+        _: &ZwlrGammaControlManagerV1,
+        _: <ZwlrGammaControlManagerV1 as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<GammaState>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrGammaControlV1, u32> for GammaState {
+    fn event(
+        state: &mut GammaState,
+        control: &ZwlrGammaControlV1,
+        event: zwlr_gamma_control_v1::Event,
+        global: &u32,
+        _: &Connection,
+        _: &QueueHandle<GammaState>,
+    ) {
+        let output = state.outputs.get_mut(global);
+        if output.is_none() {
+            return;
+        }
+        let output = output.unwrap();
+        match event {
+            zwlr_gamma_control_v1::Event::GammaSize { size } => output.size = size,
+            zwlr_gamma_control_v1::Event::Failed => {
+                LOG!(format!("Gamma control of monitor {} failed", output.name));
+                control.destroy();
+                output.control = None;
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,306 @@
+// This file handles the EDID of monitors, which is read from the DRM connectors in sysfs
+// compositors often report empty or shortened identities, the EDID is used to fill these in
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::utils::{EdidInfo, Monitor, MonitorError, Size};
+
+use super::brightness::DRM_PATH;
+
+const EDID_HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+const BLOCK_SIZE: usize = 128;
+const CTA_EXTENSION: u8 = 0x02;
+
+const DESCRIPTOR_SERIAL: u8 = 0xFF;
+const DESCRIPTOR_NAME: u8 = 0xFC;
+
+const CTA_EXTENDED_TAG: u8 = 7;
+const CTA_COLORIMETRY: u8 = 5;
+const CTA_HDR_STATIC_METADATA: u8 = 6;
+
+// subset of the PNP ids, unknown manufacturers are shown with their id
+const MANUFACTURERS: [(&str, &str); 24] = [
+    ("ACR", "Acer"),
+    ("AOC", "AOC"),
+    ("APP", "Apple"),
+    ("AUS", "ASUS"),
+    ("AUO", "AU Optronics"),
+    ("BNQ", "BenQ"),
+    ("BOE", "BOE"),
+    ("CMN", "Chimei Innolux"),
+    ("DEL", "Dell"),
+    ("ENC", "EIZO"),
+    ("GSM", "LG Electronics"),
+    ("HPN", "HP"),
+    ("HWP", "HP"),
+    ("IVM", "Iiyama"),
+    ("LEN", "Lenovo"),
+    ("LGD", "LG Display"),
+    ("MSI", "MSI"),
+    ("NEC", "NEC"),
+    ("PHL", "Philips"),
+    ("SAM", "Samsung"),
+    ("SDC", "Samsung Display"),
+    ("SHP", "Sharp"),
+    ("SNY", "Sony"),
+    ("VSC", "ViewSonic"),
+];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Edid {
+    // PNP id, e.g. DEL
+    pub manufacturer_id: String,
+    pub manufacturer: String,
+    pub product_code: u16,
+    pub product_name: String,
+    pub serial: String,
+    pub info: EdidInfo,
+}
+
+/// Parses the base block and the CTA-861 extensions of an EDID.
+pub fn parse_edid(data: &[u8]) -> Result<Edid, MonitorError> {
+    if data.len() < BLOCK_SIZE || data[..8] != EDID_HEADER {
+        return Err(MonitorError::Parse(String::from("invalid EDID header")));
+    }
+    if !valid_checksum(&data[..BLOCK_SIZE]) {
+        return Err(MonitorError::Parse(String::from("invalid EDID checksum")));
+    }
+
+    let id = u16::from_be_bytes([data[8], data[9]]);
+    let manufacturer_id: String = [(id >> 10) & 0x1F, (id >> 5) & 0x1F, id & 0x1F]
+        .iter()
+        .map(|letter| (b'A' - 1 + *letter as u8) as char)
+        .collect();
+    let manufacturer = MANUFACTURERS
+        .iter()
+        .find(|(id, _)| *id == manufacturer_id)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| manufacturer_id.clone());
+    let product_code = u16::from_le_bytes([data[10], data[11]]);
+    let serial_number = u32::from_le_bytes([data[12], data[13], data[14], data[15]]);
+
+    let mut edid = Edid {
+        manufacturer_id,
+        manufacturer,
+        product_code,
+        // the size is only reported in centimeters in the base block
+        info: EdidInfo {
+            physical_size: Size(data[21] as i32 * 10, data[22] as i32 * 10),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let mut native_found = false;
+    for descriptor in data[54..126].chunks(18) {
+        if descriptor[0] != 0 || descriptor[1] != 0 {
+            // the first detailed timing is the preferred mode
+            if !native_found {
+                native_found = true;
+                parse_detailed_timing(descriptor, &mut edid.info);
+            }
+            continue;
+        }
+        match descriptor[3] {
+            DESCRIPTOR_NAME => edid.product_name = descriptor_text(descriptor),
+            DESCRIPTOR_SERIAL => edid.serial = descriptor_text(descriptor),
+            _ => (),
+        }
+    }
+    // same notation as wlroots and mutter
+    if edid.serial.is_empty() && serial_number != 0 {
+        edid.serial = format!("0x{:08X}", serial_number);
+    }
+
+    // broken extensions are ignored, the base block is enough to identify the monitor
+    let extensions = data[126] as usize;
+    for block in data[BLOCK_SIZE..]
+        .chunks_exact(BLOCK_SIZE)
+        .take(extensions)
+        .filter(|block| block[0] == CTA_EXTENSION && valid_checksum(block))
+    {
+        parse_cta_extension(block, &mut edid.info);
+    }
+    Ok(edid)
+}
+
+fn valid_checksum(block: &[u8]) -> bool {
+    block.iter().fold(0u8, |sum, val| sum.wrapping_add(*val)) == 0
+}
+
+// text is terminated with a newline and padded with spaces
+fn descriptor_text(descriptor: &[u8]) -> String {
+    let text = &descriptor[5..18];
+    let end = text
+        .iter()
+        .position(|val| *val == b'\n')
+        .unwrap_or(text.len());
+    String::from_utf8_lossy(&text[..end]).trim().to_string()
+}
+
+fn parse_detailed_timing(descriptor: &[u8], info: &mut EdidInfo) {
+    let pixel_clock = u16::from_le_bytes([descriptor[0], descriptor[1]]) as u64 * 10_000;
+    let h_active = descriptor[2] as u64 | ((descriptor[4] as u64 & 0xF0) << 4);
+    let h_blank = descriptor[3] as u64 | ((descriptor[4] as u64 & 0x0F) << 8);
+    let v_active = descriptor[5] as u64 | ((descriptor[7] as u64 & 0xF0) << 4);
+    let v_blank = descriptor[6] as u64 | ((descriptor[7] as u64 & 0x0F) << 8);
+    let total = (h_active + h_blank) * (v_active + v_blank);
+    if total == 0 {
+        return;
+    }
+    info.native_size = Size(h_active as i32, v_active as i32);
+    info.native_refresh_rate = ((pixel_clock * 1000 + total / 2) / total) as u32;
+
+    // millimeters, more precise than the size of the base block
+    let width = descriptor[12] as i32 | ((descriptor[14] as i32 & 0xF0) << 4);
+    let height = descriptor[13] as i32 | ((descriptor[14] as i32 & 0x0F) << 8);
+    if width > 0 && height > 0 {
+        info.physical_size = Size(width, height);
+    }
+}
+
+fn parse_cta_extension(block: &[u8], info: &mut EdidInfo) {
+    // the data blocks end where the detailed timings start
+    let end = (block[2] as usize).min(BLOCK_SIZE - 1);
+    let mut offset = 4;
+    while offset < end {
+        let tag = block[offset] >> 5;
+        let length = (block[offset] & 0x1F) as usize;
+        let payload = &block[(offset + 1).min(end)..(offset + 1 + length).min(end)];
+        offset += 1 + length;
+        if tag != CTA_EXTENDED_TAG || payload.len() < 2 {
+            continue;
+        }
+        match payload[0] {
+            CTA_COLORIMETRY => parse_colorimetry(&payload[1..], info),
+            CTA_HDR_STATIC_METADATA => parse_hdr_static_metadata(&payload[1..], info),
+            _ => (),
+        }
+    }
+}
+
+fn parse_colorimetry(payload: &[u8], info: &mut EdidInfo) {
+    const COLOR_SPACES: [&str; 8] = [
+        "xvYCC601",
+        "xvYCC709",
+        "sYCC601",
+        "opYCC601",
+        "opRGB",
+        "BT2020cYCC",
+        "BT2020YCC",
+        "BT2020RGB",
+    ];
+    for (bit, color_space) in COLOR_SPACES.iter().enumerate() {
+        if payload[0] & (1 << bit) != 0 {
+            info.color_spaces.push(color_space.to_string());
+        }
+    }
+    if payload.get(1).is_some_and(|val| val & 0x80 != 0) {
+        info.color_spaces.push(String::from("DCI-P3"));
+    }
+}
+
+fn parse_hdr_static_metadata(payload: &[u8], info: &mut EdidInfo) {
+    const EOTFS: [&str; 4] = ["SDR", "HDR", "PQ", "HLG"];
+    for (bit, eotf) in EOTFS.iter().enumerate() {
+        if payload[0] & (1 << bit) != 0 {
+            info.eotfs.push(eotf.to_string());
+        }
+    }
+    // luminance values are optional and coded as defined by CTA-861
+    if let Some(value) = payload.get(2).filter(|val| **val != 0) {
+        info.max_luminance = 50.0 * 2f64.powf(*value as f64 / 32.0);
+    }
+    if let Some(value) = payload.get(3).filter(|val| **val != 0) {
+        info.max_frame_average_luminance = 50.0 * 2f64.powf(*value as f64 / 32.0);
+    }
+    if let Some(value) = payload.get(4) {
+        info.min_luminance = info.max_luminance * (*value as f64 / 255.0).powi(2) / 100.0;
+    }
+}
+
+/// Reads the EDID of every connected connector, the key is the card and the connector name, e.g.
+/// card1-DP-1, as connector names are only unique per card.
+pub fn read_edids(drm_path: &Path) -> HashMap<String, Edid> {
+    let mut edids = HashMap::new();
+    let entries = fs::read_dir(drm_path);
+    if entries.is_err() {
+        return edids;
+    }
+    // connectors are listed as card<number>-<connector name>
+    for entry in entries.unwrap().flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if connector_name(&file_name).is_none() {
+            continue;
+        }
+        // disconnected connectors have an empty EDID
+        let edid = fs::read(entry.path().join("edid"))
+            .ok()
+            .and_then(|data| parse_edid(&data).ok());
+        if let Some(edid) = edid {
+            edids.insert(file_name, edid);
+        }
+    }
+    edids
+}
+
+fn connector_name(file_name: &str) -> Option<&str> {
+    file_name
+        .split_once('-')
+        .filter(|(card, _)| card.starts_with("card"))
+        .map(|(_, connector)| connector)
+}
+
+// the card is unknown to the compositors, with multiple cards the identity reported by the
+// compositor decides which EDID belongs to the monitor
+fn find_edid<'a>(monitor: &Monitor, edids: &'a HashMap<String, Edid>) -> Option<&'a Edid> {
+    let candidates: Vec<&Edid> = edids
+        .iter()
+        .filter(|(key, _)| connector_name(key) == Some(monitor.name.as_str()))
+        .map(|(_, edid)| edid)
+        .collect();
+    if candidates.len() <= 1 {
+        return candidates.first().copied();
+    }
+    let matching: Vec<&Edid> = candidates
+        .into_iter()
+        .filter(|edid| {
+            (!monitor.serial.is_empty() && edid.serial == monitor.serial)
+                || (!monitor.model.is_empty() && edid.product_name == monitor.model)
+        })
+        .collect();
+    if matching.len() != 1 {
+        return None;
+    }
+    matching.first().copied()
+}
+
+/// Adds the EDID information to the monitors, identities reported by the compositor are kept as
+/// they are used to match profiles and stored layouts.
+pub fn apply_edids(monitors: &mut [Monitor], edids: &HashMap<String, Edid>) {
+    for monitor in monitors.iter_mut() {
+        let edid = find_edid(monitor, edids);
+        if edid.is_none() {
+            continue;
+        }
+        let edid = edid.unwrap();
+        if monitor.make.is_empty() {
+            monitor.make = edid.manufacturer.clone();
+        }
+        if monitor.model.is_empty() {
+            monitor.model = if edid.product_name.is_empty() {
+                format!("0x{:04X}", edid.product_code)
+            } else {
+                edid.product_name.clone()
+            };
+        }
+        if monitor.serial.is_empty() {
+            monitor.serial = edid.serial.clone();
+        }
+        monitor.edid = edid.info.clone();
+    }
+}
+
+/// Adds the EDID information of the connected monitors, see apply_edids.
+pub fn add_edid_information(monitors: &mut [Monitor]) {
+    apply_edids(monitors, &read_edids(Path::new(DRM_PATH)));
+}
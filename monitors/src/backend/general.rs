@@ -1,49 +1,71 @@
-// This file handles general functions for monitor conversions
+// This file handles the selection of the monitor backend for the current environment
+use std::{fmt::Debug, sync::Arc};
+
 use re_set_lib::ERROR;
 #[cfg(debug_assertions)]
 use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
 
-use crate::utils::{get_environment, Monitor, GNOME, HYPRLAND, KDE};
+use crate::utils::{
+    get_environment, BackendCapabilities, Monitor, MonitorError, MonitorFeatures, GNOME, HYPRLAND,
+    KDE, SWAY,
+};
 
 use super::{
-    gnome::g_apply_monitor_config,
-    hyprland::{hy_apply_monitor_information, hy_save_monitor_configuration},
-    kde::{kde_apply_monitor_config, kde_save_monitor_config},
-    kwin::kwin_apply_monitor_configuration,
-    utils::get_wl_backend,
-    wlr::wlr_apply_monitor_configuration,
+    gnome::GnomeBackend, hyprland::HyprlandBackend, kde::KdeBackend, kwin::KWinBackend,
+    sway::SwayBackend, utils::get_wl_backend, wlr::WlrBackend,
 };
 
-// temporary application of configuration
-pub fn apply_monitor_configuration(
-    conn: Option<std::sync::Arc<wayland_client::Connection>>,
-    monitors: &Vec<Monitor>,
-) {
-    match get_environment().as_str() {
-        HYPRLAND => hy_apply_monitor_information(monitors, conn),
-        GNOME | "ubuntu:GNOME" => g_apply_monitor_config(1, monitors),
-        KDE => kde_apply_monitor_config(conn, monitors),
-        // fallback to protocol implementations
-        _ => match get_wl_backend().as_str() {
-            "WLR" => wlr_apply_monitor_configuration(conn, monitors),
-            "KWIN" => kwin_apply_monitor_configuration(conn, monitors),
-            _ => ERROR!("Unsupported Environment", ErrorLevel::PartialBreakage),
-        },
-    };
+/// Implemented once per environment, the backend is selected once when the DBus interface is
+/// created.
+pub trait MonitorBackend: Debug + Send + Sync {
+    /// Fetches the current configuration.
+    fn get(&self, conn: Option<Arc<wayland_client::Connection>>, serial: &mut u32) -> Vec<Monitor>;
+
+    /// Temporary application of the configuration.
+    fn apply(
+        &self,
+        conn: Option<Arc<wayland_client::Connection>>,
+        monitors: &[Monitor],
+    ) -> Result<(), MonitorError>;
+
+    /// Persistent application of the configuration.
+    fn save(
+        &self,
+        conn: Option<Arc<wayland_client::Connection>>,
+        monitors: &[Monitor],
+    ) -> Result<(), MonitorError>;
+
+    /// Features offered by the environment, individual monitors may offer less.
+    fn features(&self) -> MonitorFeatures;
+
+    /// Rules of the environment, e.g. whether gaps are allowed, used to adapt the UI.
+    fn capabilities(&self) -> BackendCapabilities;
+
+    /// Blocks the current thread and calls the callback whenever the compositor reports a monitor
+    /// change.
+    fn watch(&self, conn: Option<Arc<wayland_client::Connection>>, callback: Box<dyn FnMut()>);
+
+    /// Whether the fetched monitors can be reused until the watcher reports a change.
+    fn caches_monitors(&self) -> bool {
+        true
+    }
 }
 
-// persistent application of configuration
-pub fn save_monitor_configuration(
-    conn: Option<std::sync::Arc<wayland_client::Connection>>,
-    monitors: &Vec<Monitor>,
-) {
-    match get_environment().as_str() {
-        HYPRLAND => hy_save_monitor_configuration(monitors),
-        GNOME | "ubuntu:GNOME" => g_apply_monitor_config(2, monitors),
-        KDE => kde_save_monitor_config(conn, monitors),
+pub fn select_backend() -> Option<Arc<dyn MonitorBackend>> {
+    let backend: Arc<dyn MonitorBackend> = match get_environment().as_str() {
+        HYPRLAND => Arc::new(HyprlandBackend),
+        GNOME | "ubuntu:GNOME" => Arc::new(GnomeBackend),
+        KDE => Arc::new(KdeBackend),
+        SWAY => Arc::new(SwayBackend),
+        // fallback to protocol implementations
         _ => match get_wl_backend().as_str() {
-            "KWIN" => kwin_apply_monitor_configuration(conn, monitors),
-            _ => ERROR!("Unsupported Environment", ErrorLevel::PartialBreakage),
+            "WLR" => Arc::new(WlrBackend),
+            "KWIN" => Arc::new(KWinBackend),
+            _ => {
+                ERROR!("Unsupported Environment", ErrorLevel::PartialBreakage);
+                return None;
+            }
         },
     };
+    Some(backend)
 }
@@ -2,26 +2,95 @@ use std::{
     cmp::Ordering,
     collections::{HashMap, HashSet},
     hash::{DefaultHasher, Hash, Hasher},
-    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering as AtomicOrdering},
+        Arc,
+    },
     time::Duration,
 };
 
 use dbus::{
     arg::{self, prop_cast, Append, Arg, ArgType, Get, PropMap},
     blocking::Connection,
+    message::MatchRule,
     Error, Signature,
 };
 
 use re_set_lib::ERROR;
 #[cfg(debug_assertions)]
 use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
+use reset_common::command::{CommandRunner, HostCommandRunner};
 
-use crate::utils::{AvailableMode, DragInformation, Monitor, MonitorFeatures, Offset, Size};
+use crate::utils::{
+    to_millihertz, AvailableMode, BackendCapabilities, CustomMode, DragInformation, EdidInfo,
+    HdrSettings, Monitor, MonitorError, MonitorFeatures, Offset, Size,
+};
+
+use super::{
+    general::MonitorBackend,
+    mirror::{mirror_source, use_source_mode},
+};
 
 const BASE: &str = "org.gnome.Mutter.DisplayConfig";
 const DBUS_PATH: &str = "/org/gnome/Mutter/DisplayConfig";
 const INTERFACE: &str = "org.gnome.Mutter.DisplayConfig";
 
+// mutter is the only environment that uses DBus instead of wayland
+#[derive(Debug)]
+pub struct GnomeBackend;
+
+impl MonitorBackend for GnomeBackend {
+    fn get(&self, _: Option<Arc<wayland_client::Connection>>, serial: &mut u32) -> Vec<Monitor> {
+        g_get_monitor_information(serial)
+    }
+
+    fn apply(
+        &self,
+        _: Option<Arc<wayland_client::Connection>>,
+        monitors: &[Monitor],
+    ) -> Result<(), MonitorError> {
+        g_apply_monitor_config(1, monitors)
+    }
+
+    fn save(
+        &self,
+        _: Option<Arc<wayland_client::Connection>>,
+        monitors: &[Monitor],
+    ) -> Result<(), MonitorError> {
+        g_apply_monitor_config(2, monitors)
+    }
+
+    fn features(&self) -> MonitorFeatures {
+        gnome_features()
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            persistent_save: true,
+            // mutter shows its own confirmation dialog for persistent configurations
+            confirms_save: true,
+            // logical monitors have to be adjacent
+            gaps: false,
+            compact_layout: true,
+            // mutter reports the supported scales per mode
+            arbitrary_scales: false,
+            native_mirroring: true,
+            reports_vrr: true,
+            sdr_gamut_wideness: false,
+            features: self.features(),
+        }
+    }
+
+    fn watch(&self, _: Option<Arc<wayland_client::Connection>>, callback: Box<dyn FnMut()>) {
+        g_watch_monitors(callback)
+    }
+
+    // the serial needs to be fetched again for every configuration
+    fn caches_monitors(&self) -> bool {
+        false
+    }
+}
+
 pub fn gnome_features() -> MonitorFeatures {
     let experimental_features = get_experimental_support();
     MonitorFeatures {
@@ -30,48 +99,34 @@ pub fn gnome_features() -> MonitorFeatures {
         primary: true,
         fractional_scaling: experimental_features.0,
         hdr: false,
+        subpixel: false,
+        max_render_time: false,
+        custom_modes: false,
     }
 }
 
 fn get_experimental_support() -> (bool, bool) {
-    let command = Command::new("gsettings")
-        .args(["get", "org.gnome.mutter", "experimental-features"])
-        .output();
-    match command {
-        Ok(_) => {
-            let command = command.unwrap();
-            let command = String::from_utf8(command.stdout).unwrap();
-            (
-                command.contains("scale-monitor-framebuffer"),
-                command.contains("variable-refresh-rate"),
-            )
-        }
-        Err(_) => {
-            let command = Command::new("flatpak-spawn")
-                .args([
-                    "--host",
-                    "gsettings",
-                    "get",
-                    "org.gnome.mutter",
-                    "experimental-features",
-                ])
-                .output();
-
-            if command.is_err() {
-                return (false, false);
-            }
-            let command = command.unwrap();
-            let command = String::from_utf8(command.stdout).unwrap();
-            (
-                command.contains("scale-monitor-framebuffer"),
-                command.contains("variable-refresh-rate"),
-            )
-        }
+    let output = HostCommandRunner::default().run(
+        "gsettings",
+        &["get", "org.gnome.mutter", "experimental-features"],
+    );
+    if output.is_err() {
+        return (false, false);
     }
+    let features = output.unwrap().stdout;
+    (
+        features.contains("scale-monitor-framebuffer"),
+        features.contains("variable-refresh-rate"),
+    )
 }
 
 pub fn g_get_monitor_information(serial: &mut u32) -> Vec<Monitor> {
     let conn = Connection::new_session().unwrap();
+    g_get_monitor_information_with(&conn, serial)
+}
+
+/// Fetches the configuration from the DisplayConfig service on the given bus.
+pub fn g_get_monitor_information_with(conn: &Connection, serial: &mut u32) -> Vec<Monitor> {
     let proxy = conn.with_proxy(BASE, DBUS_PATH, Duration::from_millis(5000));
     let res: Result<(u32, Vec<GnomeMonitor>, Vec<GnomeLogicalMonitor>, PropMap), Error> =
         proxy.method_call(INTERFACE, "GetCurrentState", ());
@@ -90,14 +145,29 @@ pub fn g_get_monitor_information(serial: &mut u32) -> Vec<Monitor> {
     gnome_monitors.inplace_to_regular_monitor()
 }
 
-pub fn g_apply_monitor_config(apply_mode: u32, monitors: &Vec<Monitor>) {
-    let conn = Connection::new_session().unwrap();
+pub fn g_apply_monitor_config(apply_mode: u32, monitors: &[Monitor]) -> Result<(), MonitorError> {
+    let conn = Connection::new_session()
+        .map_err(|error| MonitorError::MissingTool(format!("Session bus ({})", error)))?;
+    g_apply_monitor_config_with(&conn, apply_mode, monitors)
+}
+
+/// Applies the configuration with the DisplayConfig service on the given bus.
+/// The serial is fetched right before applying, mutter rejects configurations with a stale
+/// serial.
+pub fn g_apply_monitor_config_with(
+    conn: &Connection,
+    apply_mode: u32,
+    monitors: &[Monitor],
+) -> Result<(), MonitorError> {
     let proxy = conn.with_proxy(BASE, DBUS_PATH, Duration::from_millis(5000));
     let res: Result<(u32, Vec<GnomeMonitor>, Vec<GnomeLogicalMonitor>, PropMap), Error> =
         proxy.method_call(INTERFACE, "GetCurrentState", ());
-    if res.is_err() {
+    if let Err(error) = res {
         ERROR!("Could fetch monitor configuration", ErrorLevel::Recoverable);
-        return;
+        return Err(MonitorError::MissingTool(format!(
+            "Mutter DisplayConfig ({})",
+            error
+        )));
     }
     let serial = res.unwrap().0;
     let res: Result<(), Error> = proxy.method_call(
@@ -105,11 +175,48 @@ pub fn g_apply_monitor_config(apply_mode: u32, monitors: &Vec<Monitor>) {
         "ApplyMonitorsConfig",
         GnomeMonitorConfig::from_regular_monitor(serial, apply_mode, monitors),
     );
+    if let Err(error) = res {
+        ERROR!(
+            format!("Could not apply monitor configuration {}", error),
+            ErrorLevel::Recoverable
+        );
+        return Err(MonitorError::ApplyFailed(
+            error.message().unwrap_or_default().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// blocks the current thread and calls the callback whenever mutter reports a monitor change
+pub fn g_watch_monitors(mut callback: impl FnMut()) {
+    let conn = Connection::new_session().unwrap();
+    let changed = Arc::new(AtomicBool::new(false));
+    let changed_ref = changed.clone();
+    let res = conn.add_match(
+        MatchRule::new_signal(INTERFACE, "MonitorsChanged"),
+        move |_: (), _, _| {
+            changed_ref.store(true, AtomicOrdering::SeqCst);
+            true
+        },
+    );
     if let Err(_error) = res {
         ERROR!(
-            format!("Could not apply monitor configuration {}", _error),
+            format!("Could not watch monitor changes {}", _error),
             ErrorLevel::Recoverable
         );
+        return;
+    }
+    loop {
+        if let Err(_error) = conn.process(Duration::from_millis(1000)) {
+            ERROR!(
+                format!("Stopped watching monitor changes {}", _error),
+                ErrorLevel::Recoverable
+            );
+            return;
+        }
+        if changed.swap(false, AtomicOrdering::SeqCst) {
+            callback();
+        }
     }
 }
 
@@ -126,7 +233,6 @@ impl GnomeMonitorConfig {
         type HashModes = HashMap<Size, (String, HashSet<(u32, String)>, Vec<f64>)>;
         let mut monitors = Vec::new();
         let mut monitor_iter = self.monitors.into_iter();
-        let mut logical_iter = self.logical_monitors.into_iter().peekable();
         let mut count = 0;
         let features = gnome_features();
         loop {
@@ -161,10 +267,10 @@ impl GnomeMonitorConfig {
                 if let Some(saved_mode) = hash_modes.get_mut(&Size(mode.width, mode.height)) {
                     saved_mode
                         .1
-                        .insert((mode.refresh_rate.round() as u32, mode.id.clone()));
+                        .insert((to_millihertz(mode.refresh_rate), mode.id.clone()));
                 } else {
                     let mut refresh_rates = HashSet::new();
-                    refresh_rates.insert((mode.refresh_rate.round() as u32, mode.id.clone()));
+                    refresh_rates.insert((to_millihertz(mode.refresh_rate), mode.id.clone()));
                     hash_modes.insert(
                         Size(mode.width, mode.height),
                         (
@@ -215,25 +321,29 @@ impl GnomeMonitorConfig {
             monitor.name.connector.hash(&mut hasher);
             let id = hasher.finish();
 
-            let mut enabled = false;
-            let maybe_logical_monitor = logical_iter.peek();
-            if let Some(logical_monitor) = maybe_logical_monitor {
-                for names in logical_monitor._monitors.iter() {
-                    if names.0 == monitor.name.connector {
-                        enabled = true;
-                    }
-                }
-            }
-            if enabled {
-                let logical_monitor = logical_iter.next().unwrap();
+            // gnome mirrors monitors by placing them within the same logical monitor, the first
+            // monitor is treated as the source
+            let logical_monitor = self.logical_monitors.iter().find(|logical_monitor| {
+                logical_monitor
+                    .monitors
+                    .iter()
+                    .any(|names| names.0 == monitor.name.connector)
+            });
+            if let Some(logical_monitor) = logical_monitor {
+                let source = &logical_monitor.monitors.first().unwrap().0;
+                let mirror = if *source != monitor.name.connector {
+                    source.clone()
+                } else {
+                    "".into()
+                };
                 monitors.push(Monitor {
                     id: id as u32,
-                    enabled,
+                    enabled: true,
                     name: monitor.name.connector,
                     make: monitor.name.vendor,
                     model: monitor.name.product,
                     serial: monitor.name.serial,
-                    refresh_rate: current_mode.refresh_rate.round() as u32,
+                    refresh_rate: to_millihertz(current_mode.refresh_rate),
                     scale: logical_monitor.scale,
                     transform: logical_monitor.transform,
                     vrr,
@@ -245,17 +355,23 @@ impl GnomeMonitorConfig {
                     available_modes: modes,
                     uses_mode_id: true,
                     features,
+                    subpixel: "".into(),
+                    max_render_time: 0,
+                    mirror,
+                    custom_mode: CustomMode::default(),
+                    hdr: HdrSettings::default(),
+                    edid: EdidInfo::default(),
                 });
             } else {
                 count += 1;
                 monitors.push(Monitor {
                     id: id as u32,
-                    enabled,
+                    enabled: false,
                     name: monitor.name.connector,
                     make: monitor.name.vendor,
                     model: monitor.name.product,
                     serial: monitor.name.serial,
-                    refresh_rate: current_mode.refresh_rate.round() as u32,
+                    refresh_rate: to_millihertz(current_mode.refresh_rate),
                     scale: 1.0,
                     transform: 0,
                     vrr,
@@ -267,6 +383,12 @@ impl GnomeMonitorConfig {
                     available_modes: modes,
                     uses_mode_id: true,
                     features,
+                    subpixel: "".into(),
+                    max_render_time: 0,
+                    mirror: "".into(),
+                    custom_mode: CustomMode::default(),
+                    hdr: HdrSettings::default(),
+                    edid: EdidInfo::default(),
                 });
             }
         }
@@ -276,11 +398,11 @@ impl GnomeMonitorConfig {
     pub fn from_regular_monitor(
         serial: u32,
         apply_mode: u32,
-        monitors: &Vec<Monitor>,
+        monitors: &[Monitor],
     ) -> (u32, u32, Vec<GnomeLogicalMonitorSend>, PropMap) {
-        let mut g_logical_monitors = Vec::new();
+        let mut g_logical_monitors: Vec<GnomeLogicalMonitorSend> = Vec::new();
         for monitor in monitors {
-            if !monitor.enabled {
+            if !monitor.enabled || mirror_source(monitor, monitors).is_some() {
                 continue;
             }
             g_logical_monitors.push(GnomeLogicalMonitorSend {
@@ -292,6 +414,33 @@ impl GnomeMonitorConfig {
                 monitors: vec![(monitor.name.clone(), monitor.mode.clone(), PropMap::new())],
             });
         }
+        // mirrored monitors are added to the logical monitor of their source, gnome requires
+        // the same resolution for all monitors within a logical monitor
+        for monitor in monitors {
+            let source = mirror_source(monitor, monitors);
+            if !monitor.enabled || source.is_none() {
+                continue;
+            }
+            let source = source.unwrap();
+            let mut mirrored = monitor.clone();
+            if !use_source_mode(&mut mirrored, source) {
+                ERROR!(
+                    format!(
+                        "{} does not support the resolution of {}, mirroring is not possible",
+                        monitor.name, source.name
+                    ),
+                    ErrorLevel::Recoverable
+                );
+                continue;
+            }
+            let logical_monitor = g_logical_monitors
+                .iter_mut()
+                .find(|val| val.monitors[0].0 == source.name)
+                .unwrap();
+            logical_monitor
+                .monitors
+                .push((mirrored.name, mirrored.mode, PropMap::new()));
+        }
         (serial, apply_mode, g_logical_monitors, PropMap::new())
     }
 }
@@ -365,7 +514,7 @@ pub struct GnomeLogicalMonitor {
     pub scale: f64,
     pub transform: u32,
     pub primary: bool,
-    pub _monitors: Vec<(String, String, String, String)>,
+    pub monitors: Vec<(String, String, String, String)>,
     pub _properties: PropMap,
 }
 
@@ -386,7 +535,7 @@ impl<'a> Get<'a> for GnomeLogicalMonitor {
             scale,
             transform,
             primary,
-            _monitors: monitors,
+            monitors,
             _properties: properties,
         })
     }
@@ -433,14 +582,18 @@ impl Arg for GnomeLogicalMonitorSend {
 
 impl Append for GnomeLogicalMonitorSend {
     fn append_by_ref(&self, iter: &mut arg::IterAppend) {
-        let monitor = self.monitors.first().unwrap();
+        let monitors: Vec<(String, String, PropMap)> = self
+            .monitors
+            .iter()
+            .map(|monitor| (monitor.0.clone(), monitor.1.clone(), PropMap::new()))
+            .collect();
         iter.append_struct(|i| {
             i.append(self.x);
             i.append(self.y);
             i.append(self.scale);
             i.append(self.transform);
             i.append(self.primary);
-            i.append(vec![(monitor.0.clone(), monitor.1.clone(), PropMap::new())]);
+            i.append(monitors);
         });
     }
 }
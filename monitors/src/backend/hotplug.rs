@@ -0,0 +1,136 @@
+// This file handles automatic re-application of layouts when the set of connected monitors changes
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use re_set_lib::{utils::config::CONFIG, ERROR, LOG};
+#[cfg(debug_assertions)]
+use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
+
+use crate::utils::{Monitor, MonitorError};
+
+use super::{
+    general::MonitorBackend,
+    profiles::{read_profiles, write_profiles, MonitorProfile},
+    validation::check_configuration,
+};
+
+const SETTLE_DELAY: Duration = Duration::from_millis(1500);
+
+fn get_layout_path() -> PathBuf {
+    let dirs = directories_next::ProjectDirs::from("org", "Xetibo", "ReSet").unwrap();
+    dirs.config_dir().join("monitor_layouts.json")
+}
+
+fn auto_apply_enabled() -> bool {
+    if let Some(config) = CONFIG.get("Monitor") {
+        if let Some(value) = config.get("auto_apply") {
+            return value.as_bool().unwrap_or(true);
+        }
+    }
+    true
+}
+
+/// Identity of a single monitor, KDE does not provide make, model or serial via kscreen, in this
+/// case the connector name is used instead.
+pub fn monitor_identity(monitor: &Monitor) -> String {
+    if monitor.make.is_empty() && monitor.model.is_empty() && monitor.serial.is_empty() {
+        return monitor.name.clone();
+    }
+    format!("{}|{}|{}", monitor.make, monitor.model, monitor.serial)
+}
+
+/// Identity of a set of monitors, independent of the order in which the backend reports them.
+pub fn fingerprint(monitors: &[Monitor]) -> String {
+    let mut identities: Vec<String> = monitors.iter().map(monitor_identity).collect();
+    identities.sort_unstable();
+    identities.join(";")
+}
+
+// remembers the layout as the last used one for exactly this set of monitors
+pub fn store_layout(monitors: &[Monitor]) {
+    if let Err(_error) = store_layout_in(&get_layout_path(), monitors) {
+        ERROR!(
+            format!("Could not store monitor layout: {}", _error),
+            ErrorLevel::Recoverable
+        );
+    }
+}
+
+/// Stores the layout in the layouts at this path.
+/// Nothing is written if the layouts can't be read, as the layouts of all other sets of monitors
+/// would be removed otherwise.
+pub fn store_layout_in(path: &Path, monitors: &[Monitor]) -> Result<(), MonitorError> {
+    if monitors.is_empty() {
+        return Ok(());
+    }
+    let mut layouts = read_profiles(path)?;
+    let layout = MonitorProfile::from_regular_monitors(fingerprint(monitors), monitors);
+    match layouts.iter().position(|val| val.name == layout.name) {
+        Some(index) => layouts[index] = layout,
+        None => layouts.push(layout),
+    }
+    write_profiles(path, &layouts)
+}
+
+pub fn get_layout(monitors: &[Monitor]) -> Option<MonitorProfile> {
+    let key = fingerprint(monitors);
+    let layouts = read_profiles(&get_layout_path());
+    if let Err(_error) = layouts {
+        ERROR!(
+            format!("Could not read monitor layouts: {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return None;
+    }
+    layouts
+        .unwrap()
+        .into_iter()
+        .find(|layout| layout.name == key)
+}
+
+/// Applies the stored layout if the set of connected monitors differs from the previous one.
+/// Returns true if a layout was applied, in which case the monitors need to be fetched again.
+pub fn handle_hotplug(
+    backend: &dyn MonitorBackend,
+    conn: Option<Arc<wayland_client::Connection>>,
+    monitors: &[Monitor],
+    previous: &mut String,
+) -> bool {
+    let current = fingerprint(monitors);
+    if current == *previous {
+        return false;
+    }
+    *previous = current;
+    if !auto_apply_enabled() {
+        return false;
+    }
+    let layout = get_layout(monitors);
+    if layout.is_none() {
+        return false;
+    }
+    let layout = layout.unwrap();
+    // compositors configure newly connected outputs on their own, wait for this to finish before
+    // overriding it with the stored layout
+    thread::sleep(SETTLE_DELAY);
+    LOG!(format!("Applying stored layout for {}", layout.name));
+    let monitors = layout.apply_to_monitors(monitors);
+    // stored layouts may no longer be valid, e.g. after a mode was removed
+    if let Err(_error) = check_configuration(backend, &monitors) {
+        ERROR!(
+            format!("Not applying stored layout {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return false;
+    }
+    if let Err(_error) = backend.apply(conn, &monitors) {
+        ERROR!(
+            format!("Could not apply stored layout {}", _error),
+            ErrorLevel::Recoverable
+        );
+    }
+    true
+}
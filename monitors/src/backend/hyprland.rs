@@ -3,59 +3,136 @@
 use re_set_lib::{utils::config::CONFIG, ERROR};
 #[cfg(debug_assertions)]
 use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
+use reset_common::command::{CommandRunner, HostCommandRunner};
 
-use crate::utils::{is_flatpak, AvailableMode, Monitor, MonitorFeatures, Size};
+use crate::utils::{
+    format_refresh_rate, to_millihertz, AvailableMode, BackendCapabilities, CustomMode,
+    HdrSettings, Monitor, MonitorError, MonitorFeatures, Size, DEFAULT_SDR_BRIGHTNESS,
+    MAX_SDR_BRIGHTNESS, MIN_SDR_BRIGHTNESS,
+};
 use std::{
     cmp::Ordering,
     collections::{HashMap, HashSet},
+    env,
     fs::OpenOptions,
-    io::Write,
+    io::{BufRead, BufReader, ErrorKind, Write},
+    os::unix::net::UnixStream,
     path::PathBuf,
-    process::{Command, Stdio},
 };
 
-use super::wlr::{wlr_apply_monitor_configuration, wlr_get_monitor_information};
+use super::{
+    general::MonitorBackend,
+    hyprland_ipc::{batch_errors, hypr_batch, hypr_request, parse_batch_reply},
+    mirror::mirror_source,
+    wlr::{wlr_apply_monitor_configuration, wlr_get_monitor_information, wlr_watch_monitors},
+};
 
 pub const HYPRFEATURES: MonitorFeatures = MonitorFeatures {
     vrr: true,
     // Hyprland has no primary monitor concept
     primary: false,
     fractional_scaling: true,
+    // color management is reported per monitor, only newer versions of hyprland support it
     hdr: false,
+    subpixel: false,
+    max_render_time: false,
+    custom_modes: true,
 };
 
-// Due to hyprland moving away from WLR, ReSet chose to fetch data via hyprctl instead.
-// The tool is also always installed for hyprland.
+#[derive(Debug)]
+pub struct HyprlandBackend;
+
+impl MonitorBackend for HyprlandBackend {
+    fn get(
+        &self,
+        conn: Option<std::sync::Arc<wayland_client::Connection>>,
+        _: &mut u32,
+    ) -> Vec<Monitor> {
+        hy_get_monitor_information(conn)
+    }
+
+    fn apply(
+        &self,
+        conn: Option<std::sync::Arc<wayland_client::Connection>>,
+        monitors: &[Monitor],
+    ) -> Result<(), MonitorError> {
+        hy_apply_monitor_information(monitors, conn)
+    }
+
+    fn save(
+        &self,
+        _: Option<std::sync::Arc<wayland_client::Connection>>,
+        monitors: &[Monitor],
+    ) -> Result<(), MonitorError> {
+        hy_save_monitor_configuration(monitors)
+    }
+
+    fn features(&self) -> MonitorFeatures {
+        HYPRFEATURES
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            persistent_save: true,
+            confirms_save: false,
+            gaps: true,
+            compact_layout: false,
+            arbitrary_scales: true,
+            native_mirroring: true,
+            // hyprctl does not report whether a monitor supports VRR
+            reports_vrr: false,
+            sdr_gamut_wideness: true,
+            features: self.features(),
+        }
+    }
+
+    fn watch(
+        &self,
+        conn: Option<std::sync::Arc<wayland_client::Connection>>,
+        callback: Box<dyn FnMut()>,
+    ) {
+        hy_watch_monitors(conn, callback)
+    }
+}
+
+// Due to hyprland moving away from WLR, ReSet chose to fetch data via the hyprland socket
+// instead. hyprctl is used as a fallback, the tool is also always installed for hyprland.
 pub fn hy_get_monitor_information(
     conn: Option<std::sync::Arc<wayland_client::Connection>>,
+) -> Vec<Monitor> {
+    hy_get_monitor_information_with(&HostCommandRunner::default(), conn)
+}
+
+pub fn hy_get_monitor_information_with(
+    runner: &dyn CommandRunner,
+    conn: Option<std::sync::Arc<wayland_client::Connection>>,
 ) -> Vec<Monitor> {
     let mut monitors = Vec::new();
-    let json = get_json();
+    let json_string = get_json(runner);
 
-    if json.is_err() {
+    if json_string.is_none() {
         return wlr_get_monitor_information(conn);
     }
 
-    let json = json.expect("Could not retrieve monitor json").stdout;
-    let json_string = String::from_utf8(json);
-    if let Ok(json_string) = json_string {
-        let hypr_monitors: Result<Vec<HyprMonitor>, _> = serde_json::from_str(&json_string);
-        if hypr_monitors.is_err() {
-            ERROR!(
-                "Failed to deserialize to monitor datastructure",
-                ErrorLevel::PartialBreakage
-            );
-            return Vec::new();
-        }
-        for monitor in hypr_monitors.unwrap() {
-            let monitor = monitor.convert_to_regular_monitor();
-            monitors.push(monitor);
-        }
-    } else {
+    let hypr_monitors: Result<Vec<HyprMonitor>, _> = serde_json::from_str(&json_string.unwrap());
+    if hypr_monitors.is_err() {
         ERROR!(
-            "Failed to get string from json",
+            "Failed to deserialize to monitor datastructure",
             ErrorLevel::PartialBreakage
         );
+        return Vec::new();
+    }
+    let hypr_monitors = hypr_monitors.unwrap();
+    // hyprland reports the id of the mirrored monitor, ReSet uses names instead
+    let names: HashMap<String, String> = hypr_monitors
+        .iter()
+        .map(|monitor| (monitor.id.to_string(), monitor.name.clone()))
+        .collect();
+    for monitor in hypr_monitors {
+        let mirror = names.get(&monitor.mirrorOf).cloned().unwrap_or_default();
+        let mut monitor = monitor.convert_to_regular_monitor();
+        monitor.mirror = mirror;
+        monitors.push(monitor);
     }
 
     monitors
@@ -63,32 +140,60 @@ pub fn hy_get_monitor_information(
 
 // The same applies to applying
 pub fn hy_apply_monitor_information(
-    monitors: &Vec<Monitor>,
+    monitors: &[Monitor],
     conn: Option<std::sync::Arc<wayland_client::Connection>>,
-) {
-    let config_string = monitor_to_configstring(monitors);
+) -> Result<(), MonitorError> {
+    hy_apply_monitor_information_with(&HostCommandRunner::default(), monitors, conn)
+}
 
-    let command = if is_flatpak() {
-        Command::new("flatpak-spawn")
-            .args(["--host", "hyprctl", "--batch", &config_string])
-            .stdout(Stdio::null())
-            .spawn()
-    } else {
-        Command::new("hyprctl")
-            .args(["--batch", &config_string])
-            .stdout(Stdio::null())
-            .spawn()
-    };
-    match command.is_err() {
-        true => {
-            wlr_apply_monitor_configuration(conn, monitors);
+pub fn hy_apply_monitor_information_with(
+    runner: &dyn CommandRunner,
+    monitors: &[Monitor],
+    conn: Option<std::sync::Arc<wayland_client::Connection>>,
+) -> Result<(), MonitorError> {
+    let commands = monitor_to_commands(monitors);
+
+    if let Some(socket) = get_hyprland_socket(".socket.sock") {
+        match hypr_batch(&socket, &commands) {
+            Ok(replies) => return check_batch_errors(&commands, &replies),
+            Err(_error) => ERROR!(
+                format!(
+                    "Could not apply monitors via socket, using hyprctl: {}",
+                    _error
+                ),
+                ErrorLevel::Recoverable
+            ),
+        }
+    }
+
+    let config_string = commands.join(";");
+    match runner.run("hyprctl", &["--batch", &config_string]) {
+        Err(error) if error.kind() == ErrorKind::TimedOut => {
+            Err(MonitorError::ApplyFailed(error.to_string()))
         }
-        false => {
-            command.unwrap();
+        Err(_) => wlr_apply_monitor_configuration(conn, monitors),
+        Ok(output) => {
+            let replies = parse_batch_reply(&output.stdout);
+            check_batch_errors(&commands, &replies)
         }
     }
 }
 
+fn check_batch_errors(commands: &[String], replies: &[String]) -> Result<(), MonitorError> {
+    let errors: Vec<String> = batch_errors(commands, replies)
+        .into_iter()
+        .map(|(command, reply)| format!("{}: {}", command, reply))
+        .collect();
+    if errors.is_empty() {
+        return Ok(());
+    }
+    ERROR!(
+        format!("Hyprland rejected {}", errors.join(", ")),
+        ErrorLevel::Recoverable
+    );
+    Err(MonitorError::ApplyFailed(errors.join(", ")))
+}
+
 fn get_default_path() -> String {
     let dirs = directories_next::ProjectDirs::from("org", "Xetibo", "ReSet").unwrap();
     let buf = dirs.config_dir().join("monitor.conf");
@@ -98,7 +203,7 @@ fn get_default_path() -> String {
 
 // saving can only be done via configuration file and hence is not supported via the wlr protocol
 // either way
-pub fn hy_save_monitor_configuration(monitors: &Vec<Monitor>) {
+pub fn hy_save_monitor_configuration(monitors: &[Monitor]) -> Result<(), MonitorError> {
     let path;
     if let Some(config) = CONFIG.get("Monitor") {
         if let Some(test) = config.get("path") {
@@ -115,8 +220,8 @@ pub fn hy_save_monitor_configuration(monitors: &Vec<Monitor>) {
         .read(true)
         .create(true)
         .truncate(true)
-        .open(PathBuf::from(path))
-        .expect("Failed to open file");
+        .open(PathBuf::from(&path))
+        .map_err(|error| MonitorError::Io(format!("{}: {}", path, error)))?;
 
     let mut monitor_string = String::new();
 
@@ -126,34 +231,52 @@ pub fn hy_save_monitor_configuration(monitors: &Vec<Monitor>) {
             monitor_string += &format!("keyword monitor {},disabled;", monitor.name);
         } else {
             monitor_string += &format!(
-                "monitor={},{}x{}@{},{}x{},{:.6},transform,{},vrr,{}\n",
+                "monitor={},{},{}x{},{:.6},transform,{},vrr,{}",
                 monitor.name,
-                monitor.size.0,
-                monitor.size.1,
-                monitor.refresh_rate,
+                get_mode_string(monitor),
                 monitor.offset.0,
                 monitor.offset.1,
                 monitor.scale,
                 monitor.transform,
                 vrr
             );
+            if let Some(source) = mirror_source(monitor, monitors) {
+                monitor_string += &format!(",mirror,{}", source.name);
+            }
+            monitor_string += &get_hdr_string(monitor);
+            monitor_string += "\n";
         }
     }
 
-    input_config
-        .write_all(monitor_string.as_bytes())
-        .expect("Failed to write to file");
-    input_config.sync_all().expect("Failed to sync file");
+    input_config.write_all(monitor_string.as_bytes())?;
+    input_config.sync_all()?;
+    Ok(())
 }
 
-fn get_json() -> Result<std::process::Output, std::io::Error> {
-    if is_flatpak() {
-        Command::new("flatpak-spawn")
-            .args(["--host", "hyprctl", "monitors", "-j"])
-            .output()
-    } else {
-        Command::new("hyprctl").args(["-j", "monitors"]).output()
+// the socket is preferred, hyprctl is only used when the socket is not accessible
+// mirrored monitors are only listed with "all"
+fn get_json(runner: &dyn CommandRunner) -> Option<String> {
+    if let Some(socket) = get_hyprland_socket(".socket.sock") {
+        match hypr_request(&socket, "j/monitors all") {
+            Ok(reply) => return Some(reply),
+            Err(_error) => ERROR!(
+                format!(
+                    "Could not fetch monitors via socket, using hyprctl: {}",
+                    _error
+                ),
+                ErrorLevel::Recoverable
+            ),
+        }
     }
+    let output = runner.run("hyprctl", &["-j", "monitors", "all"]);
+    if let Err(_error) = output {
+        ERROR!(
+            format!("Could not fetch monitors via hyprctl: {}", _error),
+            ErrorLevel::PartialBreakage
+        );
+        return None;
+    }
+    Some(output.unwrap().stdout)
 }
 
 #[allow(non_snake_case)]
@@ -176,18 +299,56 @@ pub struct HyprMonitor {
     pub activelyTearing: bool,
     pub disabled: bool,
     pub availableModes: Vec<String>,
+    // either none or the id of the mirrored monitor
+    #[serde(default)]
+    pub mirrorOf: String,
+    // empty for versions without color management
+    #[serde(default)]
+    pub colorManagementPreset: String,
+    #[serde(default)]
+    pub sdrBrightness: f64,
+    #[serde(default)]
+    pub sdrSaturation: f64,
 }
 
 impl HyprMonitor {
     pub fn convert_to_regular_monitor(self) -> Monitor {
-        Monitor::new(
+        let available_modes = string_to_modes(self.availableModes);
+        // available modes are reported with 2 decimals only, the exact rate is reported for the
+        // current mode, hence the closest available rate is used
+        let mut refresh_rate = to_millihertz(self.refreshRate);
+        let closest = available_modes
+            .iter()
+            .find(|mode| mode.size == Size(self.width as i32, self.height as i32))
+            .and_then(|mode| {
+                mode.refresh_rates
+                    .iter()
+                    .map(|(rate, _)| *rate)
+                    .min_by_key(|rate| rate.abs_diff(refresh_rate))
+            })
+            .filter(|rate| rate.abs_diff(refresh_rate) <= 10);
+        // modes which are not reported by the monitor can only be custom modes
+        let custom_mode = if let Some(closest) = closest {
+            refresh_rate = closest;
+            CustomMode::default()
+        } else if !self.disabled && self.width > 0 {
+            CustomMode {
+                width: self.width as i32,
+                height: self.height as i32,
+                refresh_rate: refresh_rate as f64 / 1000.0,
+                modeline: "".into(),
+            }
+        } else {
+            CustomMode::default()
+        };
+        let mut monitor = Monitor::new(
             self.id as u32,
             !self.disabled,
             self.name,
             self.make,
             self.model,
             self.serial,
-            self.refreshRate.round() as u32,
+            refresh_rate,
             self.scale,
             self.transform as u32,
             self.vrr,
@@ -196,38 +357,110 @@ impl HyprMonitor {
             self.y as i32,
             self.width as i32,
             self.height as i32,
-            string_to_modes(self.availableModes),
+            available_modes,
             false,
             HYPRFEATURES,
-        )
+        );
+        monitor.custom_mode = custom_mode;
+        if !self.colorManagementPreset.is_empty() {
+            monitor.features.hdr = true;
+            monitor.hdr = HdrSettings {
+                enabled: HYPR_HDR_PRESETS.contains(&self.colorManagementPreset.as_str()),
+                wide_color_gamut: HYPR_WIDE_PRESETS.contains(&self.colorManagementPreset.as_str()),
+                sdr_brightness: (self.sdrBrightness * HYPR_SDR_BRIGHTNESS_SCALE).round() as u32,
+                sdr_gamut_wideness: (self.sdrSaturation - 1.0).clamp(0.0, 1.0),
+            };
+            if monitor.hdr.sdr_brightness == 0 {
+                monitor.hdr.sdr_brightness = DEFAULT_SDR_BRIGHTNESS;
+            }
+            monitor.hdr.sdr_brightness = monitor
+                .hdr
+                .sdr_brightness
+                .clamp(MIN_SDR_BRIGHTNESS, MAX_SDR_BRIGHTNESS);
+        }
+        monitor
     }
 }
 
-fn monitor_to_configstring(monitors: &Vec<Monitor>) -> String {
-    let mut strings = Vec::new();
+pub fn monitor_to_commands(monitors: &[Monitor]) -> Vec<String> {
+    let mut commands = Vec::new();
 
     for monitor in monitors {
         // re-enabled when switching is supported on the fly
         // let vrr = if monitor.vrr { 1 } else { 0 };
         if !monitor.enabled {
-            strings.push(format!("keyword monitor {},disabled;", monitor.name));
+            commands.push(format!("keyword monitor {},disabled", monitor.name));
         } else {
-            strings.push(format!(
-                "keyword monitor {},{}x{}@{},{}x{},{:.6},transform,{};",
+            let mut command = format!(
+                "keyword monitor {},{},{}x{},{:.6},transform,{}",
                 monitor.name,
-                &monitor.size.0,
-                &monitor.size.1,
-                &monitor.refresh_rate,
+                get_mode_string(monitor),
                 &monitor.offset.0,
                 &monitor.offset.1,
                 &monitor.scale,
                 &monitor.transform,
                 // vrr
-            ));
+            );
+            if let Some(source) = mirror_source(monitor, monitors) {
+                command += &format!(",mirror,{}", source.name);
+            }
+            command += &get_hdr_string(monitor);
+            commands.push(command);
         }
     }
 
-    strings.concat()
+    commands
+}
+
+// hyprland uses a multiplier for the sdr brightness, 1.0 equals the default of ReSet
+const HYPR_SDR_BRIGHTNESS_SCALE: f64 = DEFAULT_SDR_BRIGHTNESS as f64;
+
+// the default preset auto uses sRGB for 8 bit and is therefore not part of the wide presets,
+// edid depends on the monitor
+const HYPR_HDR_PRESETS: [&str; 2] = ["hdr", "hdredid"];
+const HYPR_WIDE_PRESETS: [&str; 6] = ["wide", "dcip3", "dp3", "adobe", "hdr", "hdredid"];
+
+// hdr requires a bitdepth of 10, sdr content is adjusted via brightness and saturation
+fn get_hdr_string(monitor: &Monitor) -> String {
+    if !monitor.features.hdr {
+        return "".into();
+    }
+    let hdr = &monitor.hdr;
+    let (bitdepth, preset) = if hdr.enabled {
+        (",bitdepth,10", "hdr")
+    } else if hdr.wide_color_gamut {
+        (",bitdepth,10", "wide")
+    } else {
+        ("", "srgb")
+    };
+    let mut hdr_string = format!("{},cm,{}", bitdepth, preset);
+    if hdr.enabled {
+        hdr_string += &format!(
+            ",sdrbrightness,{:.2},sdrsaturation,{:.2}",
+            hdr.sdr_brightness as f64 / HYPR_SDR_BRIGHTNESS_SCALE,
+            1.0 + hdr.sdr_gamut_wideness
+        );
+    }
+    hdr_string
+}
+
+// hyprland creates custom modes itself, either via size and refresh rate or via a modeline
+fn get_mode_string(monitor: &Monitor) -> String {
+    if let Some(custom_mode) = monitor.valid_custom_mode() {
+        if !custom_mode.modeline.is_empty() {
+            return format!("modeline {}", custom_mode.modeline);
+        }
+        return format!(
+            "{}x{}@{:.3}",
+            custom_mode.width, custom_mode.height, custom_mode.refresh_rate
+        );
+    }
+    format!(
+        "{}x{}@{}",
+        monitor.size.0,
+        monitor.size.1,
+        format_refresh_rate(monitor.refresh_rate)
+    )
 }
 
 fn string_to_modes(available_modes: Vec<String>) -> Vec<AvailableMode> {
@@ -235,18 +468,11 @@ fn string_to_modes(available_modes: Vec<String>) -> Vec<AvailableMode> {
     let mut resolutions: HashMap<&str, HashSet<(u32, String)>> = HashMap::new();
     for mode in available_modes.iter() {
         let (resolution, refresh_rate) = mode.split_once('@').unwrap();
-        let entry = resolutions.get_mut(resolution);
-        if let Some(entry) = entry {
-            let float_hz: f64 = refresh_rate.strip_suffix("Hz").unwrap().parse().unwrap();
-            let refresh_rate: u32 = float_hz.round() as u32;
-            entry.insert((refresh_rate, "".into()));
-            continue;
-        }
-        resolutions.insert(resolution, HashSet::new());
-        let entry = resolutions.get_mut(resolution).unwrap();
         let float_hz: f64 = refresh_rate.strip_suffix("Hz").unwrap().parse().unwrap();
-        let refresh_rates: u32 = float_hz.round() as u32;
-        entry.insert((refresh_rates, "".into()));
+        resolutions
+            .entry(resolution)
+            .or_default()
+            .insert((to_millihertz(float_hz), "".into()));
     }
     for (resolution, refresh_rates) in resolutions {
         let (resolution_x, resolution_y) = resolution.split_once('x').unwrap();
@@ -270,3 +496,56 @@ fn string_to_modes(available_modes: Vec<String>) -> Vec<AvailableMode> {
     });
     converted_modes
 }
+
+// Hyprland moved its sockets from /tmp to the runtime dir, both locations are checked
+pub fn get_hyprland_socket(name: &str) -> Option<PathBuf> {
+    let signature = env::var("HYPRLAND_INSTANCE_SIGNATURE").ok()?;
+    if let Ok(runtime_dir) = env::var("XDG_RUNTIME_DIR") {
+        let path = PathBuf::from(runtime_dir)
+            .join("hypr")
+            .join(&signature)
+            .join(name);
+        if path.exists() {
+            return Some(path);
+        }
+    }
+    let path = PathBuf::from("/tmp/hypr").join(&signature).join(name);
+    if path.exists() {
+        return Some(path);
+    }
+    None
+}
+
+// events are sent as EVENT>>DATA, one per line
+pub fn is_monitor_event(line: &str) -> bool {
+    let event = line.split(">>").next().unwrap_or("");
+    matches!(
+        event,
+        "monitoradded" | "monitoraddedv2" | "monitorremoved" | "configreloaded"
+    )
+}
+
+// blocks the current thread and calls the callback whenever hyprland reports a monitor change
+pub fn hy_watch_monitors(
+    conn: Option<std::sync::Arc<wayland_client::Connection>>,
+    mut callback: impl FnMut(),
+) {
+    let stream =
+        get_hyprland_socket(".socket2.sock").and_then(|path| UnixStream::connect(path).ok());
+    if stream.is_none() {
+        return wlr_watch_monitors(conn, callback);
+    }
+    let reader = BufReader::new(stream.unwrap());
+    for line in reader.lines() {
+        if let Err(_error) = line {
+            ERROR!(
+                format!("Hyprland: Stopped watching monitor changes: {}", _error),
+                ErrorLevel::Recoverable
+            );
+            return;
+        }
+        if is_monitor_event(&line.unwrap()) {
+            callback();
+        }
+    }
+}
@@ -0,0 +1,50 @@
+// This file handles the request socket of hyprland, which is used instead of spawning hyprctl
+use std::{
+    io::{Read, Write},
+    os::unix::net::UnixStream,
+    path::Path,
+    time::Duration,
+};
+
+const TIMEOUT: Duration = Duration::from_millis(5000);
+
+/// Sends a single request and returns the complete reply, hyprland closes the connection after
+/// each request.
+pub fn hypr_request(socket: &Path, request: &str) -> Result<String, std::io::Error> {
+    let mut stream = UnixStream::connect(socket)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    stream.write_all(request.as_bytes())?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    Ok(reply)
+}
+
+/// Sends all commands as one batch and returns the reply of each command in order.
+pub fn hypr_batch(socket: &Path, commands: &[String]) -> Result<Vec<String>, std::io::Error> {
+    let reply = hypr_request(socket, &format!("[[BATCH]]{}", commands.join(";")))?;
+    Ok(parse_batch_reply(&reply))
+}
+
+// replies of batched commands are separated by an empty line
+pub fn parse_batch_reply(reply: &str) -> Vec<String> {
+    reply
+        .split("\n\n")
+        .map(|val| val.trim().to_string())
+        .filter(|val| !val.is_empty())
+        .collect()
+}
+
+/// Returns each failed command together with the reply of hyprland.
+/// Missing replies are treated as failures as well.
+pub fn batch_errors(commands: &[String], replies: &[String]) -> Vec<(String, String)> {
+    let mut errors = Vec::new();
+    for (index, command) in commands.iter().enumerate() {
+        match replies.get(index) {
+            Some(reply) if reply == "ok" => (),
+            Some(reply) => errors.push((command.clone(), reply.clone())),
+            None => errors.push((command.clone(), String::from("no reply"))),
+        }
+    }
+    errors
+}
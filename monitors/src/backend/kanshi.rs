@@ -0,0 +1,540 @@
+// NOTE: This implementation is for generic wlroots compositors which have no configuration file
+// for outputs, kanshi is used to persist the configuration instead.
+
+use std::{
+    collections::HashSet,
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use re_set_lib::{utils::config::CONFIG, ERROR};
+#[cfg(debug_assertions)]
+use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
+
+use crate::utils::{format_refresh_rate, to_millihertz, CustomMode, Monitor, MonitorError};
+
+use super::{
+    mirror::{apply_mirror_fallback, detect_mirrors},
+    sway::TRANSFORMS,
+    wlr::wlr_apply_monitor_configuration,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Open,
+    Close,
+    Newline,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KanshiOutput {
+    pub criteria: String,
+    pub arguments: Vec<String>,
+}
+
+/// A profile block of a kanshi config.
+/// The byte range of the block is kept in order to replace it without touching the rest of the
+/// file, other directives of the profile like exec are kept as they are written.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KanshiProfile {
+    pub name: Option<String>,
+    pub outputs: Vec<KanshiOutput>,
+    pub directives: Vec<String>,
+    pub start: usize,
+    pub end: usize,
+}
+
+fn tokenize(content: &str) -> Vec<(Token, usize, usize)> {
+    let mut tokens = Vec::new();
+    let mut chars = content.char_indices().peekable();
+    while let Some((start, char)) = chars.next() {
+        match char {
+            '\n' => tokens.push((Token::Newline, start, start + 1)),
+            '{' => tokens.push((Token::Open, start, start + 1)),
+            '}' => tokens.push((Token::Close, start, start + 1)),
+            '#' => {
+                while let Some((_, char)) = chars.peek() {
+                    if *char == '\n' {
+                        break;
+                    }
+                    chars.next();
+                }
+            }
+            '"' => {
+                let mut word = String::new();
+                let mut end = content.len();
+                while let Some((index, char)) = chars.next() {
+                    match char {
+                        '\\' => {
+                            if let Some((_, escaped)) = chars.next() {
+                                word.push(escaped);
+                            }
+                        }
+                        '"' => {
+                            end = index + 1;
+                            break;
+                        }
+                        _ => word.push(char),
+                    }
+                }
+                tokens.push((Token::Word(word), start, end));
+            }
+            char if char.is_whitespace() => (),
+            _ => {
+                let mut word = String::from(char);
+                let mut end = start + char.len_utf8();
+                while let Some((index, char)) = chars.peek() {
+                    if char.is_whitespace() || matches!(char, '{' | '}' | '"' | '#') {
+                        break;
+                    }
+                    word.push(*char);
+                    end = index + char.len_utf8();
+                    chars.next();
+                }
+                tokens.push((Token::Word(word), start, end));
+            }
+        }
+    }
+    tokens
+}
+
+/// Parses all profile blocks of a kanshi config, other directives are ignored.
+pub fn parse_kanshi_config(content: &str) -> Vec<KanshiProfile> {
+    let mut profiles = Vec::new();
+    let mut words: Vec<String> = Vec::new();
+    let mut directive_start = None;
+    let mut directive_end = 0;
+    let mut current: Option<KanshiProfile> = None;
+    let mut depth = 0;
+
+    for (token, start, end) in tokenize(content) {
+        match token {
+            Token::Word(word) => {
+                if directive_start.is_none() {
+                    directive_start = Some(start);
+                }
+                directive_end = end;
+                words.push(word);
+            }
+            Token::Newline | Token::Open | Token::Close => {
+                if let Some(profile) = current.as_mut() {
+                    if depth == 1 && words.len() >= 2 && words[0] == "output" {
+                        profile.outputs.push(KanshiOutput {
+                            criteria: words[1].clone(),
+                            arguments: words[2..].to_vec(),
+                        });
+                    } else if depth == 1 && !words.is_empty() {
+                        let start = directive_start.unwrap_or(start);
+                        profile
+                            .directives
+                            .push(content[start..directive_end].to_string());
+                    }
+                } else if token == Token::Open && words.first().is_some_and(|val| val == "profile")
+                {
+                    current = Some(KanshiProfile {
+                        name: words.get(1).cloned(),
+                        start: directive_start.unwrap_or(start),
+                        ..Default::default()
+                    });
+                }
+                words.clear();
+                directive_start = None;
+                if token == Token::Open {
+                    depth += 1;
+                } else if token == Token::Close && depth > 0 {
+                    depth -= 1;
+                    if depth == 0 {
+                        if let Some(mut profile) = current.take() {
+                            profile.end = end;
+                            profiles.push(profile);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    profiles
+}
+
+/// kanshi identifies outputs by their description, which consists of make, model and serial.
+/// Monitors without this information are identified by their connector instead.
+pub fn output_criteria(monitor: &Monitor) -> String {
+    if monitor.make.is_empty() && monitor.model.is_empty() && monitor.serial.is_empty() {
+        return monitor.name.clone();
+    }
+    let part = |value: &String| {
+        if value.is_empty() {
+            String::from("Unknown")
+        } else {
+            value.clone()
+        }
+    };
+    format!(
+        "{} {} {}",
+        part(&monitor.make),
+        part(&monitor.model),
+        part(&monitor.serial)
+    )
+}
+
+pub fn monitor_to_kanshi_output(monitor: &Monitor) -> String {
+    kanshi_output(&output_criteria(monitor), monitor)
+}
+
+fn kanshi_output(criteria: &str, monitor: &Monitor) -> String {
+    let criteria = criteria.replace('"', "\\\"");
+    if !monitor.enabled {
+        return format!("output \"{}\" disable", criteria);
+    }
+    // kanshi does not support modelines, the resulting size and refresh rate is stored instead
+    let mut output = match monitor.valid_custom_mode() {
+        Some(custom_mode) => format!(
+            "output \"{}\" enable mode --custom {}x{}@{:.3}Hz",
+            criteria, custom_mode.width, custom_mode.height, custom_mode.refresh_rate
+        ),
+        None => format!(
+            "output \"{}\" enable mode {}x{}@{}Hz",
+            criteria,
+            monitor.size.0,
+            monitor.size.1,
+            format_refresh_rate(monitor.refresh_rate)
+        ),
+    };
+    output += &format!(
+        " position {},{} scale {:.6}",
+        monitor.offset.0, monitor.offset.1, monitor.scale
+    );
+    output += &format!(
+        " transform {} adaptive_sync {}",
+        TRANSFORMS
+            .get(monitor.transform as usize)
+            .unwrap_or(&"normal"),
+        if monitor.vrr { "on" } else { "off" }
+    );
+    output
+}
+
+// kanshi has no mirroring either, hence the fallback is stored
+pub fn monitors_to_kanshi_profile(name: &str, monitors: &[Monitor]) -> String {
+    let criteria: Vec<String> = monitors.iter().map(output_criteria).collect();
+    build_kanshi_profile(name, monitors, &criteria, &[])
+}
+
+fn build_kanshi_profile(
+    name: &str,
+    monitors: &[Monitor],
+    criteria: &[String],
+    directives: &[String],
+) -> String {
+    let mut profile = format!("profile {} {{\n", name);
+    for (monitor, criteria) in apply_mirror_fallback(monitors).iter().zip(criteria) {
+        profile += "    ";
+        profile += &kanshi_output(criteria, monitor);
+        profile += "\n";
+    }
+    for directive in directives {
+        profile += "    ";
+        profile += directive;
+        profile += "\n";
+    }
+    profile += "}";
+    profile
+}
+
+// Just like kanshi, a profile matches if every output of the profile matches exactly one of the
+// monitors. Returns the index of the monitor for each output.
+fn match_profile(profile: &KanshiProfile, monitors: &[Monitor]) -> Option<Vec<usize>> {
+    if profile.outputs.len() != monitors.len() {
+        return None;
+    }
+    let mut matched = HashSet::new();
+    let mut indices = Vec::new();
+    for output in profile.outputs.iter() {
+        let index = monitors.iter().enumerate().position(|(index, monitor)| {
+            !matched.contains(&index) && criteria_matches(&output.criteria, monitor)
+        })?;
+        matched.insert(index);
+        indices.push(index);
+    }
+    Some(indices)
+}
+
+/// Replaces the profile for this set of monitors or appends a new one, all other content of the
+/// config is kept as is.
+/// The criteria of a replaced profile are kept, hence wildcards still match the same outputs.
+pub fn merge_kanshi_config(content: &str, monitors: &[Monitor]) -> String {
+    let existing = parse_kanshi_config(content)
+        .into_iter()
+        .find_map(|profile| Some((match_profile(&profile, monitors)?, profile)));
+    match existing {
+        Some((indices, profile)) => {
+            let mut criteria: Vec<String> = monitors.iter().map(output_criteria).collect();
+            for (output, index) in profile.outputs.iter().zip(indices) {
+                criteria[index] = output.criteria.clone();
+            }
+            let name = profile
+                .name
+                .unwrap_or_else(|| default_profile_name(monitors));
+            let mut merged = String::from(&content[..profile.start]);
+            merged += &build_kanshi_profile(&name, monitors, &criteria, &profile.directives);
+            merged += &content[profile.end..];
+            merged
+        }
+        None => {
+            let mut merged = String::from(content);
+            if !merged.is_empty() && !merged.ends_with("\n\n") {
+                merged += if merged.ends_with('\n') { "\n" } else { "\n\n" };
+            }
+            merged += &monitors_to_kanshi_profile(&default_profile_name(monitors), monitors);
+            merged += "\n";
+            merged
+        }
+    }
+}
+
+fn default_profile_name(monitors: &[Monitor]) -> String {
+    let mut names: Vec<&str> = monitors
+        .iter()
+        .map(|monitor| monitor.name.as_str())
+        .collect();
+    names.sort_unstable();
+    format!("reset_{}", names.join("_"))
+}
+
+// kanshi criteria can contain * as a wildcard
+fn wildcard_matches(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    if !value.starts_with(first) {
+        return false;
+    }
+    let mut rest = &value[first.len()..];
+    let parts: Vec<&str> = parts.collect();
+    if parts.is_empty() {
+        return rest.is_empty();
+    }
+    for (index, part) in parts.iter().enumerate() {
+        if index == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+pub fn criteria_matches(criteria: &str, monitor: &Monitor) -> bool {
+    wildcard_matches(criteria, &monitor.name)
+        || wildcard_matches(criteria, &output_criteria(monitor))
+}
+
+fn parse_mode(value: &str) -> Option<(i32, i32, Option<f64>)> {
+    let value = value.strip_suffix("Hz").unwrap_or(value);
+    let (size, refresh_rate) = match value.split_once('@') {
+        Some((size, refresh_rate)) => (size, Some(refresh_rate.parse().ok()?)),
+        None => (value, None),
+    };
+    let (width, height) = size.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?, refresh_rate))
+}
+
+// the closest available refresh rate is used, if none is provided the highest one is used
+fn apply_mode(monitor: &mut Monitor, width: i32, height: i32, refresh_rate: Option<f64>) {
+    let mode = monitor
+        .available_modes
+        .iter()
+        .find(|mode| mode.size.0 == width && mode.size.1 == height);
+    if mode.is_none() {
+        ERROR!(
+            format!(
+                "Mode {}x{} is not available for {}",
+                width, height, monitor.name
+            ),
+            ErrorLevel::Recoverable
+        );
+        return;
+    }
+    let refresh_rates = &mode.unwrap().refresh_rates;
+    let selected = match refresh_rate {
+        Some(refresh_rate) => {
+            let refresh_rate = to_millihertz(refresh_rate);
+            refresh_rates
+                .iter()
+                .min_by_key(|rate| rate.0.abs_diff(refresh_rate))
+        }
+        None => refresh_rates.iter().max_by_key(|rate| rate.0),
+    };
+    if let Some((refresh_rate, id)) = selected.cloned() {
+        monitor.size.0 = width;
+        monitor.size.1 = height;
+        monitor.refresh_rate = refresh_rate;
+        monitor.custom_mode = CustomMode::default();
+        if monitor.uses_mode_id {
+            monitor.mode = id;
+        }
+    }
+}
+
+// custom modes do not need to be available, without a refresh rate the current one is used
+fn apply_custom_mode(monitor: &mut Monitor, width: i32, height: i32, refresh_rate: Option<f64>) {
+    let custom_mode = CustomMode {
+        width,
+        height,
+        refresh_rate: refresh_rate.unwrap_or(monitor.refresh_rate as f64 / 1000.0),
+        modeline: "".into(),
+    };
+    if let Err(_error) = custom_mode.validate() {
+        ERROR!(
+            format!("Invalid custom mode for {}: {}", monitor.name, _error),
+            ErrorLevel::Recoverable
+        );
+        return;
+    }
+    monitor.size.0 = width;
+    monitor.size.1 = height;
+    monitor.refresh_rate = to_millihertz(custom_mode.refresh_rate);
+    monitor.custom_mode = custom_mode;
+}
+
+pub fn apply_output_arguments(arguments: &[String], monitor: &mut Monitor) {
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "enable" => monitor.enabled = true,
+            "disable" => monitor.enabled = false,
+            "mode" => {
+                let mut value = arguments.next();
+                let custom = value.is_some_and(|val| val == "--custom");
+                if custom {
+                    value = arguments.next();
+                }
+                if let Some((width, height, refresh_rate)) = value.and_then(|val| parse_mode(val)) {
+                    if custom {
+                        apply_custom_mode(monitor, width, height, refresh_rate);
+                    } else {
+                        apply_mode(monitor, width, height, refresh_rate);
+                    }
+                }
+            }
+            "position" => {
+                let position = arguments.next().and_then(|val| val.split_once(','));
+                if let Some((x, y)) = position {
+                    monitor.offset.0 = x.parse().unwrap_or(monitor.offset.0);
+                    monitor.offset.1 = y.parse().unwrap_or(monitor.offset.1);
+                }
+            }
+            "scale" => {
+                if let Some(scale) = arguments.next().and_then(|val| val.parse().ok()) {
+                    monitor.scale = scale;
+                }
+            }
+            "transform" => {
+                if let Some(transform) = arguments
+                    .next()
+                    .and_then(|val| TRANSFORMS.iter().position(|transform| transform == val))
+                {
+                    monitor.transform = transform as u32;
+                }
+            }
+            "adaptive_sync" => {
+                let value = arguments.next().is_some_and(|val| val == "on");
+                monitor.vrr = value && monitor.features.vrr;
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Converts a kanshi profile to the currently connected monitors with the profile applied.
+/// Just like kanshi, a profile is only usable if each output matches exactly one connected
+/// monitor.
+pub fn kanshi_profile_to_monitors(
+    profile: &KanshiProfile,
+    current: &[Monitor],
+) -> Option<Vec<Monitor>> {
+    let indices = match_profile(profile, current)?;
+    let mut monitors = current.to_vec();
+    for monitor in monitors.iter_mut() {
+        monitor.mirror.clear();
+    }
+    for (output, index) in profile.outputs.iter().zip(indices) {
+        apply_output_arguments(&output.arguments, &mut monitors[index]);
+    }
+    detect_mirrors(&mut monitors);
+    Some(monitors)
+}
+
+/// Returns all profiles of the kanshi config which can be used with the connected monitors.
+pub fn get_kanshi_profiles(current: &[Monitor]) -> Vec<(String, Vec<Monitor>)> {
+    let content = fs::read_to_string(get_kanshi_path());
+    if content.is_err() {
+        return Vec::new();
+    }
+    parse_kanshi_config(&content.unwrap())
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, profile)| {
+            let monitors = kanshi_profile_to_monitors(&profile, current)?;
+            let name = profile
+                .name
+                .unwrap_or_else(|| format!("Profile {}", index + 1));
+            Some((name, monitors))
+        })
+        .collect()
+}
+
+fn get_default_path() -> PathBuf {
+    let dirs = directories_next::BaseDirs::new().unwrap();
+    dirs.config_dir().join("kanshi").join("config")
+}
+
+pub fn get_kanshi_path() -> PathBuf {
+    if let Some(config) = CONFIG.get("Monitor") {
+        if let Some(path) = config.get("kanshi_path") {
+            if let Some(path) = path.as_str() {
+                return PathBuf::from(path);
+            }
+        }
+    }
+    get_default_path()
+}
+
+/// Reads the kanshi config, a missing config is empty.
+/// Other errors are returned, as the config would otherwise be overwritten with only the new
+/// profile.
+pub fn read_kanshi_config(path: &Path) -> Result<String, MonitorError> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(content),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(String::new()),
+        Err(error) => Err(MonitorError::Io(format!("{}: {}", path.display(), error))),
+    }
+}
+
+// saving is done via the kanshi config, the configuration is still applied in order to see the
+// changes immediately
+pub fn kanshi_save_monitor_configuration(
+    conn: Option<std::sync::Arc<wayland_client::Connection>>,
+    monitors: &[Monitor],
+) -> Result<(), MonitorError> {
+    let path = get_kanshi_path();
+    let content = read_kanshi_config(&path)?;
+    let merged = merge_kanshi_config(&content, monitors);
+    let io_error =
+        |error: std::io::Error| MonitorError::Io(format!("{}: {}", path.display(), error));
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(merged.as_bytes()))
+        .map_err(io_error)?;
+    wlr_apply_monitor_configuration(conn, monitors)
+}
@@ -1,57 +1,145 @@
 use std::{
     cmp::Ordering,
     collections::{HashMap, HashSet},
-    process::Command,
+    io::ErrorKind,
 };
 
 use re_set_lib::ERROR;
 #[cfg(debug_assertions)]
 use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
+use reset_common::command::{CommandRunner, HostCommandRunner};
 
-use crate::utils::{is_flatpak, AvailableMode, Monitor, MonitorFeatures, Offset, Size};
+use crate::utils::{
+    to_millihertz, AvailableMode, BackendCapabilities, CustomMode, EdidInfo, HdrSettings, Monitor,
+    MonitorError, MonitorFeatures, Offset, Size, DEFAULT_SDR_BRIGHTNESS,
+};
 
-use super::kwin::{kwin_apply_monitor_configuration, kwin_get_monitor_information};
+use super::{
+    general::MonitorBackend,
+    kwin::{kwin_apply_monitor_configuration, kwin_get_monitor_information, kwin_watch_monitors},
+    mirror::mirror_source,
+};
 
 pub const KDE_FEATURES: MonitorFeatures = MonitorFeatures {
     // KDE supports all the features!
     vrr: true,
     primary: true,
     fractional_scaling: true,
-    hdr: true,
+    // hdr is reported per monitor
+    hdr: false,
+    subpixel: false,
+    max_render_time: false,
+    custom_modes: true,
 };
 
+#[derive(Debug)]
+pub struct KdeBackend;
+
+impl MonitorBackend for KdeBackend {
+    fn get(
+        &self,
+        conn: Option<std::sync::Arc<wayland_client::Connection>>,
+        _: &mut u32,
+    ) -> Vec<Monitor> {
+        kde_get_monitor_information(conn)
+    }
+
+    fn apply(
+        &self,
+        conn: Option<std::sync::Arc<wayland_client::Connection>>,
+        monitors: &[Monitor],
+    ) -> Result<(), MonitorError> {
+        kde_apply_monitor_config(conn, monitors)
+    }
+
+    fn save(
+        &self,
+        conn: Option<std::sync::Arc<wayland_client::Connection>>,
+        monitors: &[Monitor],
+    ) -> Result<(), MonitorError> {
+        kde_save_monitor_config(conn, monitors)
+    }
+
+    fn features(&self) -> MonitorFeatures {
+        KDE_FEATURES
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            // kscreen stores every configuration on its own
+            persistent_save: false,
+            confirms_save: false,
+            // kscreen moves monitors next to each other
+            gaps: false,
+            compact_layout: false,
+            arbitrary_scales: true,
+            native_mirroring: true,
+            reports_vrr: false,
+            sdr_gamut_wideness: false,
+            features: self.features(),
+        }
+    }
+
+    fn watch(
+        &self,
+        conn: Option<std::sync::Arc<wayland_client::Connection>>,
+        callback: Box<dyn FnMut()>,
+    ) {
+        kwin_watch_monitors(conn, callback)
+    }
+}
+
 pub fn kde_get_monitor_information(
     conn: Option<std::sync::Arc<wayland_client::Connection>>,
+) -> Vec<Monitor> {
+    kde_get_monitor_information_with(&HostCommandRunner::default(), conn)
+}
+
+pub fn kde_get_monitor_information_with(
+    runner: &dyn CommandRunner,
+    conn: Option<std::sync::Arc<wayland_client::Connection>>,
 ) -> Vec<Monitor> {
     let mut monitors = Vec::new();
-    let json = get_json();
+    let json = get_json(runner);
     if json.is_none() {
         // if kscreen is not installed fall back to protocol
         return kwin_get_monitor_information(conn);
     }
-    let json = json.unwrap();
-    let kde_monitors: KDEMonitorConfiguration =
-        serde_json::from_str(&String::from_utf8(json).expect("Could not parse json"))
-            .expect("Could not parse json");
+    let kde_monitors: Result<KDEMonitorConfiguration, serde_json::Error> =
+        serde_json::from_str(&json.unwrap());
+    if let Err(_error) = kde_monitors {
+        ERROR!(
+            format!("Could not parse the kscreen-doctor output: {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return kwin_get_monitor_information(conn);
+    }
+    let kde_monitors = kde_monitors.unwrap();
+    // kscreen reports the id of the replication source, ReSet uses names instead
+    let names: HashMap<u32, String> = kde_monitors
+        .outputs
+        .iter()
+        .map(|monitor| (monitor.id, monitor.name.clone()))
+        .collect();
     for monitor in kde_monitors.outputs {
         if !monitor.modes.is_empty() {
-            let monitor = monitor.convert_to_regular_monitor();
-            monitors.push(monitor);
+            let mirror = names
+                .get(&monitor.replicationSource)
+                .cloned()
+                .unwrap_or_default();
+            // outputs without a current mode can't be shown
+            if let Some(mut monitor) = monitor.convert_to_regular_monitor() {
+                monitor.mirror = mirror;
+                monitors.push(monitor);
+            }
         }
     }
     monitors
 }
 
-fn get_json() -> Option<Vec<u8>> {
-    let command = if is_flatpak() {
-        Command::new("flatpak-spawn")
-            .args(["--host", "kscreen-doctor", "-j"])
-            .output()
-    } else {
-        Command::new("kscreen-doctor").args(["-j"]).output()
-    };
-    if let Ok(command) = command {
-        return Some(command.stdout);
+fn get_json(runner: &dyn CommandRunner) -> Option<String> {
+    if let Ok(output) = runner.run("kscreen-doctor", &["-j"]) {
+        return Some(output.stdout);
     }
     ERROR!(
         "Kscreen is not installed, please install kscreen for kde.",
@@ -62,33 +150,35 @@ fn get_json() -> Option<Vec<u8>> {
 
 pub fn kde_apply_monitor_config(
     conn: Option<std::sync::Arc<wayland_client::Connection>>,
-    monitors: &Vec<Monitor>,
-) {
-    kde_save_monitor_config(conn, monitors);
+    monitors: &[Monitor],
+) -> Result<(), MonitorError> {
+    kde_save_monitor_config(conn, monitors)
 }
 
+// kscreen-doctor is awaited in order to report rejected configurations
 pub fn kde_save_monitor_config(
     conn: Option<std::sync::Arc<wayland_client::Connection>>,
-    monitors: &Vec<Monitor>,
-) {
-    let args = convert_modes_to_kscreen_string(monitors);
+    monitors: &[Monitor],
+) -> Result<(), MonitorError> {
+    kde_save_monitor_config_with(&HostCommandRunner::default(), conn, monitors)
+}
 
-    let command = if is_flatpak() {
-        let concat_strings: Vec<String> = args
-            .into_iter()
-            .map(|mut val| {
-                val.push(' ');
-                val
-            })
-            .collect();
-        Command::new("flatpak-spawn")
-            .args(["--host", "kscreen-doctor", &concat_strings.concat()])
-            .spawn()
-    } else {
-        Command::new("kscreen-doctor").args(&args).spawn()
-    };
-    if command.is_err() {
-        kwin_apply_monitor_configuration(conn, monitors);
+pub fn kde_save_monitor_config_with(
+    runner: &dyn CommandRunner,
+    conn: Option<std::sync::Arc<wayland_client::Connection>>,
+    monitors: &[Monitor],
+) -> Result<(), MonitorError> {
+    let args = convert_modes_to_kscreen_string(monitors);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match runner.run("kscreen-doctor", &args) {
+        Err(error) if error.kind() == ErrorKind::TimedOut => {
+            Err(MonitorError::ApplyFailed(error.to_string()))
+        }
+        Err(_) => kwin_apply_monitor_configuration(conn, monitors),
+        Ok(output) if !output.success => {
+            Err(MonitorError::ApplyFailed(output.stderr.trim().to_string()))
+        }
+        Ok(_) => Ok(()),
     }
 }
 
@@ -112,11 +202,21 @@ pub struct KDEMonitor {
     pub vrrPolicy: Option<u32>,
     pub currentModeId: String,
     pub modes: Vec<KDEMode>,
+    // 0 when the output is not replicating another output
+    #[serde(default)]
+    pub replicationSource: u32,
+    // like VRR, the hdr values are only reported for monitors which support hdr
+    #[serde(default)]
+    pub hdr: Option<bool>,
+    #[serde(default)]
+    pub wcg: Option<bool>,
+    #[serde(default, rename = "sdr-brightness")]
+    pub sdr_brightness: Option<u32>,
 }
 
 impl KDEMonitor {
-    pub fn convert_to_regular_monitor(self) -> Monitor {
-        let modes = convert_modes(&self.currentModeId, self.modes);
+    pub fn convert_to_regular_monitor(self) -> Option<Monitor> {
+        let modes = convert_modes(&self.currentModeId, self.modes)?;
         let vrr = if let Some(_vrr) = self.vrrPolicy {
             // NOTE: KDE offers more than 2 options, to make them compatible with ReSet
             // only offer on or off
@@ -126,7 +226,7 @@ impl KDEMonitor {
             // monitor can't handle VRR either way
             false
         };
-        Monitor {
+        Some(Monitor {
             id: self.id,
             enabled: self.enabled,
             name: self.name,
@@ -134,7 +234,7 @@ impl KDEMonitor {
             make: "".into(),
             model: "".into(),
             serial: "".into(),
-            refresh_rate: modes.1.refreshRate.round() as u32,
+            refresh_rate: to_millihertz(modes.1.refreshRate),
             scale: self.scale,
             transform: convert_to_regular_transform(self.rotation),
             vrr,
@@ -145,8 +245,26 @@ impl KDEMonitor {
             mode: self.currentModeId,
             available_modes: modes.0,
             uses_mode_id: false,
-            features: KDE_FEATURES,
-        }
+            features: MonitorFeatures {
+                hdr: self.hdr.is_some(),
+                ..KDE_FEATURES
+            },
+            subpixel: "".into(),
+            max_render_time: 0,
+            mirror: "".into(),
+            custom_mode: CustomMode::default(),
+            hdr: match self.hdr {
+                Some(enabled) => HdrSettings {
+                    enabled,
+                    wide_color_gamut: self.wcg.unwrap_or(false),
+                    sdr_brightness: self.sdr_brightness.unwrap_or(DEFAULT_SDR_BRIGHTNESS),
+                    // not provided by kscreen
+                    sdr_gamut_wideness: 0.0,
+                },
+                None => HdrSettings::default(),
+            },
+            edid: EdidInfo::default(),
+        })
     }
 }
 
@@ -207,7 +325,7 @@ pub struct KDESize {
 fn convert_modes(
     current_mode_id: &String,
     kde_modes: Vec<KDEMode>,
-) -> (Vec<AvailableMode>, KDEMode) {
+) -> Option<(Vec<AvailableMode>, KDEMode)> {
     type HashModes = HashMap<(i32, i32), (HashSet<(u32, String)>, String)>;
     let mut modes = Vec::new();
     let mut current_mode: Option<KDEMode> = None;
@@ -220,10 +338,10 @@ fn convert_modes(
         if let Some(hash_mode) = hash_modes.get_mut(&(mode.size.width, mode.size.height)) {
             hash_mode
                 .0
-                .insert((mode.refreshRate.round() as u32, "".into()));
+                .insert((to_millihertz(mode.refreshRate), mode.id.clone()));
         } else {
             let mut refresh_rates = HashSet::new();
-            refresh_rates.insert((mode.refreshRate.round() as u32, "".into()));
+            refresh_rates.insert((to_millihertz(mode.refreshRate), mode.id.clone()));
             hash_modes.insert(
                 (mode.size.width, mode.size.height),
                 (refresh_rates, mode.id),
@@ -257,10 +375,38 @@ fn convert_modes(
         }
     });
 
-    (modes, current_mode.unwrap())
+    if current_mode.is_none() {
+        ERROR!(
+            format!("Current mode {} is not offered by kscreen", current_mode_id),
+            ErrorLevel::Recoverable
+        );
+        return None;
+    }
+    Some((modes, current_mode.unwrap()))
+}
+
+// modes are selected by id, as kscreen mode names only contain the rounded refresh rate
+fn get_mode_string(monitor: &Monitor) -> String {
+    let id = monitor
+        .available_modes
+        .iter()
+        .filter(|mode| mode.size == monitor.size)
+        .flat_map(|mode| mode.refresh_rates.iter())
+        .find(|(refresh_rate, _)| *refresh_rate == monitor.refresh_rate)
+        .map(|(_, id)| id.clone())
+        .unwrap_or_default();
+    if !id.is_empty() && monitor.valid_custom_mode().is_none() {
+        return id;
+    }
+    format!(
+        "{}x{}@{}",
+        monitor.size.0,
+        monitor.size.1,
+        (monitor.refresh_rate + 500) / 1000
+    )
 }
 
-fn convert_modes_to_kscreen_string(monitors: &Vec<Monitor>) -> Vec<String> {
+pub fn convert_modes_to_kscreen_string(monitors: &[Monitor]) -> Vec<String> {
     let mut kscreen = Vec::new();
     let mut count = 2;
 
@@ -286,19 +432,38 @@ fn convert_modes_to_kscreen_string(monitors: &Vec<Monitor>) -> Vec<String> {
                 count += 1;
             }
             kscreen.push(start.clone() + "enable");
-            kscreen.push(
-                start.clone()
-                    + &format!(
-                        "mode.{}x{}@{}",
-                        monitor.size.0, monitor.size.1, monitor.refresh_rate
-                    ),
-            );
+            // kscreen creates the custom mode first, modelines are not supported
+            if let Some(custom_mode) = monitor.valid_custom_mode() {
+                kscreen.push(
+                    start.clone()
+                        + &format!(
+                            "addCustomMode.{}.{}.{}.full",
+                            custom_mode.width,
+                            custom_mode.height,
+                            custom_mode.refresh_millihertz()
+                        ),
+                );
+            }
+            kscreen.push(start.clone() + &format!("mode.{}", get_mode_string(monitor)));
             kscreen.push(start.clone() + &format!("scale.{}", monitor.scale));
             kscreen.push(start.clone() + &format!("priority.{}", priority));
             kscreen.push(
                 start.clone() + &format!("position.{},{}", monitor.offset.0, monitor.offset.1),
             );
-            kscreen.push(start + &format!("rotation.{}", rotation));
+            kscreen.push(start.clone() + &format!("rotation.{}", rotation));
+            if monitor.features.hdr {
+                let state = |enabled| if enabled { "enable" } else { "disable" };
+                kscreen.push(start.clone() + &format!("hdr.{}", state(monitor.hdr.enabled)));
+                kscreen
+                    .push(start.clone() + &format!("wcg.{}", state(monitor.hdr.wide_color_gamut)));
+                kscreen.push(
+                    start.clone() + &format!("sdr-brightness.{}", monitor.hdr.sdr_brightness),
+                );
+            }
+            match mirror_source(monitor, monitors) {
+                Some(source) => kscreen.push(start + &format!("mirror.{}", source.name)),
+                None => kscreen.push(start + "mirror.none"),
+            }
         }
     }
 
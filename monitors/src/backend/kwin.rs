@@ -23,7 +23,15 @@ use wayland_protocols_plasma::output_management::v2::client::kde_output_manageme
 use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
 use re_set_lib::{ERROR, LOG};
 
-use crate::utils::{AvailableMode, Monitor, MonitorFeatures, Offset, Size};
+use crate::utils::{
+    AvailableMode, BackendCapabilities, CustomMode, EdidInfo, HdrSettings, Monitor, MonitorError,
+    MonitorFeatures, Offset, Size,
+};
+
+use super::{
+    general::MonitorBackend,
+    mirror::{apply_mirror_fallback, detect_mirrors},
+};
 
 const FEATURES: MonitorFeatures = MonitorFeatures {
     vrr: true,
@@ -31,6 +39,10 @@ const FEATURES: MonitorFeatures = MonitorFeatures {
     primary: false,
     fractional_scaling: true,
     hdr: false,
+    subpixel: false,
+    max_render_time: false,
+    // the protocol has no custom modes
+    custom_modes: false,
 };
 
 struct CurrentMode {
@@ -48,6 +60,8 @@ struct AppData {
     heads: HashMap<u32, KWinMonitor>,
     current_monitor: u32,
     current_mode_key: (i32, i32),
+    // set once the compositor reports the result of an applied configuration
+    configuration_result: Option<Result<(), String>>,
 }
 
 #[derive(Debug)]
@@ -117,15 +131,8 @@ impl Dispatch<KdeOutputDeviceModeV2, CurrentMode> for AppData {
                 }
             }
             OutputModeEvent::Refresh { refresh } => {
-                let refresh = refresh / 1000;
-                let remainder = refresh % 10;
-                let refresh_rate = match remainder {
-                    0..=4 => refresh - remainder,
-                    5 => refresh,
-                    6..=9 => refresh + 10 - remainder,
-                    _ => unreachable!(),
-                };
-                let refresh_rate = refresh_rate as u32;
+                // the protocol already reports millihertz
+                let refresh_rate = refresh as u32;
                 current.refresh_rate.replace(refresh_rate);
                 let len;
                 let new;
@@ -241,7 +248,7 @@ impl Dispatch<KdeOutputDeviceV2, ()> for AppData {
 
 impl Dispatch<KdeOutputConfigurationV2, ()> for AppData {
     fn event(
-        _state: &mut Self,
+        state: &mut Self,
         _: &KdeOutputConfigurationV2,
         event: OutputConfigurationEvent,
         _: &(),
@@ -249,9 +256,13 @@ impl Dispatch<KdeOutputConfigurationV2, ()> for AppData {
         _: &QueueHandle<AppData>,
     ) {
         match event {
-            OutputConfigurationEvent::Applied => LOG!("applied configuration"),
+            OutputConfigurationEvent::Applied => {
+                LOG!("applied configuration");
+                state.configuration_result = Some(Ok(()));
+            }
             OutputConfigurationEvent::Failed => {
                 ERROR!("Could not apply configuration", ErrorLevel::Recoverable);
+                state.configuration_result = Some(Err("rejected by the compositor".into()));
             }
             _ => unreachable!(),
         }
@@ -294,6 +305,56 @@ impl Dispatch<wl_callback::WlCallback, ()> for AppData {
     }
 }
 
+// KWin stores every applied configuration on its own
+#[derive(Debug)]
+pub struct KWinBackend;
+
+impl MonitorBackend for KWinBackend {
+    fn get(&self, conn: Option<Arc<wayland_client::Connection>>, _: &mut u32) -> Vec<Monitor> {
+        kwin_get_monitor_information(conn)
+    }
+
+    fn apply(
+        &self,
+        conn: Option<Arc<wayland_client::Connection>>,
+        monitors: &[Monitor],
+    ) -> Result<(), MonitorError> {
+        kwin_apply_monitor_configuration(conn, monitors)
+    }
+
+    fn save(
+        &self,
+        conn: Option<Arc<wayland_client::Connection>>,
+        monitors: &[Monitor],
+    ) -> Result<(), MonitorError> {
+        kwin_apply_monitor_configuration(conn, monitors)
+    }
+
+    fn features(&self) -> MonitorFeatures {
+        FEATURES
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            // the protocol only offers temporary configurations
+            persistent_save: false,
+            confirms_save: false,
+            gaps: true,
+            compact_layout: false,
+            arbitrary_scales: true,
+            // the protocol has no native mirroring, see mirror.rs
+            native_mirroring: false,
+            reports_vrr: true,
+            sdr_gamut_wideness: false,
+            features: self.features(),
+        }
+    }
+
+    fn watch(&self, conn: Option<Arc<wayland_client::Connection>>, callback: Box<dyn FnMut()>) {
+        kwin_watch_monitors(conn, callback)
+    }
+}
+
 pub fn kwin_get_monitor_information(conn: Option<Arc<wayland_client::Connection>>) -> Vec<Monitor> {
     if conn.is_none() {
         LOG!("KWIN: No wayland information without wayland connection");
@@ -308,6 +369,7 @@ pub fn kwin_get_monitor_information(conn: Option<Arc<wayland_client::Connection>
         heads: HashMap::new(),
         current_monitor: 0,
         current_mode_key: (0, 0),
+        configuration_result: None,
     };
 
     for global in globals.contents().clone_list() {
@@ -390,26 +452,36 @@ pub fn kwin_get_monitor_information(conn: Option<Arc<wayland_client::Connection>
             available_modes: modes,
             uses_mode_id: true,
             features: FEATURES,
+            subpixel: "".into(),
+            max_render_time: 0,
+            mirror: "".into(),
+            custom_mode: CustomMode::default(),
+            hdr: HdrSettings::default(),
+            edid: EdidInfo::default(),
         };
         monitors.push(monitor);
     }
+    detect_mirrors(&mut monitors);
     monitors
 }
 
 pub fn kwin_apply_monitor_configuration(
     conn: Option<Arc<wayland_client::Connection>>,
     monitors: &[Monitor],
-) {
+) -> Result<(), MonitorError> {
     if conn.is_none() {
-        return;
+        return Err(MonitorError::MissingTool("Wayland connection".into()));
     }
+    // neither protocol supports mirroring
+    let monitors = apply_mirror_fallback(monitors);
     let conn = conn.clone().unwrap();
-    let (globals, mut queue) = registry_queue_init::<AppData>(&conn).unwrap();
+    let (globals, mut queue) = registry_queue_init::<AppData>(&conn)
+        .map_err(|error| MonitorError::MissingTool(format!("Wayland registry ({})", error)))?;
     let handle = queue.handle();
 
     let manager = globals.bind::<KdeOutputManagementV2, _, _>(&handle, 1..=2, ());
     if manager.is_err() {
-        return;
+        return Err(MonitorError::MissingTool("kde_output_management_v2".into()));
     }
     let configuration = manager.unwrap().create_configuration(&handle, ());
 
@@ -417,6 +489,7 @@ pub fn kwin_apply_monitor_configuration(
         heads: HashMap::new(),
         current_monitor: 0,
         current_mode_key: (0, 0),
+        configuration_result: None,
     };
 
     for global in globals.contents().clone_list() {
@@ -451,7 +524,7 @@ pub fn kwin_apply_monitor_configuration(
             data.current_monitor = len;
             data.heads.insert(len, monitor);
 
-            queue.blocking_dispatch(&mut data).unwrap();
+            queue.blocking_dispatch(&mut data).map_err(apply_failed)?;
         }
     }
 
@@ -466,11 +539,21 @@ pub fn kwin_apply_monitor_configuration(
                 }
                 configuration.enable(&current_head, 1);
 
-                let current_mode = monitor.mode.parse::<u32>().unwrap();
-                let mode_id = head.hash_modes.get(&current_mode).unwrap();
+                let mode_id = monitor
+                    .mode
+                    .parse::<u32>()
+                    .ok()
+                    .and_then(|mode| head.hash_modes.get(&mode));
+                if mode_id.is_none() {
+                    configuration.destroy();
+                    return Err(MonitorError::InvalidConfiguration(format!(
+                        "{}: mode {} does not exist",
+                        monitor.name, monitor.mode
+                    )));
+                }
                 configuration.mode(
                     &current_head,
-                    &KdeOutputDeviceModeV2::from_id(&conn, mode_id.clone()).unwrap(),
+                    &KdeOutputDeviceModeV2::from_id(&conn, mode_id.unwrap().clone()).unwrap(),
                 );
 
                 configuration.transform(&current_head, monitor.transform as i32);
@@ -489,5 +572,127 @@ pub fn kwin_apply_monitor_configuration(
         }
     }
     configuration.apply();
-    queue.blocking_dispatch(&mut data).unwrap();
+    // other events may arrive before the result of the configuration
+    while data.configuration_result.is_none() {
+        queue.blocking_dispatch(&mut data).map_err(apply_failed)?;
+    }
+    data.configuration_result
+        .unwrap()
+        .map_err(MonitorError::ApplyFailed)
+}
+
+fn apply_failed(error: impl std::fmt::Display) -> MonitorError {
+    MonitorError::ApplyFailed(error.to_string())
+}
+
+// separate state for watching changes, the full AppData is only needed for fetching and applying
+struct WatchData {
+    changed: bool,
+}
+
+impl Dispatch<KdeOutputDeviceV2, ()> for WatchData {
+    fn event(
+        state: &mut Self,
+        _: &KdeOutputDeviceV2,
+        event: Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<WatchData>,
+    ) {
+        // done is sent after every change to the output
+        if let Event::Done = event {
+            state.changed = true;
+        }
+    }
+
+    fn event_created_child(_: u16, _qhandle: &QueueHandle<Self>) -> Arc<dyn ObjectData> {
+        _qhandle.make_data::<KdeOutputDeviceModeV2, _>(())
+    }
+}
+
+impl Dispatch<KdeOutputDeviceModeV2, ()> for WatchData {
+    fn event(
+        _: &mut Self,
+        _: &KdeOutputDeviceModeV2,
+        _: OutputModeEvent,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<WatchData>,
+    ) {
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for WatchData {
+    fn event(
+        state: &mut WatchData,
+        registry: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        handle: &QueueHandle<WatchData>,
+    ) {
+        match event {
+            // newly connected outputs are announced as new globals
+            wl_registry::Event::Global {
+                name, interface, ..
+            } if &interface[..] == "kde_output_device_v2" => {
+                registry.bind::<KdeOutputDeviceV2, _, _>(name, 2, handle, ());
+                state.changed = true;
+            }
+            wl_registry::Event::GlobalRemove { .. } => {
+                state.changed = true;
+            }
+            _ => (),
+        }
+    }
+}
+
+// blocks the current thread and calls the callback whenever the compositor reports a change
+pub fn kwin_watch_monitors(
+    conn: Option<Arc<wayland_client::Connection>>,
+    mut callback: impl FnMut(),
+) {
+    if conn.is_none() {
+        ERROR!(
+            "KWIN: Could not watch monitor changes without wayland connection",
+            ErrorLevel::Recoverable
+        );
+        return;
+    }
+    let registry = registry_queue_init::<WatchData>(&conn.unwrap());
+    if let Err(_error) = registry {
+        ERROR!(
+            format!("KWIN: Could not watch monitor changes: {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return;
+    }
+    let (globals, mut queue) = registry.unwrap();
+    let handle = queue.handle();
+    for global in globals.contents().clone_list() {
+        if &global.interface[..] == "kde_output_device_v2" {
+            globals
+                .registry()
+                .bind::<KdeOutputDeviceV2, _, _>(global.name, 2, &handle, ());
+        }
+    }
+    let mut data = WatchData { changed: false };
+    // the initial state is also terminated with done, this is not a change
+    if queue.roundtrip(&mut data).is_err() {
+        return;
+    }
+    data.changed = false;
+    loop {
+        if let Err(_error) = queue.blocking_dispatch(&mut data) {
+            ERROR!(
+                format!("KWIN: Stopped watching monitor changes: {}", _error),
+                ErrorLevel::Recoverable
+            );
+            return;
+        }
+        if data.changed {
+            data.changed = false;
+            callback();
+        }
+    }
 }
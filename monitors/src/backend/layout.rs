@@ -0,0 +1,47 @@
+// This file handles layout documents, which are used to share monitor configurations as file
+use crate::utils::{Monitor, MonitorError};
+
+use super::profiles::MonitorProfile;
+
+/// Version of the layout document, documents with a newer version are rejected.
+/// The version has to be increased when existing values change their meaning, new values can be
+/// added without a new version.
+pub const LAYOUT_VERSION: u32 = 1;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct LayoutDocument {
+    pub version: u32,
+    pub monitors: Vec<Monitor>,
+}
+
+pub fn export_layout(monitors: &[Monitor]) -> Result<String, MonitorError> {
+    let document = LayoutDocument {
+        version: LAYOUT_VERSION,
+        monitors: monitors.to_vec(),
+    };
+    Ok(serde_json::to_string_pretty(&document)?)
+}
+
+pub fn parse_layout(layout: &str) -> Result<LayoutDocument, MonitorError> {
+    let document: LayoutDocument = serde_json::from_str(layout)?;
+    if document.version == 0 || document.version > LAYOUT_VERSION {
+        return Err(MonitorError::Parse(format!(
+            "unsupported layout version {}, supported up to {}",
+            document.version, LAYOUT_VERSION
+        )));
+    }
+    if document.monitors.is_empty() {
+        return Err(MonitorError::Parse(String::from(
+            "the layout does not contain any monitors",
+        )));
+    }
+    Ok(document)
+}
+
+/// Returns the currently connected monitors with the values of the layout applied.
+/// The layout may have been exported on a different machine, monitors are therefore matched the
+/// same way as profiles and modes are resolved against the connected monitors.
+pub fn import_layout(layout: &str, current: &[Monitor]) -> Result<Vec<Monitor>, MonitorError> {
+    let document = parse_layout(layout)?;
+    Ok(MonitorProfile::from_regular_monitors("", &document.monitors).apply_to_monitors(current))
+}
@@ -0,0 +1,99 @@
+// This file handles mirrored monitors, both for compositors with and without native mirroring
+use crate::utils::Monitor;
+
+/// Returns the monitor whose content is shown on the given monitor.
+/// Only enabled monitors which are not mirroring themselves can be used as a source, otherwise
+/// the monitor is treated as a regular monitor.
+pub fn mirror_source<'a>(monitor: &Monitor, monitors: &'a [Monitor]) -> Option<&'a Monitor> {
+    if monitor.mirror.is_empty() || monitor.mirror == monitor.name {
+        return None;
+    }
+    monitors
+        .iter()
+        .find(|val| val.name == monitor.mirror && val.enabled && val.mirror.is_empty())
+}
+
+/// Selects the resolution of the source with the closest available refresh rate.
+/// Returns false if the monitor does not support the resolution of the source.
+pub fn use_source_mode(monitor: &mut Monitor, source: &Monitor) -> bool {
+    let mode = monitor
+        .available_modes
+        .iter()
+        .find(|mode| mode.size == source.size);
+    if mode.is_none() {
+        return false;
+    }
+    let selected = mode
+        .unwrap()
+        .refresh_rates
+        .iter()
+        .min_by_key(|rate| rate.0.abs_diff(source.refresh_rate))
+        .cloned();
+    if selected.is_none() {
+        return false;
+    }
+    let (refresh_rate, id) = selected.unwrap();
+    monitor.size = source.size;
+    monitor.refresh_rate = refresh_rate;
+    if monitor.uses_mode_id {
+        monitor.mode = id;
+    }
+    true
+}
+
+// mirrored monitors are always shown at the position of their source
+pub fn align_mirrors(monitors: &mut [Monitor]) {
+    for index in 0..monitors.len() {
+        let offset = mirror_source(&monitors[index], monitors).map(|source| source.offset);
+        if let Some(offset) = offset {
+            monitors[index].offset = offset;
+        }
+    }
+}
+
+/// Compositors without native mirroring show the same area on both monitors instead.
+/// The mirrored monitor uses the position, mode and transform of its source, if the mode is not
+/// available, the scale is adjusted so that both monitors still cover the same area.
+pub fn apply_mirror_fallback(monitors: &[Monitor]) -> Vec<Monitor> {
+    let mut converted = monitors.to_vec();
+    for monitor in converted.iter_mut() {
+        let source = mirror_source(monitor, monitors);
+        if source.is_none() {
+            continue;
+        }
+        let source = source.unwrap();
+        monitor.offset = source.offset;
+        monitor.transform = source.transform;
+        if use_source_mode(monitor, source) {
+            monitor.scale = source.scale;
+            continue;
+        }
+        let (width, _) = monitor.handle_transform();
+        let (source_width, _) = source.handle_scaled_transform();
+        if source_width > 0 {
+            monitor.scale = width as f64 / source_width as f64;
+        }
+    }
+    converted
+}
+
+/// Counterpart of the fallback, monitors that cover the exact same area are reported as mirrors
+/// of the first of these monitors.
+pub fn detect_mirrors(monitors: &mut [Monitor]) {
+    for index in 1..monitors.len() {
+        let (previous, rest) = monitors.split_at_mut(index);
+        let monitor = &mut rest[0];
+        if !monitor.enabled {
+            continue;
+        }
+        let source = previous.iter().find(|val| {
+            val.enabled
+                && val.mirror.is_empty()
+                && val.offset == monitor.offset
+                && val.handle_scaled_transform() == monitor.handle_scaled_transform()
+        });
+        if let Some(source) = source {
+            monitor.mirror = source.name.clone();
+        }
+    }
+}
@@ -1,31 +1,52 @@
-use std::sync::{Arc, RwLock, RwLockWriteGuard};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock, RwLockWriteGuard,
+    },
+    time::Duration,
+};
 
 use dbus_crossroads::IfaceBuilder;
-#[cfg(debug_assertions)]
-use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
-use re_set_lib::{
-    utils::{plugin::PluginTestFunc, plugin_setup::CrossWrapper},
-    ERROR,
-};
+use re_set_lib::utils::{plugin::PluginTestFunc, plugin_setup::CrossWrapper};
 
-use crate::{tests::dbus_end_point, utils::{get_environment, is_gnome, Monitor, MonitorData, GNOME, HYPRLAND, KDE}};
+use crate::{
+    tests::dbus_end_point,
+    utils::{Monitor, MonitorBrightness, MonitorData, MonitorError, MonitorFeatures, NightLight},
+};
 
 use self::{
-    general::{apply_monitor_configuration, save_monitor_configuration},
-    gnome::g_get_monitor_information,
-    hyprland::hy_get_monitor_information,
-    kde::kde_get_monitor_information,
-    kwin::kwin_get_monitor_information,
-    utils::get_wl_backend,
-    wlr::wlr_get_monitor_information,
+    brightness::BrightnessData,
+    edid::add_edid_information,
+    general::select_backend,
+    hotplug::store_layout,
+    kanshi::get_kanshi_profiles,
+    layout::{export_layout, import_layout},
+    night_light::{get_night_light, set_night_light, start_night_light_scheduler},
+    profiles::{delete_profile, get_profile, list_profiles, save_profile},
+    revert::{revert_timeout, start_revert_timer, RevertData},
+    validation::{check_configuration, validate_configuration},
+    watcher::start_monitor_watcher,
 };
 
+pub mod brightness;
+pub mod edid;
 pub mod general;
 pub mod gnome;
+pub mod hotplug;
 pub mod hyprland;
+pub mod hyprland_ipc;
+pub mod kanshi;
 pub mod kde;
 pub mod kwin;
+pub mod layout;
+pub mod mirror;
+pub mod night_light;
+pub mod profiles;
+pub mod revert;
+pub mod sway;
 pub mod utils;
+pub mod validation;
+pub mod watcher;
 pub mod wlr;
 
 #[no_mangle]
@@ -51,32 +72,37 @@ pub extern "C" fn dbus_interface(cross: Arc<RwLock<CrossWrapper>>) {
     } else {
         None
     };
+    let backend = select_backend();
+    if backend.is_none() {
+        return;
+    }
+    let backend = backend.unwrap();
     let interface = setup_dbus_interface(&mut cross);
-    let env = get_environment();
     let mut serial = 0;
+    let changed = Arc::new(AtomicBool::new(false));
+    let mut monitors = backend.get(conn.clone(), &mut serial);
+    add_edid_information(&mut monitors);
     let data = MonitorData {
-        monitors: match env.as_str() {
-            HYPRLAND => hy_get_monitor_information(conn.clone()),
-            GNOME | "ubuntu:GNOME" => g_get_monitor_information(&mut serial),
-            KDE => kde_get_monitor_information(conn.clone()),
-            // fallback to protocol implementations
-            _ => match get_wl_backend().as_str() {
-                "WLR" => wlr_get_monitor_information(conn.clone()),
-                "KWIN" => kwin_get_monitor_information(conn.clone()),
-                _ => {
-                    ERROR!("Unsupported Environment", ErrorLevel::PartialBreakage);
-                    Vec::new()
-                }
-            },
-        },
-        connection: conn,
+        backend: backend.clone(),
+        monitors,
+        connection: conn.clone(),
         serial,
+        changed: changed.clone(),
+        brightness: Arc::new(Mutex::new(BrightnessData::default())),
+        night_light: Arc::new(Mutex::new(get_night_light())),
+        revert: Arc::new(Mutex::new(RevertData::default())),
     };
     if data.monitors.is_empty() {
         // means the environment is not supported
         // hence don't show the plugin
         return;
     }
+    start_night_light_scheduler(
+        conn.clone(),
+        data.brightness.clone(),
+        data.night_light.clone(),
+    );
+    start_monitor_watcher(backend, conn, changed, &data.monitors);
     cross.insert::<MonitorData>("Monitors", &[interface], data);
 }
 
@@ -96,18 +122,46 @@ pub fn setup_dbus_interface(
     cross: &mut RwLockWriteGuard<CrossWrapper>,
 ) -> dbus_crossroads::IfaceToken<MonitorData> {
     cross.register::<MonitorData>(
-        "org.Xetibo.ReSet.Monitors",
+        reset_common::monitors::INTERFACE,
         |c: &mut IfaceBuilder<MonitorData>| {
             c.method(
                 "GetMonitors",
                 (),
                 ("monitors",),
+                move |_, d: &mut MonitorData, ()| Ok((get_current_monitors(d),)),
+            );
+            c.signal::<(Vec<Monitor>,), _>("MonitorsChanged", ("monitors",));
+            // called by the monitor watcher, the signal has to be sent via the daemon connection
+            // that owns this object, which is only reachable within a method call
+            c.method(
+                "EmitMonitorsChanged",
+                (),
+                (),
+                move |ctx, d: &mut MonitorData, ()| {
+                    let signal = ctx.make_signal("MonitorsChanged", (get_current_monitors(d),));
+                    ctx.push_msg(signal);
+                    Ok(())
+                },
+            );
+            c.method(
+                "GetCapabilities",
+                (),
+                ("capabilities", "monitors"),
                 move |_, d: &mut MonitorData, ()| {
-                    if is_gnome() {
-                        Ok((g_get_monitor_information(&mut d.serial),))
-                    } else {
-                        Ok((d.monitors.clone(),))
-                    }
+                    let monitors: Vec<(String, MonitorFeatures)> = get_current_monitors(d)
+                        .into_iter()
+                        .map(|monitor| (monitor.name, monitor.features))
+                        .collect();
+                    Ok((d.backend.capabilities(), monitors))
+                },
+            );
+            c.method(
+                "ValidateMonitors",
+                ("monitors",),
+                ("ok", "errors"),
+                move |_, d: &mut MonitorData, (monitors,): (Vec<Monitor>,)| {
+                    let errors = validate_configuration(d.backend.as_ref(), &monitors);
+                    Ok((errors.is_empty(), errors))
                 },
             );
             c.method(
@@ -115,21 +169,215 @@ pub fn setup_dbus_interface(
                 ("monitors",),
                 (),
                 move |_, d: &mut MonitorData, (monitors,): (Vec<Monitor>,)| {
-                    apply_monitor_configuration(d.connection.clone(), &monitors);
+                    check_configuration(d.backend.as_ref(), &monitors)?;
+                    d.backend.apply(d.connection.clone(), &monitors)?;
+                    d.revert.lock().unwrap().cancel();
+                    store_layout(&monitors);
                     d.monitors = monitors;
                     Ok(())
                 },
             );
+            c.method(
+                "SetMonitorsWithTimeout",
+                ("monitors", "seconds"),
+                ("timeout",),
+                move |_, d: &mut MonitorData, (monitors, seconds): (Vec<Monitor>, u32)| {
+                    Ok((apply_with_timeout(d, monitors, seconds, false)?,))
+                },
+            );
             c.method(
                 "SaveMonitors",
                 ("monitors",),
                 (),
                 move |_, d: &mut MonitorData, (monitors,): (Vec<Monitor>,)| {
-                    save_monitor_configuration(d.connection.clone(), &monitors);
+                    check_configuration(d.backend.as_ref(), &monitors)?;
+                    d.backend.save(d.connection.clone(), &monitors)?;
+                    d.revert.lock().unwrap().cancel();
+                    store_layout(&monitors);
                     d.monitors = monitors;
                     Ok(())
                 },
             );
+            c.method(
+                "SaveMonitorsWithTimeout",
+                ("monitors", "seconds"),
+                ("timeout",),
+                move |_, d: &mut MonitorData, (monitors, seconds): (Vec<Monitor>, u32)| {
+                    Ok((apply_with_timeout(d, monitors, seconds, true)?,))
+                },
+            );
+            c.method(
+                "ConfirmMonitors",
+                (),
+                ("result",),
+                move |_, d: &mut MonitorData, ()| {
+                    if !d.revert.lock().unwrap().cancel() {
+                        return Ok((false,));
+                    }
+                    store_layout(&d.monitors);
+                    Ok((true,))
+                },
+            );
+            c.method(
+                "ListProfiles",
+                (),
+                ("profiles",),
+                move |_, _: &mut MonitorData, ()| Ok((list_profiles()?,)),
+            );
+            c.method(
+                "SaveProfile",
+                ("name", "monitors"),
+                ("result",),
+                move |_, _: &mut MonitorData, (name, monitors): (String, Vec<Monitor>)| {
+                    save_profile(&name, &monitors)?;
+                    Ok((true,))
+                },
+            );
+            c.method(
+                "ApplyProfile",
+                ("name", "seconds"),
+                ("result", "timeout"),
+                move |_, d: &mut MonitorData, (name, seconds): (String, u32)| {
+                    let profile = get_profile(&name)?;
+                    if profile.is_none() {
+                        return Ok((false, 0));
+                    }
+                    let current = get_current_monitors(d);
+                    let monitors = profile.unwrap().apply_to_monitors(&current);
+                    Ok((true, apply_with_timeout(d, monitors, seconds, false)?))
+                },
+            );
+            c.method(
+                "DeleteProfile",
+                ("name",),
+                ("result",),
+                move |_, _: &mut MonitorData, (name,): (String,)| Ok((delete_profile(&name)?,)),
+            );
+            c.method(
+                "GetBrightness",
+                ("name",),
+                ("brightness",),
+                move |_, d: &mut MonitorData, (name,): (String,)| {
+                    let brightness: MonitorBrightness = d
+                        .brightness
+                        .lock()
+                        .unwrap()
+                        .get_brightness(d.connection.clone(), &name);
+                    Ok((brightness,))
+                },
+            );
+            c.method(
+                "SetBrightness",
+                ("name", "brightness"),
+                ("result",),
+                move |_, d: &mut MonitorData, (name, brightness): (String, u32)| {
+                    Ok((d.brightness.lock().unwrap().set_brightness(
+                        d.connection.clone(),
+                        &name,
+                        brightness,
+                    ),))
+                },
+            );
+            c.method(
+                "SetGamma",
+                ("name", "gamma"),
+                ("result",),
+                move |_, d: &mut MonitorData, (name, gamma): (String, f64)| {
+                    Ok((d
+                        .brightness
+                        .lock()
+                        .unwrap()
+                        .set_gamma(d.connection.clone(), &name, gamma),))
+                },
+            );
+            c.method(
+                "GetNightLight",
+                (),
+                ("night_light",),
+                move |_, d: &mut MonitorData, ()| {
+                    let night_light: NightLight = d.night_light.lock().unwrap().clone();
+                    Ok((night_light,))
+                },
+            );
+            c.method(
+                "SetNightLight",
+                ("night_light",),
+                ("result",),
+                move |_, d: &mut MonitorData, (night_light,): (NightLight,)| {
+                    Ok((set_night_light(
+                        d.connection.clone(),
+                        &d.brightness,
+                        &d.night_light,
+                        night_light,
+                    ),))
+                },
+            );
+            c.method(
+                "ExportLayout",
+                (),
+                ("layout",),
+                move |_, d: &mut MonitorData, ()| {
+                    let current = get_current_monitors(d);
+                    Ok((export_layout(&current)?,))
+                },
+            );
+            c.method(
+                "ImportLayout",
+                ("layout",),
+                ("monitors",),
+                move |_, d: &mut MonitorData, (layout,): (String,)| {
+                    let current = get_current_monitors(d);
+                    Ok((import_layout(&layout, &current)?,))
+                },
+            );
+            c.method(
+                "GetKanshiProfiles",
+                (),
+                ("profiles",),
+                move |_, d: &mut MonitorData, ()| {
+                    let current = get_current_monitors(d);
+                    Ok((get_kanshi_profiles(&current),))
+                },
+            );
         },
     )
 }
+
+// gnome always needs to be fetched again, other environments only on external changes
+fn get_current_monitors(d: &mut MonitorData) -> Vec<Monitor> {
+    if !d.backend.caches_monitors() || d.changed.swap(false, Ordering::SeqCst) {
+        d.monitors = d.backend.get(d.connection.clone(), &mut d.serial);
+        add_edid_information(&mut d.monitors);
+    }
+    d.monitors.clone()
+}
+
+/// Applies the configuration and restores the current one unless ConfirmMonitors is called within
+/// the timeout, the layout is only remembered once it is confirmed.
+/// Returns the timeout in seconds, 0 uses the configured timeout.
+pub fn apply_with_timeout(
+    d: &mut MonitorData,
+    monitors: Vec<Monitor>,
+    seconds: u32,
+    persistent: bool,
+) -> Result<u32, MonitorError> {
+    check_configuration(d.backend.as_ref(), &monitors)?;
+    let previous = get_current_monitors(d);
+    if persistent {
+        d.backend.save(d.connection.clone(), &monitors)?;
+    } else {
+        d.backend.apply(d.connection.clone(), &monitors)?;
+    }
+    d.monitors = monitors;
+    let timeout = revert_timeout(seconds);
+    let generation = d.revert.lock().unwrap().start(previous, persistent);
+    start_revert_timer(
+        d.backend.clone(),
+        d.connection.clone(),
+        d.revert.clone(),
+        d.changed.clone(),
+        generation,
+        Duration::from_secs(timeout.into()),
+    );
+    Ok(timeout)
+}
@@ -0,0 +1,437 @@
+// This file handles the night light, which lowers the colour temperature of all monitors at night
+// GNOME and KDE schedule the night light themselves, ReSet only writes their configuration
+// for all other environments ReSet schedules the night light and sets the temperature itself
+use std::{
+    f64::consts::PI,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    ptr,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use re_set_lib::ERROR;
+#[cfg(debug_assertions)]
+use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
+use reset_common::command::{CommandRunner, HostCommandRunner};
+
+use crate::utils::{
+    get_environment, NightLight, GNOME, HYPRLAND, KDE, MIN_TEMPERATURE, NEUTRAL_TEMPERATURE,
+    NIGHT_LIGHT_FIXED, NIGHT_LIGHT_SUN, SWAY,
+};
+
+use super::{
+    brightness::BrightnessData, hyprland::get_hyprland_socket, hyprland_ipc::hypr_request,
+    utils::get_wl_backend,
+};
+
+const GNOME_COLOR_SCHEMA: &str = "org.gnome.settings-daemon.plugins.color";
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(60);
+const MINUTES_PER_DAY: u32 = 24 * 60;
+// the sun is considered set when its center is 0.833° below the horizon due to refraction
+const SUNSET_ZENITH: f64 = 90.833;
+
+pub fn get_night_light_path() -> PathBuf {
+    let dirs = directories_next::ProjectDirs::from("org", "Xetibo", "ReSet").unwrap();
+    dirs.config_dir().join("night_light.json")
+}
+
+pub fn get_night_light() -> NightLight {
+    read_night_light(&get_night_light_path())
+}
+
+pub fn read_night_light(path: &Path) -> NightLight {
+    if !path.is_file() {
+        return NightLight::default();
+    }
+    let content = fs::read_to_string(path);
+    if let Err(_error) = content {
+        ERROR!(
+            format!("Could not read night light settings: {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return NightLight::default();
+    }
+    let night_light: Result<NightLight, _> = serde_json::from_str(&content.unwrap());
+    if let Err(_error) = night_light {
+        ERROR!(
+            format!("Could not parse night light settings: {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return NightLight::default();
+    }
+    night_light.unwrap()
+}
+
+pub fn write_night_light(path: &Path, night_light: &NightLight) -> bool {
+    if let Some(parent) = path.parent() {
+        if let Err(_error) = fs::create_dir_all(parent) {
+            ERROR!(
+                format!("Could not create config directory: {}", _error),
+                ErrorLevel::Recoverable
+            );
+            return false;
+        }
+    }
+    let json = serde_json::to_string_pretty(night_light);
+    if let Err(_error) = json {
+        ERROR!(
+            format!("Could not serialize night light settings: {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return false;
+    }
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path);
+    if let Err(_error) = file {
+        ERROR!(
+            format!("Could not open night light settings: {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return false;
+    }
+    let mut file = file.unwrap();
+    if let Err(_error) = file.write_all(json.unwrap().as_bytes()) {
+        ERROR!(
+            format!("Could not write night light settings: {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return false;
+    }
+    true
+}
+
+/// Checks the ranges of the settings before they are stored.
+pub fn is_valid_night_light(night_light: &NightLight) -> bool {
+    (MIN_TEMPERATURE..=NEUTRAL_TEMPERATURE).contains(&night_light.temperature)
+        && (night_light.schedule == NIGHT_LIGHT_FIXED || night_light.schedule == NIGHT_LIGHT_SUN)
+        && night_light.start < MINUTES_PER_DAY
+        && night_light.end < MINUTES_PER_DAY
+        && (-90.0..=90.0).contains(&night_light.latitude)
+        && (-180.0..=180.0).contains(&night_light.longitude)
+}
+
+// stores and applies the settings, invalid settings are rejected
+pub fn set_night_light(
+    conn: Option<Arc<wayland_client::Connection>>,
+    brightness: &Arc<Mutex<BrightnessData>>,
+    current: &Arc<Mutex<NightLight>>,
+    night_light: NightLight,
+) -> bool {
+    if !is_valid_night_light(&night_light) {
+        ERROR!("Invalid night light settings", ErrorLevel::Recoverable);
+        return false;
+    }
+    if !write_night_light(&get_night_light_path(), &night_light) {
+        return false;
+    }
+    let applied = apply_night_light(conn, brightness, &night_light);
+    *current.lock().unwrap() = night_light;
+    applied
+}
+
+// GNOME and KDE offer their own night light, which is configured instead
+fn has_native_night_light() -> bool {
+    matches!(get_environment().as_str(), GNOME | "ubuntu:GNOME" | KDE)
+}
+
+pub fn apply_night_light(
+    conn: Option<Arc<wayland_client::Connection>>,
+    brightness: &Arc<Mutex<BrightnessData>>,
+    night_light: &NightLight,
+) -> bool {
+    match get_environment().as_str() {
+        GNOME | "ubuntu:GNOME" => g_apply_night_light(night_light),
+        KDE => kde_apply_night_light(night_light),
+        _ => {
+            let (minutes, day_of_year, utc_offset) = local_time();
+            let temperature = current_temperature(night_light, minutes, day_of_year, utc_offset);
+            set_temperature(conn, brightness, temperature)
+        }
+    }
+}
+
+/// Reapplies the temperature every minute for environments without a native night light.
+pub fn start_night_light_scheduler(
+    conn: Option<Arc<wayland_client::Connection>>,
+    brightness: Arc<Mutex<BrightnessData>>,
+    night_light: Arc<Mutex<NightLight>>,
+) {
+    if has_native_night_light() {
+        return;
+    }
+    thread::spawn(move || {
+        let mut previous = NEUTRAL_TEMPERATURE;
+        loop {
+            let settings = night_light.lock().unwrap().clone();
+            let (minutes, day_of_year, utc_offset) = local_time();
+            let temperature = current_temperature(&settings, minutes, day_of_year, utc_offset);
+            // lowered temperatures are reapplied for monitors that were connected in between
+            if temperature != previous || temperature != NEUTRAL_TEMPERATURE {
+                set_temperature(conn.clone(), &brightness, temperature);
+                previous = temperature;
+            }
+            thread::sleep(SCHEDULER_INTERVAL);
+        }
+    });
+}
+
+fn set_temperature(
+    conn: Option<Arc<wayland_client::Connection>>,
+    brightness: &Arc<Mutex<BrightnessData>>,
+    temperature: u32,
+) -> bool {
+    match get_environment().as_str() {
+        HYPRLAND => hy_set_temperature(temperature),
+        SWAY => brightness
+            .lock()
+            .unwrap()
+            .set_temperature(conn, temperature),
+        _ => match get_wl_backend().as_str() {
+            "WLR" => brightness
+                .lock()
+                .unwrap()
+                .set_temperature(conn, temperature),
+            _ => {
+                ERROR!(
+                    "Night light is not supported in this environment",
+                    ErrorLevel::Recoverable
+                );
+                false
+            }
+        },
+    }
+}
+
+// hyprsunset needs to be running, it offers its own socket next to the hyprland sockets
+fn hy_set_temperature(temperature: u32) -> bool {
+    let socket = get_hyprland_socket(".hyprsunset.sock");
+    if socket.is_none() {
+        ERROR!(
+            "hyprsunset is not running, please start hyprsunset for the night light.",
+            ErrorLevel::Recoverable
+        );
+        return false;
+    }
+    let request = if temperature >= NEUTRAL_TEMPERATURE {
+        "identity".to_string()
+    } else {
+        format!("temperature {}", temperature)
+    };
+    if let Err(_error) = hypr_request(&socket.unwrap(), &request) {
+        ERROR!(
+            format!("Could not set temperature via hyprsunset: {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return false;
+    }
+    true
+}
+
+fn run_command(program: &str, args: &[String]) -> bool {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match HostCommandRunner::default().run(program, &args) {
+        Ok(output) => {
+            if !output.success {
+                ERROR!(
+                    format!("{} failed: {}", program, output.stderr.trim()),
+                    ErrorLevel::Recoverable
+                );
+            }
+            output.success
+        }
+        Err(_error) => {
+            ERROR!(
+                format!("Could not run {}: {}", program, _error),
+                ErrorLevel::Recoverable
+            );
+            false
+        }
+    }
+}
+
+/// Returns the gsettings keys and values for the night light of GNOME.
+/// GNOME uses its own location for the sun schedule, the coordinates are only used until the
+/// location is known.
+pub fn gnome_night_light_settings(night_light: &NightLight) -> Vec<(&'static str, String)> {
+    vec![
+        ("night-light-enabled", night_light.enabled.to_string()),
+        (
+            "night-light-temperature",
+            night_light.temperature.to_string(),
+        ),
+        (
+            "night-light-schedule-automatic",
+            (night_light.schedule == NIGHT_LIGHT_SUN).to_string(),
+        ),
+        (
+            "night-light-schedule-from",
+            format!("{:.2}", night_light.start as f64 / 60.0),
+        ),
+        (
+            "night-light-schedule-to",
+            format!("{:.2}", night_light.end as f64 / 60.0),
+        ),
+        (
+            "night-light-last-coordinates",
+            format!(
+                "({:.4}, {:.4})",
+                night_light.latitude, night_light.longitude
+            ),
+        ),
+    ]
+}
+
+fn g_apply_night_light(night_light: &NightLight) -> bool {
+    gnome_night_light_settings(night_light)
+        .into_iter()
+        .all(|(key, value)| {
+            run_command(
+                "gsettings",
+                &["set".into(), GNOME_COLOR_SCHEMA.into(), key.into(), value],
+            )
+        })
+}
+
+/// Returns the kwinrc keys and values of the NightColor group.
+pub fn kde_night_light_settings(night_light: &NightLight) -> Vec<(&'static str, String)> {
+    let mode = if night_light.schedule == NIGHT_LIGHT_SUN {
+        "Location"
+    } else {
+        "Times"
+    };
+    vec![
+        ("Active", night_light.enabled.to_string()),
+        ("Mode", mode.into()),
+        ("NightTemperature", night_light.temperature.to_string()),
+        ("EveningBeginFixed", format_kde_time(night_light.start)),
+        ("MorningBeginFixed", format_kde_time(night_light.end)),
+        ("LatitudeFixed", night_light.latitude.to_string()),
+        ("LongitudeFixed", night_light.longitude.to_string()),
+    ]
+}
+
+// kwin expects times as hhmm
+fn format_kde_time(minutes: u32) -> String {
+    format!("{:02}{:02}", minutes / 60 % 24, minutes % 60)
+}
+
+// the configuration is written to kwinrc, kwin is then asked to reload it via DBus
+fn kde_apply_night_light(night_light: &NightLight) -> bool {
+    let written = kde_night_light_settings(night_light)
+        .into_iter()
+        .all(|(key, value)| {
+            run_command(
+                "kwriteconfig6",
+                &[
+                    "--file".into(),
+                    "kwinrc".into(),
+                    "--group".into(),
+                    "NightColor".into(),
+                    "--key".into(),
+                    key.into(),
+                    value,
+                ],
+            )
+        });
+    if !written {
+        return false;
+    }
+    let conn = dbus::blocking::Connection::new_session().unwrap();
+    let proxy = conn.with_proxy("org.kde.KWin", "/KWin", Duration::from_millis(1000));
+    let res: Result<(), dbus::Error> = proxy.method_call("org.kde.KWin", "reconfigure", ());
+    if let Err(_error) = res {
+        ERROR!(
+            format!("Could not reload the KDE night light: {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return false;
+    }
+    true
+}
+
+// returns the minutes after midnight, the day of the year and the offset to UTC in minutes
+fn local_time() -> (u32, u32, i64) {
+    let mut time: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe {
+        let now = libc::time(ptr::null_mut());
+        libc::localtime_r(&now, &mut time);
+    }
+    (
+        (time.tm_hour * 60 + time.tm_min) as u32,
+        time.tm_yday as u32 + 1,
+        time.tm_gmtoff / 60,
+    )
+}
+
+/// Returns sunrise and sunset in minutes after midnight UTC, based on the equations of the NOAA.
+/// None is returned during polar day and polar night.
+pub fn sun_times(day_of_year: u32, latitude: f64, longitude: f64) -> Option<(f64, f64)> {
+    let year = 2.0 * PI / 365.0 * (day_of_year as f64 - 1.0);
+    let equation_of_time = 229.18
+        * (0.000075 + 0.001868 * year.cos()
+            - 0.032077 * year.sin()
+            - 0.014615 * (2.0 * year).cos()
+            - 0.040849 * (2.0 * year).sin());
+    let declination = 0.006918 - 0.399912 * year.cos() + 0.070257 * year.sin()
+        - 0.006758 * (2.0 * year).cos()
+        + 0.000907 * (2.0 * year).sin()
+        - 0.002697 * (3.0 * year).cos()
+        + 0.00148 * (3.0 * year).sin();
+    let latitude = latitude.to_radians();
+    let hour_angle = SUNSET_ZENITH.to_radians().cos() / (latitude.cos() * declination.cos())
+        - latitude.tan() * declination.tan();
+    if !(-1.0..=1.0).contains(&hour_angle) {
+        return None;
+    }
+    let hour_angle = hour_angle.acos().to_degrees();
+    let sunrise = 720.0 - 4.0 * (longitude + hour_angle) - equation_of_time;
+    let sunset = 720.0 - 4.0 * (longitude - hour_angle) - equation_of_time;
+    Some((sunrise, sunset))
+}
+
+/// Checks whether the time lies between start and end, which may wrap around midnight.
+pub fn is_night(minutes: u32, start: u32, end: u32) -> bool {
+    if start == end {
+        return false;
+    }
+    if start < end {
+        return minutes >= start && minutes < end;
+    }
+    minutes >= start || minutes < end
+}
+
+/// Returns the temperature for the given local time.
+pub fn current_temperature(
+    night_light: &NightLight,
+    minutes: u32,
+    day_of_year: u32,
+    utc_offset: i64,
+) -> u32 {
+    if !night_light.enabled {
+        return NEUTRAL_TEMPERATURE;
+    }
+    let (start, end) = if night_light.schedule == NIGHT_LIGHT_SUN {
+        let times = sun_times(day_of_year, night_light.latitude, night_light.longitude);
+        if times.is_none() {
+            return NEUTRAL_TEMPERATURE;
+        }
+        let (sunrise, sunset) = times.unwrap();
+        let to_local = |time: f64| {
+            (time.round() as i64 + utc_offset).rem_euclid(MINUTES_PER_DAY as i64) as u32
+        };
+        (to_local(sunset), to_local(sunrise))
+    } else {
+        (night_light.start, night_light.end)
+    };
+    if is_night(minutes, start, end) {
+        night_light.temperature
+    } else {
+        NEUTRAL_TEMPERATURE
+    }
+}
@@ -0,0 +1,283 @@
+// This file handles named monitor layouts which can be stored and applied on any backend
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use re_set_lib::ERROR;
+#[cfg(debug_assertions)]
+use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
+
+use crate::utils::{to_millihertz, CustomMode, HdrSettings, Monitor, MonitorError};
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct MonitorProfile {
+    pub name: String,
+    pub monitors: Vec<ProfileMonitor>,
+}
+
+// Only the values which can be applied are stored, modes and features are always taken from
+// the currently connected monitors instead.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ProfileMonitor {
+    pub name: String,
+    pub make: String,
+    pub model: String,
+    pub serial: String,
+    pub enabled: bool,
+    pub width: i32,
+    pub height: i32,
+    // millihertz
+    pub refresh_rate: u32,
+    pub scale: f64,
+    pub transform: u32,
+    pub vrr: bool,
+    pub primary: bool,
+    pub x: i32,
+    pub y: i32,
+    // profiles stored before mirroring was supported do not contain this value
+    #[serde(default)]
+    pub mirror: String,
+    #[serde(default)]
+    pub custom_mode: CustomMode,
+    #[serde(default)]
+    pub hdr: HdrSettings,
+    #[serde(default)]
+    pub subpixel: String,
+    #[serde(default)]
+    pub max_render_time: u32,
+}
+
+impl ProfileMonitor {
+    pub fn from_regular_monitor(monitor: &Monitor) -> Self {
+        Self {
+            name: monitor.name.clone(),
+            make: monitor.make.clone(),
+            model: monitor.model.clone(),
+            serial: monitor.serial.clone(),
+            enabled: monitor.enabled,
+            width: monitor.size.0,
+            height: monitor.size.1,
+            refresh_rate: monitor.refresh_rate,
+            scale: monitor.scale,
+            transform: monitor.transform,
+            vrr: monitor.vrr,
+            primary: monitor.primary,
+            x: monitor.offset.0,
+            y: monitor.offset.1,
+            mirror: monitor.mirror.clone(),
+            custom_mode: monitor.custom_mode.clone(),
+            hdr: monitor.hdr,
+            subpixel: monitor.subpixel.clone(),
+            max_render_time: monitor.max_render_time,
+        }
+    }
+
+    fn has_identity(&self) -> bool {
+        !self.make.is_empty() || !self.model.is_empty() || !self.serial.is_empty()
+    }
+
+    fn same_identity(&self, monitor: &Monitor) -> bool {
+        self.make == monitor.make && self.model == monitor.model && self.serial == monitor.serial
+    }
+
+    /// Applies the stored values onto a currently connected monitor.
+    /// The mode is resolved against the available modes of the monitor, if the stored mode no
+    /// longer exists, the current mode of the monitor is kept.
+    pub fn apply_to(&self, monitor: &mut Monitor) {
+        monitor.enabled = self.enabled;
+        monitor.scale = self.scale;
+        monitor.transform = self.transform;
+        monitor.vrr = self.vrr && monitor.features.vrr;
+        monitor.primary = self.primary && monitor.features.primary;
+        monitor.offset.0 = self.x;
+        monitor.offset.1 = self.y;
+        monitor.mirror = self.mirror.clone();
+        if monitor.features.hdr {
+            monitor.hdr = self.hdr;
+        }
+        // profiles stored before these values were supported keep the current values
+        if monitor.features.subpixel && !self.subpixel.is_empty() {
+            monitor.subpixel = self.subpixel.clone();
+        }
+        if monitor.features.max_render_time {
+            monitor.max_render_time = self.max_render_time;
+        }
+
+        // custom modes are not part of the available modes and are therefore applied as is
+        if self.custom_mode.is_set() && monitor.features.custom_modes {
+            monitor.size.0 = self.custom_mode.width;
+            monitor.size.1 = self.custom_mode.height;
+            monitor.refresh_rate = to_millihertz(self.custom_mode.refresh_rate);
+            monitor.custom_mode = self.custom_mode.clone();
+            return;
+        }
+        monitor.custom_mode = CustomMode::default();
+
+        // profiles stored before millihertz were used contain whole Hz values
+        let legacy = self.refresh_rate < 1000;
+        for mode in monitor.available_modes.iter() {
+            if mode.size.0 != self.width || mode.size.1 != self.height {
+                continue;
+            }
+            for (refresh_rate, id) in mode.refresh_rates.iter() {
+                let matches = if legacy {
+                    (refresh_rate + 500) / 1000 == self.refresh_rate
+                } else {
+                    *refresh_rate == self.refresh_rate
+                };
+                if !matches {
+                    continue;
+                }
+                monitor.size.0 = self.width;
+                monitor.size.1 = self.height;
+                monitor.refresh_rate = *refresh_rate;
+                if monitor.uses_mode_id {
+                    monitor.mode = id.clone();
+                }
+                return;
+            }
+        }
+        ERROR!(
+            format!(
+                "Mode {}x{}@{} is not available for {}, keeping current mode",
+                self.width, self.height, self.refresh_rate, monitor.name
+            ),
+            ErrorLevel::Recoverable
+        );
+    }
+}
+
+impl MonitorProfile {
+    pub fn from_regular_monitors(name: impl Into<String>, monitors: &[Monitor]) -> Self {
+        Self {
+            name: name.into(),
+            monitors: monitors
+                .iter()
+                .map(ProfileMonitor::from_regular_monitor)
+                .collect(),
+        }
+    }
+
+    /// Returns the currently connected monitors with the values of this profile applied.
+    /// Monitors are matched by connector name and identity first, afterwards by identity only in
+    /// order to handle monitors that moved to a different connector.
+    pub fn apply_to_monitors(&self, current: &[Monitor]) -> Vec<Monitor> {
+        let mut monitors = current.to_vec();
+        let mut used = vec![false; self.monitors.len()];
+        let mut matched = vec![false; monitors.len()];
+
+        for (index, monitor) in monitors.iter_mut().enumerate() {
+            for (profile_index, profile_monitor) in self.monitors.iter().enumerate() {
+                if used[profile_index] || profile_monitor.name != monitor.name {
+                    continue;
+                }
+                if profile_monitor.has_identity() && !profile_monitor.same_identity(monitor) {
+                    continue;
+                }
+                profile_monitor.apply_to(monitor);
+                used[profile_index] = true;
+                matched[index] = true;
+                break;
+            }
+        }
+
+        for (index, monitor) in monitors.iter_mut().enumerate() {
+            if matched[index] {
+                continue;
+            }
+            for (profile_index, profile_monitor) in self.monitors.iter().enumerate() {
+                if used[profile_index]
+                    || !profile_monitor.has_identity()
+                    || !profile_monitor.same_identity(monitor)
+                {
+                    continue;
+                }
+                profile_monitor.apply_to(monitor);
+                used[profile_index] = true;
+                break;
+            }
+        }
+        monitors
+    }
+}
+
+pub fn get_profile_path() -> PathBuf {
+    let dirs = directories_next::ProjectDirs::from("org", "Xetibo", "ReSet").unwrap();
+    dirs.config_dir().join("monitor_profiles.json")
+}
+
+pub fn get_profiles() -> Result<Vec<MonitorProfile>, MonitorError> {
+    read_profiles(&get_profile_path())
+}
+
+/// Reads the stored profiles, a missing file contains no profiles.
+/// Other errors are returned, as writing the profiles afterwards would otherwise remove all of
+/// them.
+pub fn read_profiles(path: &Path) -> Result<Vec<MonitorProfile>, MonitorError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => {
+            return Err(MonitorError::Io(format!("{}: {}", path.display(), error)));
+        }
+    };
+    serde_json::from_str(&content)
+        .map_err(|error| MonitorError::Parse(format!("{}: {}", path.display(), error)))
+}
+
+pub fn write_profiles(path: &Path, profiles: &[MonitorProfile]) -> Result<(), MonitorError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(profiles)?;
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    file.write_all(json.as_bytes())?;
+    Ok(())
+}
+
+pub fn list_profiles() -> Result<Vec<String>, MonitorError> {
+    Ok(get_profiles()?
+        .into_iter()
+        .map(|profile| profile.name)
+        .collect())
+}
+
+pub fn get_profile(name: &str) -> Result<Option<MonitorProfile>, MonitorError> {
+    Ok(get_profiles()?
+        .into_iter()
+        .find(|profile| profile.name == name))
+}
+
+// saving a profile with an existing name overwrites the previous profile
+pub fn save_profile(name: &str, monitors: &[Monitor]) -> Result<(), MonitorError> {
+    if name.trim().is_empty() {
+        return Err(MonitorError::InvalidConfiguration(String::from(
+            "profile names can't be empty",
+        )));
+    }
+    let mut profiles = get_profiles()?;
+    let profile = MonitorProfile::from_regular_monitors(name.trim(), monitors);
+    match profiles.iter().position(|val| val.name == profile.name) {
+        Some(index) => profiles[index] = profile,
+        None => profiles.push(profile),
+    }
+    write_profiles(&get_profile_path(), &profiles)
+}
+
+/// Returns false if no profile with this name exists.
+pub fn delete_profile(name: &str) -> Result<bool, MonitorError> {
+    let mut profiles = get_profiles()?;
+    let len = profiles.len();
+    profiles.retain(|profile| profile.name != name);
+    if profiles.len() == len {
+        return Ok(false);
+    }
+    write_profiles(&get_profile_path(), &profiles)?;
+    Ok(true)
+}
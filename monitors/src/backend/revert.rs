@@ -0,0 +1,118 @@
+// This file handles the revert of configurations that were not confirmed in time
+// The timer is owned by the daemon, a crashed or unreachable frontend therefore can't leave the
+// user with an unusable configuration.
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use re_set_lib::{utils::config::CONFIG, ERROR, LOG};
+#[cfg(debug_assertions)]
+use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
+
+use crate::utils::Monitor;
+
+use super::general::MonitorBackend;
+
+pub const DEFAULT_REVERT_TIMEOUT: u32 = 15;
+
+/// Configuration that is restored unless the applied configuration is confirmed.
+#[derive(Debug, Clone)]
+pub struct PendingRevert {
+    pub previous: Vec<Monitor>,
+    pub persistent: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct RevertData {
+    // incremented for each timer, timers of replaced configurations are ignored
+    generation: u64,
+    pending: Option<PendingRevert>,
+}
+
+impl RevertData {
+    /// Starts a new revert, the previous configuration of a revert that is still pending is kept,
+    /// as this is the last configuration that was confirmed.
+    pub fn start(&mut self, previous: Vec<Monitor>, persistent: bool) -> u64 {
+        self.generation += 1;
+        match &mut self.pending {
+            Some(pending) => pending.persistent |= persistent,
+            None => {
+                self.pending = Some(PendingRevert {
+                    previous,
+                    persistent,
+                })
+            }
+        }
+        self.generation
+    }
+
+    /// Removes the pending revert, returns false if there was none.
+    pub fn cancel(&mut self) -> bool {
+        self.pending.take().is_some()
+    }
+
+    /// Returns the pending revert if the timer with this generation is still the latest one.
+    pub fn expire(&mut self, generation: u64) -> Option<PendingRevert> {
+        if self.generation != generation {
+            return None;
+        }
+        self.pending.take()
+    }
+}
+
+/// Timeout in seconds, 0 uses the configured timeout.
+pub fn revert_timeout(seconds: u32) -> u32 {
+    if seconds > 0 {
+        return seconds;
+    }
+    if let Some(config) = CONFIG.get("Monitor") {
+        if let Some(value) = config.get("revert_timeout") {
+            return value
+                .as_integer()
+                .and_then(|value| u32::try_from(value).ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(DEFAULT_REVERT_TIMEOUT);
+        }
+    }
+    DEFAULT_REVERT_TIMEOUT
+}
+
+/// Restores the previous configuration once the timeout expires, unless it was confirmed or
+/// replaced in the meantime.
+/// The stored layout is left as is, it is only replaced once a configuration is confirmed.
+/// The changed flag marks the cached monitors as outdated after the revert.
+pub fn start_revert_timer(
+    backend: Arc<dyn MonitorBackend>,
+    conn: Option<Arc<wayland_client::Connection>>,
+    revert: Arc<Mutex<RevertData>>,
+    changed: Arc<AtomicBool>,
+    generation: u64,
+    timeout: Duration,
+) {
+    thread::spawn(move || {
+        thread::sleep(timeout);
+        let pending = revert.lock().unwrap().expire(generation);
+        if pending.is_none() {
+            return;
+        }
+        let pending = pending.unwrap();
+        LOG!("Configuration was not confirmed, reverting");
+        let res = if pending.persistent {
+            backend.save(conn, &pending.previous)
+        } else {
+            backend.apply(conn, &pending.previous)
+        };
+        if let Err(_error) = res {
+            ERROR!(
+                format!("Could not revert monitor configuration {}", _error),
+                ErrorLevel::Recoverable
+            );
+        }
+        changed.store(true, Ordering::SeqCst);
+    });
+}
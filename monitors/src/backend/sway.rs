@@ -0,0 +1,556 @@
+// NOTE: This implementation is for the sway compositor
+
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    env,
+    fs::OpenOptions,
+    io::{Read, Write},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use re_set_lib::{utils::config::CONFIG, ERROR};
+#[cfg(debug_assertions)]
+use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
+
+use crate::utils::{
+    format_refresh_rate, AvailableMode, BackendCapabilities, CustomMode, EdidInfo, HdrSettings,
+    Monitor, MonitorError, MonitorFeatures, Offset, Size,
+};
+
+use super::{
+    general::MonitorBackend,
+    mirror::{apply_mirror_fallback, detect_mirrors},
+    wlr::{wlr_apply_monitor_configuration, wlr_get_monitor_information, wlr_watch_monitors},
+};
+
+pub const SWAYFEATURES: MonitorFeatures = MonitorFeatures {
+    vrr: true,
+    // sway has no primary monitor concept
+    primary: false,
+    fractional_scaling: true,
+    hdr: false,
+    subpixel: true,
+    max_render_time: true,
+    custom_modes: true,
+};
+
+const MAGIC: &[u8; 6] = b"i3-ipc";
+const RUN_COMMAND: u32 = 0;
+const SUBSCRIBE: u32 = 2;
+const GET_OUTPUTS: u32 = 3;
+const OUTPUT_EVENT: u32 = 0x80000001;
+const TIMEOUT: Duration = Duration::from_millis(5000);
+
+pub const SUBPIXEL_LAYOUTS: [&str; 5] = ["rgb", "bgr", "vrgb", "vbgr", "none"];
+
+pub const TRANSFORMS: [&str; 8] = [
+    "normal",
+    "90",
+    "180",
+    "270",
+    "flipped",
+    "flipped-90",
+    "flipped-180",
+    "flipped-270",
+];
+
+fn get_sway_socket() -> Option<PathBuf> {
+    env::var("SWAYSOCK").ok().map(PathBuf::from)
+}
+
+/// Sends a single message via the i3/sway IPC and returns the payload of the reply.
+pub fn sway_request(
+    socket: &Path,
+    message_type: u32,
+    payload: &str,
+) -> Result<String, std::io::Error> {
+    let mut stream = UnixStream::connect(socket)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    write_message(&mut stream, message_type, payload)?;
+    let (_, reply) = read_message(&mut stream)?;
+    Ok(reply)
+}
+
+// messages consist of the magic string, the payload length and the message type in native byte
+// order, followed by the payload
+fn write_message(
+    stream: &mut UnixStream,
+    message_type: u32,
+    payload: &str,
+) -> Result<(), std::io::Error> {
+    let mut message = Vec::with_capacity(14 + payload.len());
+    message.extend_from_slice(MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&message_type.to_ne_bytes());
+    message.extend_from_slice(payload.as_bytes());
+    stream.write_all(&message)
+}
+
+// replies and events use the same format as messages, events have the highest bit of the type set
+fn read_message(stream: &mut UnixStream) -> Result<(u32, String), std::io::Error> {
+    let mut header = [0; 14];
+    stream.read_exact(&mut header)?;
+    if &header[..6] != MAGIC {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Invalid sway IPC reply",
+        ));
+    }
+    let len = u32::from_ne_bytes(header[6..10].try_into().unwrap()) as usize;
+    let message_type = u32::from_ne_bytes(header[10..14].try_into().unwrap());
+    let mut reply = vec![0; len];
+    stream.read_exact(&mut reply)?;
+    Ok((message_type, String::from_utf8_lossy(&reply).to_string()))
+}
+
+/// Subscribes to the output events of sway and calls the callback for each of them.
+/// Blocks the current thread until the connection is closed.
+pub fn sway_watch_outputs(socket: &Path, mut callback: impl FnMut()) -> Result<(), std::io::Error> {
+    let mut stream = UnixStream::connect(socket)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    write_message(&mut stream, SUBSCRIBE, "[\"output\"]")?;
+    let (_, reply) = read_message(&mut stream)?;
+    let result: Result<SwayCommandResult, _> = serde_json::from_str(&reply);
+    if !result.is_ok_and(|result| result.success) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Sway rejected the subscription",
+        ));
+    }
+    // events only arrive on changes
+    stream.set_read_timeout(None)?;
+    loop {
+        let (message_type, _) = read_message(&mut stream)?;
+        if message_type == OUTPUT_EVENT {
+            callback();
+        }
+    }
+}
+
+// blocks the current thread and calls the callback whenever sway reports an output change
+pub fn sway_watch_monitors(
+    conn: Option<std::sync::Arc<wayland_client::Connection>>,
+    callback: impl FnMut(),
+) {
+    let socket = get_sway_socket();
+    if socket.is_none() {
+        return wlr_watch_monitors(conn, callback);
+    }
+    if let Err(_error) = sway_watch_outputs(&socket.unwrap(), callback) {
+        ERROR!(
+            format!("Sway: Stopped watching monitor changes: {}", _error),
+            ErrorLevel::Recoverable
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct SwayBackend;
+
+impl MonitorBackend for SwayBackend {
+    fn get(
+        &self,
+        conn: Option<std::sync::Arc<wayland_client::Connection>>,
+        _: &mut u32,
+    ) -> Vec<Monitor> {
+        sway_get_monitor_information(conn)
+    }
+
+    fn apply(
+        &self,
+        conn: Option<std::sync::Arc<wayland_client::Connection>>,
+        monitors: &[Monitor],
+    ) -> Result<(), MonitorError> {
+        sway_apply_monitor_configuration(conn, monitors)
+    }
+
+    fn save(
+        &self,
+        conn: Option<std::sync::Arc<wayland_client::Connection>>,
+        monitors: &[Monitor],
+    ) -> Result<(), MonitorError> {
+        sway_save_monitor_configuration(conn, monitors)
+    }
+
+    fn features(&self) -> MonitorFeatures {
+        SWAYFEATURES
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            // saved in the outputs file, see README
+            persistent_save: true,
+            confirms_save: false,
+            gaps: true,
+            compact_layout: false,
+            arbitrary_scales: true,
+            // sway has no native mirroring, see mirror.rs
+            native_mirroring: false,
+            reports_vrr: true,
+            sdr_gamut_wideness: false,
+            features: self.features(),
+        }
+    }
+
+    fn watch(
+        &self,
+        conn: Option<std::sync::Arc<wayland_client::Connection>>,
+        callback: Box<dyn FnMut()>,
+    ) {
+        sway_watch_monitors(conn, callback)
+    }
+}
+
+pub fn sway_get_monitor_information(
+    conn: Option<std::sync::Arc<wayland_client::Connection>>,
+) -> Vec<Monitor> {
+    let socket = get_sway_socket();
+    if socket.is_none() {
+        return wlr_get_monitor_information(conn);
+    }
+    let reply = sway_request(&socket.unwrap(), GET_OUTPUTS, "");
+    if let Err(_error) = reply {
+        ERROR!(
+            format!("Could not fetch outputs from sway: {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return wlr_get_monitor_information(conn);
+    }
+    let outputs: Result<Vec<SwayOutput>, _> = serde_json::from_str(&reply.unwrap());
+    if outputs.is_err() {
+        ERROR!(
+            "Failed to deserialize to monitor datastructure",
+            ErrorLevel::PartialBreakage
+        );
+        return Vec::new();
+    }
+    let mut monitors: Vec<Monitor> = outputs
+        .unwrap()
+        .into_iter()
+        .filter(|output| !output.non_desktop)
+        .enumerate()
+        .map(|(index, output)| output.convert_to_regular_monitor(index as u32))
+        .collect();
+    detect_mirrors(&mut monitors);
+    monitors
+}
+
+pub fn sway_apply_monitor_configuration(
+    conn: Option<std::sync::Arc<wayland_client::Connection>>,
+    monitors: &[Monitor],
+) -> Result<(), MonitorError> {
+    let socket = get_sway_socket();
+    if socket.is_none() {
+        return wlr_apply_monitor_configuration(conn, monitors);
+    }
+    let commands = monitor_to_commands(monitors);
+    let reply = sway_request(&socket.unwrap(), RUN_COMMAND, &commands.join(";"));
+    if let Err(_error) = reply {
+        ERROR!(
+            format!("Could not apply outputs via sway: {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return wlr_apply_monitor_configuration(conn, monitors);
+    }
+    let errors: Vec<String> = command_errors(&commands, &reply.unwrap())
+        .into_iter()
+        .map(|(command, error)| format!("{}: {}", command, error))
+        .collect();
+    if errors.is_empty() {
+        return Ok(());
+    }
+    ERROR!(
+        format!("Sway rejected {}", errors.join(", ")),
+        ErrorLevel::Recoverable
+    );
+    Err(MonitorError::ApplyFailed(errors.join(", ")))
+}
+
+fn get_default_path() -> String {
+    let dirs = directories_next::ProjectDirs::from("org", "Xetibo", "ReSet").unwrap();
+    let buf = dirs.config_dir().join("outputs");
+    let path = buf.to_str().unwrap();
+    String::from(path)
+}
+
+// sway has no persistent configuration via IPC, the created file has to be included in the sway
+// config, the configuration is still applied in order to see the changes immediately
+pub fn sway_save_monitor_configuration(
+    conn: Option<std::sync::Arc<wayland_client::Connection>>,
+    monitors: &[Monitor],
+) -> Result<(), MonitorError> {
+    let path;
+    if let Some(config) = CONFIG.get("Monitor") {
+        if let Some(test) = config.get("sway_path") {
+            path = test.as_str().unwrap().to_string();
+        } else {
+            path = get_default_path();
+        }
+    } else {
+        path = get_default_path();
+    }
+
+    let mut input_config = OpenOptions::new()
+        .write(true)
+        .read(true)
+        .create(true)
+        .truncate(true)
+        .open(PathBuf::from(&path))
+        .map_err(|error| MonitorError::Io(format!("{}: {}", path, error)))?;
+
+    let mut output_string = String::new();
+    for command in monitor_to_commands(monitors) {
+        output_string += &command;
+        output_string += "\n";
+    }
+
+    input_config
+        .write_all(output_string.as_bytes())
+        .and_then(|()| input_config.sync_all())
+        .map_err(|error| MonitorError::Io(format!("{}: {}", path, error)))?;
+    sway_apply_monitor_configuration(conn, monitors)
+}
+
+// one command per output, the same commands are used for the IPC and the config file
+// sway has no native mirroring, mirrored outputs are placed onto their source instead
+pub fn monitor_to_commands(monitors: &[Monitor]) -> Vec<String> {
+    let mut commands = Vec::new();
+
+    for monitor in apply_mirror_fallback(monitors).iter() {
+        if !monitor.enabled {
+            commands.push(format!("output \"{}\" disable", monitor.name));
+            continue;
+        }
+        let mut command = format!(
+            "output \"{}\" enable {}",
+            monitor.name,
+            get_mode_argument(monitor)
+        );
+        command += &format!(
+            " position {} {} scale {:.6}",
+            monitor.offset.0, monitor.offset.1, monitor.scale
+        );
+        command += &format!(
+            " transform {} adaptive_sync {}",
+            TRANSFORMS
+                .get(monitor.transform as usize)
+                .unwrap_or(&"normal"),
+            if monitor.vrr { "on" } else { "off" }
+        );
+        if SUBPIXEL_LAYOUTS.contains(&monitor.subpixel.as_str()) {
+            command += &format!(" subpixel {}", monitor.subpixel);
+        }
+        if monitor.max_render_time == 0 {
+            command += " max_render_time off";
+        } else {
+            command += &format!(" max_render_time {}", monitor.max_render_time);
+        }
+        commands.push(command);
+    }
+
+    commands
+}
+
+// custom modes are either created from the modeline or from size and refresh rate
+fn get_mode_argument(monitor: &Monitor) -> String {
+    if let Some(custom_mode) = monitor.valid_custom_mode() {
+        if !custom_mode.modeline.is_empty() {
+            return format!("modeline {}", custom_mode.modeline);
+        }
+        return format!(
+            "mode --custom {}x{}@{:.3}Hz",
+            custom_mode.width, custom_mode.height, custom_mode.refresh_rate
+        );
+    }
+    format!("mode {}", get_mode_string(monitor))
+}
+
+// the mode is reset when the resolution is changed, in this case the matching mode is searched
+fn get_mode_string(monitor: &Monitor) -> String {
+    if monitor
+        .mode
+        .starts_with(&format!("{}x{}@", monitor.size.0, monitor.size.1))
+    {
+        return monitor.mode.clone();
+    }
+    for mode in monitor.available_modes.iter() {
+        if mode.size != monitor.size {
+            continue;
+        }
+        for (refresh_rate, id) in mode.refresh_rates.iter() {
+            if *refresh_rate == monitor.refresh_rate {
+                return id.clone();
+            }
+        }
+    }
+    format!(
+        "{}x{}@{}Hz",
+        monitor.size.0,
+        monitor.size.1,
+        format_refresh_rate(monitor.refresh_rate)
+    )
+}
+
+/// Returns each failed command together with the error reported by sway.
+pub fn command_errors(commands: &[String], reply: &str) -> Vec<(String, String)> {
+    let results: Result<Vec<SwayCommandResult>, _> = serde_json::from_str(reply);
+    if results.is_err() {
+        return vec![(commands.join(";"), String::from("invalid reply"))];
+    }
+    let results = results.unwrap();
+    let mut errors = Vec::new();
+    for (index, command) in commands.iter().enumerate() {
+        match results.get(index) {
+            Some(result) if result.success => (),
+            Some(result) => errors.push((command.clone(), result.error.clone())),
+            None => errors.push((command.clone(), String::from("no reply"))),
+        }
+    }
+    errors
+}
+
+#[derive(serde::Deserialize, Debug, Default)]
+struct SwayCommandResult {
+    success: bool,
+    #[serde(default)]
+    error: String,
+}
+
+#[derive(serde::Deserialize, Debug, Default, Clone, Copy)]
+pub struct SwayMode {
+    pub width: i32,
+    pub height: i32,
+    // millihertz
+    pub refresh: u32,
+}
+
+impl SwayMode {
+    fn to_mode_string(self) -> String {
+        format!(
+            "{}x{}@{}.{:03}Hz",
+            self.width,
+            self.height,
+            self.refresh / 1000,
+            self.refresh % 1000
+        )
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Default)]
+pub struct SwayRect {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(serde::Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct SwayOutput {
+    pub name: String,
+    pub make: String,
+    pub model: String,
+    pub serial: String,
+    pub active: bool,
+    pub non_desktop: bool,
+    pub scale: f64,
+    pub subpixel_hinting: String,
+    pub transform: String,
+    pub modes: Vec<SwayMode>,
+    pub current_mode: Option<SwayMode>,
+    pub rect: SwayRect,
+    pub adaptive_sync_status: String,
+    pub max_render_time: u32,
+}
+
+impl SwayOutput {
+    pub fn convert_to_regular_monitor(self, id: u32) -> Monitor {
+        let current_mode = self
+            .current_mode
+            .or_else(|| self.modes.first().copied())
+            .unwrap_or_default();
+        let transform = TRANSFORMS
+            .iter()
+            .position(|val| *val == self.transform)
+            .unwrap_or(0) as u32;
+        let scale = if self.scale <= 0.0 { 1.0 } else { self.scale };
+        // modes which are not reported by the output can only be custom modes
+        let custom_mode = if self.active
+            && self.current_mode.is_some()
+            && !self.modes.iter().any(|mode| {
+                mode.width == current_mode.width
+                    && mode.height == current_mode.height
+                    && mode.refresh == current_mode.refresh
+            }) {
+            CustomMode {
+                width: current_mode.width,
+                height: current_mode.height,
+                refresh_rate: current_mode.refresh as f64 / 1000.0,
+                modeline: "".into(),
+            }
+        } else {
+            CustomMode::default()
+        };
+        Monitor {
+            id,
+            enabled: self.active,
+            name: self.name,
+            make: self.make,
+            model: self.model,
+            serial: self.serial,
+            refresh_rate: current_mode.refresh,
+            scale,
+            transform,
+            vrr: self.adaptive_sync_status == "enabled",
+            primary: false,
+            offset: Offset(self.rect.x, self.rect.y),
+            size: Size(current_mode.width, current_mode.height),
+            drag_information: Default::default(),
+            mode: current_mode.to_mode_string(),
+            available_modes: convert_modes(&self.modes),
+            uses_mode_id: true,
+            features: SWAYFEATURES,
+            subpixel: self.subpixel_hinting,
+            max_render_time: self.max_render_time,
+            mirror: "".into(),
+            custom_mode,
+            hdr: HdrSettings::default(),
+            edid: EdidInfo::default(),
+        }
+    }
+}
+
+fn convert_modes(modes: &[SwayMode]) -> Vec<AvailableMode> {
+    let mut sizes: HashMap<Size, Vec<(u32, String)>> = HashMap::new();
+    for mode in modes {
+        sizes
+            .entry(Size(mode.width, mode.height))
+            .or_default()
+            .push((mode.refresh, mode.to_mode_string()));
+    }
+    let mut converted_modes: Vec<AvailableMode> = sizes
+        .into_iter()
+        .map(|(size, mut refresh_rates)| {
+            refresh_rates.sort_unstable();
+            refresh_rates.reverse();
+            AvailableMode {
+                id: "".into(),
+                size,
+                refresh_rates,
+                // sway allows arbitrary scales and hence no supported scales are provided
+                supported_scales: Vec::new(),
+            }
+        })
+        .collect();
+    converted_modes.sort_unstable_by(|a, b| {
+        if a.size < b.size {
+            Ordering::Greater
+        } else {
+            Ordering::Less
+        }
+    });
+    converted_modes
+}
@@ -0,0 +1,226 @@
+// This file handles the validation of monitor configurations before they are sent to the compositor
+use crate::utils::{
+    format_refresh_rate, is_nonfunctional_scale, Monitor, MonitorError, ValidationError,
+    INVALID_GAP, INVALID_MODE, INVALID_NO_ENABLED_MONITOR, INVALID_OVERLAP, INVALID_SCALE,
+};
+
+use super::{general::MonitorBackend, mirror::mirror_source};
+
+// scales found by searching the nearest scale are rounded, hence the logical size is not exact
+const LOGICAL_SIZE_TOLERANCE: f64 = 0.01;
+const MAX_SCALE: f64 = 10.0;
+
+/// Validates the configuration for the backend, e.g. GNOME and KDE reject layouts with monitors
+/// that are not connected to the others.
+pub fn validate_configuration(
+    backend: &dyn MonitorBackend,
+    monitors: &[Monitor],
+) -> Vec<ValidationError> {
+    validate_monitors(monitors, !backend.capabilities().gaps)
+}
+
+/// Invalid configurations are rejected before they reach the compositor.
+pub fn check_configuration(
+    backend: &dyn MonitorBackend,
+    monitors: &[Monitor],
+) -> Result<(), MonitorError> {
+    let errors = validate_configuration(backend, monitors);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let message = errors
+        .iter()
+        .map(|error| error.to_string())
+        .collect::<Vec<String>>()
+        .join(", ");
+    Err(MonitorError::InvalidConfiguration(message))
+}
+
+/// Returns all problems of the configuration, an empty list means the configuration is valid.
+/// Mirrored monitors are placed by their source and are therefore not checked for overlaps or
+/// gaps.
+pub fn validate_monitors(monitors: &[Monitor], require_adjacent: bool) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let enabled: Vec<&Monitor> = monitors.iter().filter(|monitor| monitor.enabled).collect();
+    if enabled.is_empty() {
+        errors.push(ValidationError {
+            monitor: "".into(),
+            code: INVALID_NO_ENABLED_MONITOR,
+            message: "At least one monitor has to be enabled".into(),
+        });
+        return errors;
+    }
+
+    for monitor in enabled.iter() {
+        if let Some(error) = validate_mode(monitor) {
+            errors.push(error);
+        }
+        if let Some(error) = validate_scale(monitor) {
+            errors.push(error);
+        }
+    }
+
+    let placed: Vec<&Monitor> = enabled
+        .into_iter()
+        .filter(|monitor| mirror_source(monitor, monitors).is_none())
+        .collect();
+    for (index, monitor) in placed.iter().enumerate() {
+        for other in placed.iter().skip(index + 1) {
+            if overlaps(monitor, other) {
+                errors.push(ValidationError {
+                    monitor: other.name.clone(),
+                    code: INVALID_OVERLAP,
+                    message: format!("Overlaps with {}", monitor.name),
+                });
+            }
+        }
+    }
+
+    if require_adjacent {
+        for monitor in unconnected_monitors(&placed) {
+            errors.push(ValidationError {
+                monitor: monitor.name.clone(),
+                code: INVALID_GAP,
+                message: "Has to be placed next to another monitor".into(),
+            });
+        }
+    }
+    errors
+}
+
+// custom modes are not part of the available modes, they are validated on their own
+fn validate_mode(monitor: &Monitor) -> Option<ValidationError> {
+    let error = |message: String| ValidationError {
+        monitor: monitor.name.clone(),
+        code: INVALID_MODE,
+        message,
+    };
+    if monitor.custom_mode.is_set() {
+        return monitor
+            .custom_mode
+            .validate()
+            .err()
+            .map(|message| error(format!("Invalid custom mode, {}", message)));
+    }
+    // some environments don't report modes
+    if monitor.available_modes.is_empty() {
+        return None;
+    }
+    let exists = monitor.available_modes.iter().any(|mode| {
+        mode.size == monitor.size
+            && mode
+                .refresh_rates
+                .iter()
+                .any(|(refresh_rate, _)| *refresh_rate == monitor.refresh_rate)
+    });
+    if exists {
+        return None;
+    }
+    Some(error(format!(
+        "Mode {}x{}@{} is not available",
+        monitor.size.0,
+        monitor.size.1,
+        format_refresh_rate(monitor.refresh_rate)
+    )))
+}
+
+fn validate_scale(monitor: &Monitor) -> Option<ValidationError> {
+    let error = |message: String| ValidationError {
+        monitor: monitor.name.clone(),
+        code: INVALID_SCALE,
+        message,
+    };
+    let scale = monitor.scale;
+    if scale <= 0.0 || scale > MAX_SCALE {
+        return Some(error(format!(
+            "Scale has to be between 0 and {}",
+            MAX_SCALE
+        )));
+    }
+    // GNOME reports the scales that are supported for each mode
+    let supported_scales = monitor
+        .available_modes
+        .iter()
+        .find(|mode| mode.size == monitor.size)
+        .map(|mode| &mode.supported_scales)
+        .filter(|scales| !scales.is_empty());
+    if let Some(supported_scales) = supported_scales {
+        if supported_scales
+            .iter()
+            .any(|supported| (supported - scale).abs() < 0.0001)
+        {
+            return None;
+        }
+        return Some(error(format!("Scale {} is not supported", scale)));
+    }
+    if !monitor.features.fractional_scaling && scale.fract() != 0.0 {
+        return Some(error("Fractional scaling is not supported".into()));
+    }
+    if !is_nonfunctional_scale(monitor.size.0, monitor.size.1, scale) {
+        return None;
+    }
+    let is_whole = |size: i32| {
+        let logical = size as f64 / scale;
+        (logical - logical.round()).abs() < LOGICAL_SIZE_TOLERANCE
+    };
+    if is_whole(monitor.size.0) || is_whole(monitor.size.1) {
+        return None;
+    }
+    Some(error(format!(
+        "Scale {} does not result in a whole logical size",
+        scale
+    )))
+}
+
+// x, y, width and height in the logical coordinate space
+fn logical_rect(monitor: &Monitor) -> (i32, i32, i32, i32) {
+    let (width, height) = monitor.handle_scaled_transform();
+    (monitor.offset.0, monitor.offset.1, width, height)
+}
+
+fn overlaps(monitor: &Monitor, other: &Monitor) -> bool {
+    let (x, y, width, height) = logical_rect(monitor);
+    let (other_x, other_y, other_width, other_height) = logical_rect(other);
+    x < other_x + other_width
+        && other_x < x + width
+        && y < other_y + other_height
+        && other_y < y + height
+}
+
+// monitors are connected when they share a part of an edge
+fn touches(monitor: &Monitor, other: &Monitor) -> bool {
+    let (x, y, width, height) = logical_rect(monitor);
+    let (other_x, other_y, other_width, other_height) = logical_rect(other);
+    let shares_rows = y < other_y + other_height && other_y < y + height;
+    let shares_columns = x < other_x + other_width && other_x < x + width;
+    ((x + width == other_x || other_x + other_width == x) && shares_rows)
+        || ((y + height == other_y || other_y + other_height == y) && shares_columns)
+}
+
+// returns all monitors that can't be reached from the first monitor
+fn unconnected_monitors<'a>(monitors: &[&'a Monitor]) -> Vec<&'a Monitor> {
+    if monitors.is_empty() {
+        return Vec::new();
+    }
+    let mut reached = vec![false; monitors.len()];
+    let mut pending = vec![0];
+    reached[0] = true;
+    while let Some(index) = pending.pop() {
+        for (other_index, other) in monitors.iter().enumerate() {
+            if reached[other_index] {
+                continue;
+            }
+            let monitor = monitors[index];
+            if touches(monitor, other) || overlaps(monitor, other) {
+                reached[other_index] = true;
+                pending.push(other_index);
+            }
+        }
+    }
+    monitors
+        .iter()
+        .zip(reached)
+        .filter(|(_, reached)| !reached)
+        .map(|(monitor, _)| *monitor)
+        .collect()
+}
@@ -0,0 +1,66 @@
+// This file handles change notifications from the compositor, which are forwarded as DBus signal
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use dbus::{blocking::Connection, channel::Sender, Message};
+use re_set_lib::ERROR;
+#[cfg(debug_assertions)]
+use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
+
+use crate::{
+    r#const::{BASE, DBUS_PATH, INTERFACE},
+    utils::Monitor,
+};
+
+use super::{
+    edid::add_edid_information,
+    general::MonitorBackend,
+    hotplug::{fingerprint, handle_hotplug},
+};
+
+/// Watches the compositor for monitor changes made outside of ReSet.
+/// Each change marks the cached monitors as outdated and asks the daemon to emit the
+/// MonitorsChanged signal with the new configuration.
+pub fn start_monitor_watcher(
+    backend: Arc<dyn MonitorBackend>,
+    conn: Option<Arc<wayland_client::Connection>>,
+    changed: Arc<AtomicBool>,
+    initial: &[Monitor],
+) {
+    let mut previous = fingerprint(initial);
+    thread::spawn(move || {
+        let dbus_conn = Connection::new_session();
+        if let Err(_error) = dbus_conn {
+            ERROR!(
+                format!("Could not watch monitor changes: {}", _error),
+                ErrorLevel::Recoverable
+            );
+            return;
+        }
+        let dbus_conn = dbus_conn.unwrap();
+        let watch_conn = conn.clone();
+        let watch_backend = backend.clone();
+        let callback = move || {
+            let mut serial = 0;
+            // layouts are stored with the identity of the EDID, hence it is added before matching
+            let mut monitors = backend.get(conn.clone(), &mut serial);
+            add_edid_information(&mut monitors);
+            // an applied layout is fetched again by the daemon when emitting the signal
+            handle_hotplug(backend.as_ref(), conn.clone(), &monitors, &mut previous);
+            changed.store(true, Ordering::SeqCst);
+            // the connection of the watcher does not own the object, clients matching the sender
+            // would miss signals sent from it
+            let mut msg =
+                Message::new_method_call(BASE, DBUS_PATH, INTERFACE, "EmitMonitorsChanged")
+                    .unwrap();
+            msg.set_no_reply(true);
+            let _ = dbus_conn.send(msg);
+        };
+        watch_backend.watch(watch_conn, Box::new(callback));
+    });
+}
@@ -24,7 +24,16 @@ use re_set_lib::{ERROR, LOG};
 #[cfg(debug_assertions)]
 use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
 
-use crate::utils::{AvailableMode, Monitor, MonitorFeatures, Offset, Size};
+use crate::utils::{
+    AvailableMode, BackendCapabilities, CustomMode, EdidInfo, HdrSettings, Monitor, MonitorError,
+    MonitorFeatures, Offset, Size,
+};
+
+use super::{
+    general::MonitorBackend,
+    kanshi::kanshi_save_monitor_configuration,
+    mirror::{apply_mirror_fallback, detect_mirrors},
+};
 
 const FEATURES: MonitorFeatures = MonitorFeatures {
     // NOTE: this function currently causes a crash on the wayland library
@@ -33,6 +42,9 @@ const FEATURES: MonitorFeatures = MonitorFeatures {
     primary: false,
     fractional_scaling: true,
     hdr: false,
+    subpixel: false,
+    max_render_time: false,
+    custom_modes: true,
 };
 
 struct TransformWrapper(Transform);
@@ -74,6 +86,8 @@ struct AppData {
     heads: HashMap<u32, WlrMonitor>,
     current_monitor: u32,
     current_mode_key: (i32, i32),
+    // set once the compositor reports the result of an applied configuration
+    configuration_result: Option<Result<(), String>>,
 }
 
 #[derive(Debug)]
@@ -141,16 +155,8 @@ impl Dispatch<ZwlrOutputModeV1, CurrentMode> for AppData {
                 }
             }
             OutputModeEvent::Refresh { refresh } => {
-                let refresh = refresh / 1000;
-                let remainder = refresh % 10;
-                // set the refresh rate to be a multiple of 5
-                let refresh_rate = match remainder {
-                    0..=4 => refresh - remainder,
-                    5 => refresh,
-                    6..=9 => refresh + 10 - remainder,
-                    _ => unreachable!(),
-                };
-                let refresh_rate = refresh_rate as u32;
+                // the protocol already reports millihertz
+                let refresh_rate = refresh as u32;
                 current.refresh_rate.replace(refresh_rate);
                 let len;
                 let new;
@@ -313,7 +319,7 @@ impl Dispatch<ZwlrOutputManagerV1, ()> for AppData {
 }
 impl Dispatch<ZwlrOutputConfigurationV1, ()> for AppData {
     fn event(
-        _state: &mut Self,
+        state: &mut Self,
         _: &ZwlrOutputConfigurationV1,
         event: OutputConfigurationEvent,
         _: &(),
@@ -321,11 +327,15 @@ impl Dispatch<ZwlrOutputConfigurationV1, ()> for AppData {
         _: &QueueHandle<AppData>,
     ) {
         match event {
-            OutputConfigurationEvent::Succeeded => (),
+            OutputConfigurationEvent::Succeeded => state.configuration_result = Some(Ok(())),
             OutputConfigurationEvent::Failed => {
                 ERROR!("Could not apply configuration", ErrorLevel::Recoverable);
+                state.configuration_result = Some(Err("rejected by the compositor".into()));
+            }
+            OutputConfigurationEvent::Cancelled => {
+                state.configuration_result =
+                    Some(Err("cancelled, the outputs changed in the meantime".into()));
             }
-            OutputConfigurationEvent::Cancelled => (),
             _ => unreachable!(),
         }
     }
@@ -353,6 +363,56 @@ impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for AppData {
     }
 }
 
+// the protocol has no persistent configuration, kanshi is used for saving instead
+#[derive(Debug)]
+pub struct WlrBackend;
+
+impl MonitorBackend for WlrBackend {
+    fn get(&self, conn: Option<Arc<wayland_client::Connection>>, _: &mut u32) -> Vec<Monitor> {
+        wlr_get_monitor_information(conn)
+    }
+
+    fn apply(
+        &self,
+        conn: Option<Arc<wayland_client::Connection>>,
+        monitors: &[Monitor],
+    ) -> Result<(), MonitorError> {
+        wlr_apply_monitor_configuration(conn, monitors)
+    }
+
+    fn save(
+        &self,
+        conn: Option<Arc<wayland_client::Connection>>,
+        monitors: &[Monitor],
+    ) -> Result<(), MonitorError> {
+        kanshi_save_monitor_configuration(conn, monitors)
+    }
+
+    fn features(&self) -> MonitorFeatures {
+        FEATURES
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            // saved as kanshi profile
+            persistent_save: true,
+            confirms_save: false,
+            gaps: true,
+            compact_layout: false,
+            arbitrary_scales: true,
+            // the protocol has no native mirroring, see mirror.rs
+            native_mirroring: false,
+            reports_vrr: true,
+            sdr_gamut_wideness: false,
+            features: self.features(),
+        }
+    }
+
+    fn watch(&self, conn: Option<Arc<wayland_client::Connection>>, callback: Box<dyn FnMut()>) {
+        wlr_watch_monitors(conn, callback)
+    }
+}
+
 pub fn wlr_get_monitor_information(conn: Option<Arc<wayland_client::Connection>>) -> Vec<Monitor> {
     if conn.is_none() {
         LOG!("WLR: No wayland information without wayland connection");
@@ -371,6 +431,7 @@ pub fn wlr_get_monitor_information(conn: Option<Arc<wayland_client::Connection>>
         heads: HashMap::new(),
         current_monitor: 0,
         current_mode_key: (0, 0),
+        configuration_result: None,
     };
     queue.blocking_dispatch(&mut data).unwrap();
     for (index, wlr_monitor) in data.heads.into_iter() {
@@ -417,26 +478,36 @@ pub fn wlr_get_monitor_information(conn: Option<Arc<wayland_client::Connection>>
             available_modes: modes,
             uses_mode_id: true,
             features: FEATURES,
+            subpixel: "".into(),
+            max_render_time: 0,
+            mirror: "".into(),
+            custom_mode: CustomMode::default(),
+            hdr: HdrSettings::default(),
+            edid: EdidInfo::default(),
         };
         monitors.push(monitor);
     }
     queue.flush().unwrap();
+    detect_mirrors(&mut monitors);
     monitors
 }
 
 pub fn wlr_apply_monitor_configuration(
     conn: Option<Arc<wayland_client::Connection>>,
     monitors: &[Monitor],
-) {
+) -> Result<(), MonitorError> {
     if conn.is_none() {
-        return;
+        return Err(MonitorError::MissingTool("Wayland connection".into()));
     }
+    // neither protocol supports mirroring
+    let monitors = apply_mirror_fallback(monitors);
     let conn = conn.clone().unwrap();
-    let (globals, mut queue) = registry_queue_init::<AppData>(&conn).unwrap();
+    let (globals, mut queue) = registry_queue_init::<AppData>(&conn)
+        .map_err(|error| MonitorError::MissingTool(format!("Wayland registry ({})", error)))?;
     let handle = queue.handle();
     let manager = globals.bind::<ZwlrOutputManagerV1, _, _>(&handle, RangeInclusive::new(0, 1), ());
     if manager.is_err() {
-        return;
+        return Err(MonitorError::MissingTool("zwlr_output_manager_v1".into()));
     }
     let configuration = manager.unwrap().create_configuration(0, &handle, ());
 
@@ -444,8 +515,9 @@ pub fn wlr_apply_monitor_configuration(
         heads: HashMap::new(),
         current_monitor: 0,
         current_mode_key: (0, 0),
+        configuration_result: None,
     };
-    queue.blocking_dispatch(&mut data).unwrap();
+    queue.blocking_dispatch(&mut data).map_err(apply_failed)?;
     for monitor in monitors.iter() {
         for (id, head) in data.heads.iter() {
             if monitor.id == *id {
@@ -458,12 +530,32 @@ pub fn wlr_apply_monitor_configuration(
                 }
                 let head_configuration = configuration.enable_head(&current_head, &handle, ());
 
-                // get the mode id back, and apply the mode
-                // mode is size and refresh rate
-                let current_mode = monitor.mode.parse::<u32>().unwrap();
-                let mode_id = head.hash_modes.get(&current_mode).unwrap();
-                head_configuration
-                    .set_mode(&ZwlrOutputModeV1::from_id(&conn, mode_id.clone()).unwrap());
+                if let Some(custom_mode) = monitor.valid_custom_mode() {
+                    // modelines are not supported by the protocol, only size and refresh rate
+                    head_configuration.set_custom_mode(
+                        custom_mode.width,
+                        custom_mode.height,
+                        custom_mode.refresh_millihertz(),
+                    );
+                } else {
+                    // get the mode id back, and apply the mode
+                    // mode is size and refresh rate
+                    let mode_id = monitor
+                        .mode
+                        .parse::<u32>()
+                        .ok()
+                        .and_then(|mode| head.hash_modes.get(&mode));
+                    if mode_id.is_none() {
+                        configuration.destroy();
+                        return Err(MonitorError::InvalidConfiguration(format!(
+                            "{}: mode {} does not exist",
+                            monitor.name, monitor.mode
+                        )));
+                    }
+                    head_configuration.set_mode(
+                        &ZwlrOutputModeV1::from_id(&conn, mode_id.unwrap().clone()).unwrap(),
+                    );
+                }
 
                 let transform: TransformWrapper = monitor.transform.into();
                 head_configuration.set_transform(transform.value());
@@ -483,6 +575,131 @@ pub fn wlr_apply_monitor_configuration(
         }
     }
     configuration.apply();
-    queue.blocking_dispatch(&mut data).unwrap();
-    queue.flush().unwrap();
+    // other events may arrive before the result of the configuration
+    while data.configuration_result.is_none() {
+        queue.blocking_dispatch(&mut data).map_err(apply_failed)?;
+    }
+    data.configuration_result
+        .unwrap()
+        .map_err(MonitorError::ApplyFailed)
+}
+
+fn apply_failed(error: impl std::fmt::Display) -> MonitorError {
+    MonitorError::ApplyFailed(error.to_string())
+}
+
+// separate state for watching changes, the full AppData is only needed for fetching and applying
+struct WatchData {
+    changed: bool,
+}
+
+impl Dispatch<ZwlrOutputManagerV1, ()> for WatchData {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrOutputManagerV1,
+        event: OutputManagerEvent,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<WatchData>,
+    ) {
+        // done is sent after every change to heads or modes
+        if let OutputManagerEvent::Done { .. } = event {
+            state.changed = true;
+        }
+    }
+
+    fn event_created_child(_: u16, _qhandle: &QueueHandle<Self>) -> Arc<dyn ObjectData> {
+        _qhandle.make_data::<ZwlrOutputHeadV1, _>(())
+    }
+}
+
+impl Dispatch<ZwlrOutputHeadV1, ()> for WatchData {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrOutputHeadV1,
+        _: Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<WatchData>,
+    ) {
+    }
+
+    fn event_created_child(_: u16, _qhandle: &QueueHandle<Self>) -> Arc<dyn ObjectData> {
+        _qhandle.make_data::<ZwlrOutputModeV1, _>(())
+    }
+}
+
+impl Dispatch<ZwlrOutputModeV1, ()> for WatchData {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrOutputModeV1,
+        _: OutputModeEvent,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<WatchData>,
+    ) {
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for WatchData {
+    fn event(
+        _: &mut WatchData,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<WatchData>,
+    ) {
+    }
+}
+
+// blocks the current thread and calls the callback whenever the compositor reports a change
+pub fn wlr_watch_monitors(
+    conn: Option<Arc<wayland_client::Connection>>,
+    mut callback: impl FnMut(),
+) {
+    if conn.is_none() {
+        ERROR!(
+            "WLR: Could not watch monitor changes without wayland connection",
+            ErrorLevel::Recoverable
+        );
+        return;
+    }
+    let registry = registry_queue_init::<WatchData>(&conn.unwrap());
+    if let Err(_error) = registry {
+        ERROR!(
+            format!("WLR: Could not watch monitor changes: {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return;
+    }
+    let (globals, mut queue) = registry.unwrap();
+    let handle = queue.handle();
+    let manager = globals.bind::<ZwlrOutputManagerV1, _, _>(&handle, RangeInclusive::new(0, 1), ());
+    if let Err(_error) = manager {
+        ERROR!(
+            format!("WLR: Could not watch monitor changes: {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return;
+    }
+    let mut data = WatchData { changed: false };
+    // the initial state is also terminated with done, this is not a change
+    if queue.roundtrip(&mut data).is_err() {
+        return;
+    }
+    data.changed = false;
+    loop {
+        if let Err(_error) = queue.blocking_dispatch(&mut data) {
+            ERROR!(
+                format!("WLR: Stopped watching monitor changes: {}", _error),
+                ErrorLevel::Recoverable
+            );
+            return;
+        }
+        if data.changed {
+            data.changed = false;
+            callback();
+        }
+    }
 }
@@ -1,7 +1,8 @@
-use crate::utils::{GNOME, HYPRLAND, KDE};
+use crate::utils::{GNOME, HYPRLAND, KDE, SWAY};
 
 pub const BASE: &str = "org.Xetibo.ReSet.Daemon";
 pub const DBUS_PATH: &str = "/org/Xetibo/ReSet/Plugins/Monitors";
-pub const INTERFACE: &str = "org.Xetibo.ReSet.Monitors";
+pub const INTERFACE: &str = reset_common::monitors::INTERFACE;
 
-pub const SUPPORTED_ENVIRONMENTS: [&str; 5] = [HYPRLAND, GNOME, "ubuntu:GNOME", "pop:GNOME", KDE];
+pub const SUPPORTED_ENVIRONMENTS: [&str; 6] =
+    [HYPRLAND, GNOME, "ubuntu:GNOME", "pop:GNOME", KDE, SWAY];
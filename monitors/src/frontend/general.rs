@@ -1,16 +1,36 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    thread,
+    time::Duration,
+};
 
 use adw::{
-    prelude::{PreferencesGroupExt, PreferencesRowExt},
-    PreferencesGroup, SpinRow,
+    prelude::{
+        ActionRowExt, ComboRowExt, EntryRowExt, ExpanderRowExt, PreferencesGroupExt,
+        PreferencesRowExt,
+    },
+    EntryRow, ExpanderRow, PreferencesGroup, SpinRow,
 };
+use dbus::{blocking::Connection, Error};
 use gtk::{
     prelude::BoxExt,
-    prelude::{ButtonExt, WidgetExt},
-    DrawingArea,
+    prelude::{ButtonExt, EditableExt, RangeExt, ScaleExt, WidgetExt},
+    DrawingArea, StringList,
 };
+use re_set_lib::ERROR;
+#[cfg(debug_assertions)]
+use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
 
-use crate::utils::{get_environment, is_gnome, is_hyprland, is_kde, Monitor, GNOME, HYPRLAND};
+use crate::{
+    backend::{mirror::align_mirrors, sway::SUBPIXEL_LAYOUTS},
+    r#const::{BASE, DBUS_PATH, INTERFACE},
+    utils::{
+        to_millihertz, CustomMode, Monitor, MonitorBrightness, Size, BRIGHTNESS_BACKLIGHT,
+        BRIGHTNESS_DDC, CAPABILITIES, MAX_BRIGHTNESS, MAX_GAMMA, MAX_MODE_SIZE, MAX_REFRESH_RATE,
+        MAX_SDR_BRIGHTNESS, MIN_BRIGHTNESS, MIN_GAMMA, MIN_SDR_BRIGHTNESS,
+    },
+};
 
 use super::handlers::{apply_monitor_clicked, rearrange_monitors, scaling_update};
 
@@ -40,32 +60,28 @@ pub fn add_save_button(
     drawing_ref_save: DrawingArea,
     apply_row: gtk::Box,
 ) -> Option<gtk::Button> {
-    let mut save = None;
-    match get_environment().as_str() {
-        GNOME | "ubuntu:GNOME" | HYPRLAND => {
-            let button = gtk::Button::builder()
-                .label("Save")
-                .hexpand_set(false)
-                .halign(gtk::Align::End)
-                .sensitive(false)
-                .build();
-            button.set_tooltip_markup(Some("Persistant saving of configuration"));
-            button.connect_clicked(move |_| {
-                apply_monitor_clicked(
-                    save_ref.clone(),
-                    fallback_save_ref.clone(),
-                    &settings_box_ref_save,
-                    &drawing_ref_save,
-                    false,
-                    true,
-                );
-            });
-            apply_row.append(&button);
-            save = Some(button);
-        }
-        _ => (),
+    if !CAPABILITIES.persistent_save {
+        return None;
     }
-    save
+    let button = gtk::Button::builder()
+        .label("Save")
+        .hexpand_set(false)
+        .halign(gtk::Align::End)
+        .sensitive(false)
+        .build();
+    button.set_tooltip_markup(Some("Persistant saving of configuration"));
+    button.connect_clicked(move |_| {
+        apply_monitor_clicked(
+            save_ref.clone(),
+            fallback_save_ref.clone(),
+            &settings_box_ref_save,
+            &drawing_ref_save,
+            false,
+            true,
+        );
+    });
+    apply_row.append(&button);
+    Some(button)
 }
 
 pub fn add_primary_monitor_option(
@@ -128,7 +144,7 @@ pub fn add_vrr_monitor_option(
     let vrr = adw::SwitchRow::new();
     vrr.set_title("Variable Refresh-Rate");
     vrr.set_active(vrr_value);
-    if get_environment().as_str() == HYPRLAND {
+    if !CAPABILITIES.reports_vrr {
         vrr.set_tooltip_markup(Some("Please note that this option will set the configuration for this monitor, however, if your monitor does not offer VRR, this setting will fail to make a change."));
     } else {
         vrr.set_tooltip_markup(Some("Enable or disable Variable Refresh Rate"));
@@ -141,7 +157,7 @@ pub fn add_vrr_monitor_option(
                 Some(&glib::Variant::from(true)),
             )
             .expect("Could not activate reset action");
-        if is_hyprland() || is_kde() { 
+        if !CAPABILITIES.reports_vrr {
             state.activate_action(
                 "win.banner",
                 Some(&glib::Variant::from("Note, VRR is a non reported feature and is therefore shown by default despite possible incompatibility. Additionally, VRR might require a reload or persistent saving instead of temporary application." ))
@@ -151,6 +167,297 @@ pub fn add_vrr_monitor_option(
     settings.add(&vrr);
 }
 
+pub fn add_subpixel_monitor_option(
+    monitor_index: usize,
+    monitors: Rc<RefCell<Vec<Monitor>>>,
+    settings: &PreferencesGroup,
+) {
+    let subpixel_value;
+    {
+        let monitor = monitors.borrow();
+        let monitor = monitor.get(monitor_index).unwrap();
+        if !monitor.features.subpixel {
+            return;
+        }
+        subpixel_value = monitor.subpixel.clone();
+    }
+
+    let model_list = StringList::new(&SUBPIXEL_LAYOUTS);
+    let subpixel = adw::ComboRow::new();
+    subpixel.set_title("Subpixel Layout");
+    subpixel.set_tooltip_markup(Some(
+        "Changes the subpixel layout used for font antialiasing",
+    ));
+    subpixel.set_model(Some(&model_list));
+    if let Some(index) = SUBPIXEL_LAYOUTS
+        .iter()
+        .position(|layout| *layout == subpixel_value)
+    {
+        subpixel.set_selected(index as u32);
+    }
+    subpixel.connect_selected_item_notify(move |dropdown| {
+        let selected = SUBPIXEL_LAYOUTS.get(dropdown.selected() as usize);
+        if selected.is_none() {
+            return;
+        }
+        monitors
+            .borrow_mut()
+            .get_mut(monitor_index)
+            .unwrap()
+            .subpixel = selected.unwrap().to_string();
+        dropdown
+            .activate_action(
+                "monitor.reset_monitor_buttons",
+                Some(&glib::Variant::from(true)),
+            )
+            .expect("Could not activate reset action");
+    });
+    settings.add(&subpixel);
+}
+
+pub fn add_max_render_time_option(
+    monitor_index: usize,
+    monitors: Rc<RefCell<Vec<Monitor>>>,
+    settings: &PreferencesGroup,
+) {
+    let max_render_time_value;
+    {
+        let monitor = monitors.borrow();
+        let monitor = monitor.get(monitor_index).unwrap();
+        if !monitor.features.max_render_time {
+            return;
+        }
+        max_render_time_value = monitor.max_render_time;
+    }
+
+    let adjustment = gtk::Adjustment::new(max_render_time_value as f64, 0.0, 100.0, 1.0, 0.0, 0.0);
+    let max_render_time = adw::SpinRow::new(Some(&adjustment), 1.0, 0);
+    max_render_time.set_title("Max Render Time");
+    max_render_time.set_tooltip_markup(Some("Delays rendering of frames in order to reduce latency, the value is in milliseconds.\n0 disables this option."));
+    max_render_time.connect_value_notify(move |state| {
+        monitors
+            .borrow_mut()
+            .get_mut(monitor_index)
+            .unwrap()
+            .max_render_time = state.value() as u32;
+        state
+            .activate_action(
+                "monitor.reset_monitor_buttons",
+                Some(&glib::Variant::from(true)),
+            )
+            .expect("Could not activate reset action");
+    });
+    settings.add(&max_render_time);
+}
+
+pub fn add_hdr_monitor_options(
+    monitor_index: usize,
+    monitors: Rc<RefCell<Vec<Monitor>>>,
+    settings: &PreferencesGroup,
+) {
+    let hdr_value;
+    {
+        let monitor = monitors.borrow();
+        let monitor = monitor.get(monitor_index).unwrap();
+        if !monitor.features.hdr {
+            return;
+        }
+        hdr_value = monitor.hdr;
+    }
+
+    let hdr = adw::SwitchRow::new();
+    hdr.set_title("HDR");
+    hdr.set_active(hdr_value.enabled);
+    hdr.set_tooltip_markup(Some("Enable or disable High Dynamic Range"));
+    let hdr_ref = monitors.clone();
+    hdr.connect_active_notify(move |state| {
+        hdr_ref
+            .borrow_mut()
+            .get_mut(monitor_index)
+            .unwrap()
+            .hdr
+            .enabled = state.is_active();
+        state
+            .activate_action(
+                "monitor.reset_monitor_buttons",
+                Some(&glib::Variant::from(true)),
+            )
+            .expect("Could not activate reset action");
+    });
+    settings.add(&hdr);
+
+    let wcg = adw::SwitchRow::new();
+    wcg.set_title("Wide Color Gamut");
+    wcg.set_active(hdr_value.wide_color_gamut);
+    wcg.set_tooltip_markup(Some(
+        "Uses the full color gamut of the monitor instead of sRGB.\nHDR always uses the wide color gamut.",
+    ));
+    let wcg_ref = monitors.clone();
+    wcg.connect_active_notify(move |state| {
+        wcg_ref
+            .borrow_mut()
+            .get_mut(monitor_index)
+            .unwrap()
+            .hdr
+            .wide_color_gamut = state.is_active();
+        state
+            .activate_action(
+                "monitor.reset_monitor_buttons",
+                Some(&glib::Variant::from(true)),
+            )
+            .expect("Could not activate reset action");
+    });
+    settings.add(&wcg);
+
+    let brightness_adjustment = gtk::Adjustment::new(
+        hdr_value.sdr_brightness as f64,
+        MIN_SDR_BRIGHTNESS as f64,
+        MAX_SDR_BRIGHTNESS as f64,
+        10.0,
+        0.0,
+        0.0,
+    );
+    let brightness = adw::SpinRow::new(Some(&brightness_adjustment), 10.0, 0);
+    brightness.set_title("SDR Brightness");
+    brightness.set_tooltip_markup(Some(
+        "Brightness of SDR content while HDR is enabled, the value is in nits.",
+    ));
+    let brightness_ref = monitors.clone();
+    brightness.connect_value_notify(move |state| {
+        brightness_ref
+            .borrow_mut()
+            .get_mut(monitor_index)
+            .unwrap()
+            .hdr
+            .sdr_brightness = state.value() as u32;
+        state
+            .activate_action(
+                "monitor.reset_monitor_buttons",
+                Some(&glib::Variant::from(true)),
+            )
+            .expect("Could not activate reset action");
+    });
+    settings.add(&brightness);
+
+    if !CAPABILITIES.sdr_gamut_wideness {
+        return;
+    }
+    let wideness_adjustment = gtk::Adjustment::new(
+        hdr_value.sdr_gamut_wideness * 100.0,
+        0.0,
+        100.0,
+        5.0,
+        0.0,
+        0.0,
+    );
+    let wideness = adw::SpinRow::new(Some(&wideness_adjustment), 5.0, 0);
+    wideness.set_title("SDR Gamut Wideness");
+    wideness.set_tooltip_markup(Some("Stretches SDR content onto the color gamut of the monitor while HDR is enabled, the value is in percent.\n0 shows SDR content in sRGB."));
+    wideness.connect_value_notify(move |state| {
+        monitors
+            .borrow_mut()
+            .get_mut(monitor_index)
+            .unwrap()
+            .hdr
+            .sdr_gamut_wideness = state.value() / 100.0;
+        state
+            .activate_action(
+                "monitor.reset_monitor_buttons",
+                Some(&glib::Variant::from(true)),
+            )
+            .expect("Could not activate reset action");
+    });
+    settings.add(&wideness);
+}
+
+// brightness and gamma are applied right away, they are not part of the monitor configuration
+pub fn add_brightness_monitor_option(
+    monitor_index: usize,
+    monitors: Rc<RefCell<Vec<Monitor>>>,
+    settings: &PreferencesGroup,
+) {
+    let name = monitors.borrow().get(monitor_index).unwrap().name.clone();
+    let brightness_value = get_brightness(&name);
+    if brightness_value.is_none() {
+        return;
+    }
+    let brightness_value = brightness_value.unwrap();
+
+    if !brightness_value.method.is_empty() {
+        let brightness_row = adw::ActionRow::new();
+        brightness_row.set_title("Brightness");
+        brightness_row.set_tooltip_markup(Some(match brightness_value.method.as_str() {
+            BRIGHTNESS_DDC => "Changes the brightness of the monitor via DDC/CI.",
+            BRIGHTNESS_BACKLIGHT => "Changes the backlight of the monitor.",
+            _ => "Dims the monitor via its gamma ramp, the backlight is not changed.",
+        }));
+        let brightness = gtk::Scale::with_range(
+            gtk::Orientation::Horizontal,
+            MIN_BRIGHTNESS as f64,
+            MAX_BRIGHTNESS as f64,
+            1.0,
+        );
+        brightness.set_value(brightness_value.brightness as f64);
+        brightness.set_hexpand(true);
+        brightness.set_draw_value(true);
+        let brightness_name = name.clone();
+        brightness.connect_value_changed(move |state| {
+            let name = brightness_name.clone();
+            let value = state.value() as u32;
+            thread::spawn(move || set_brightness(&name, value));
+        });
+        brightness_row.add_suffix(&brightness);
+        settings.add(&brightness_row);
+    }
+
+    if !brightness_value.gamma_supported {
+        return;
+    }
+    let gamma_adjustment =
+        gtk::Adjustment::new(brightness_value.gamma, MIN_GAMMA, MAX_GAMMA, 0.05, 0.0, 0.0);
+    let gamma = adw::SpinRow::new(Some(&gamma_adjustment), 0.05, 2);
+    gamma.set_title("Gamma");
+    gamma.set_tooltip_markup(Some(
+        "Values above 1.0 brighten dark colors, values below 1.0 darken them.",
+    ));
+    gamma.connect_value_notify(move |state| {
+        let name = name.clone();
+        let value = state.value();
+        thread::spawn(move || set_gamma(&name, value));
+    });
+    settings.add(&gamma);
+}
+
+fn get_brightness(name: &str) -> Option<MonitorBrightness> {
+    let conn = Connection::new_session().unwrap();
+    let proxy = conn.with_proxy(BASE, DBUS_PATH, Duration::from_millis(1000));
+    let res: Result<(MonitorBrightness,), Error> =
+        proxy.method_call(INTERFACE, "GetBrightness", (name,));
+    if let Err(_error) = res {
+        ERROR!(
+            format!("Could not fetch monitor brightness {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return None;
+    }
+    Some(res.unwrap().0)
+}
+
+fn set_brightness(name: &str, brightness: u32) -> bool {
+    let conn = Connection::new_session().unwrap();
+    let proxy = conn.with_proxy(BASE, DBUS_PATH, Duration::from_millis(1000));
+    let res: Result<(bool,), Error> =
+        proxy.method_call(INTERFACE, "SetBrightness", (name, brightness));
+    res.map(|val| val.0).unwrap_or(false)
+}
+
+fn set_gamma(name: &str, gamma: f64) -> bool {
+    let conn = Connection::new_session().unwrap();
+    let proxy = conn.with_proxy(BASE, DBUS_PATH, Duration::from_millis(1000));
+    let res: Result<(bool,), Error> = proxy.method_call(INTERFACE, "SetGamma", (name, gamma));
+    res.map(|val| val.0).unwrap_or(false)
+}
+
 pub fn add_enabled_monitor_option(
     monitor_index: usize,
     monitors_ref: Rc<RefCell<Vec<Monitor>>>,
@@ -187,7 +494,7 @@ pub fn add_enabled_monitor_option(
         let original_monitor = monitor.clone();
         monitor.enabled = state.is_active();
 
-        if is_gnome() {
+        if CAPABILITIES.compact_layout {
             if monitor.enabled {
                 rearrange_monitors(original_monitor, monitors);
             } else {
@@ -211,3 +518,257 @@ pub fn add_enabled_monitor_option(
     });
     settings.add(&enabled);
 }
+
+pub fn add_mirror_monitor_option(
+    monitor_index: usize,
+    monitors: Rc<RefCell<Vec<Monitor>>>,
+    settings: &PreferencesGroup,
+    drawing_area: DrawingArea,
+) {
+    let mut sources = vec![String::from("None")];
+    let mut selected = 0;
+    {
+        let monitors = monitors.borrow();
+        let monitor = monitors.get(monitor_index).unwrap();
+        if !monitor.enabled {
+            return;
+        }
+        for (index, other) in monitors.iter().enumerate() {
+            // monitors which are mirroring themselves can't be used as a source
+            if index == monitor_index || !other.enabled || !other.mirror.is_empty() {
+                continue;
+            }
+            if other.name == monitor.mirror {
+                selected = sources.len();
+            }
+            sources.push(other.name.clone());
+        }
+    }
+    if sources.len() < 2 {
+        return;
+    }
+
+    let model_list = StringList::new(&sources.iter().map(|val| val.as_str()).collect::<Vec<_>>());
+    let mirror = adw::ComboRow::new();
+    mirror.set_title("Mirror");
+    if CAPABILITIES.native_mirroring {
+        mirror.set_tooltip_markup(Some("Shows the content of another monitor on this monitor"));
+    } else {
+        mirror.set_tooltip_markup(Some("Shows the content of another monitor on this monitor.\nThe monitor is placed at the position of the other monitor, as the environment has no native mirroring."));
+    }
+    mirror.set_model(Some(&model_list));
+    mirror.set_selected(selected as u32);
+    mirror.connect_selected_item_notify(move |dropdown| {
+        let source = sources.get(dropdown.selected() as usize);
+        if source.is_none() {
+            return;
+        }
+        let source = source.unwrap();
+        {
+            let mut monitors = monitors.borrow_mut();
+            if dropdown.selected() == 0 {
+                // move the monitor next to the other monitors again
+                let furthest = monitors
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, monitor)| *index != monitor_index && monitor.enabled)
+                    .map(|(_, monitor)| monitor.offset.0 + monitor.handle_scaled_transform().0)
+                    .max()
+                    .unwrap_or(0);
+                let monitor = monitors.get_mut(monitor_index).unwrap();
+                monitor.mirror.clear();
+                monitor.offset.0 = furthest;
+            } else {
+                // monitors mirroring this monitor now mirror the new source instead
+                let name = monitors.get(monitor_index).unwrap().name.clone();
+                for monitor in monitors.iter_mut() {
+                    if monitor.mirror == name {
+                        monitor.mirror = source.clone();
+                    }
+                }
+                monitors.get_mut(monitor_index).unwrap().mirror = source.clone();
+            }
+            align_mirrors(&mut monitors);
+        }
+        drawing_area.queue_draw();
+        dropdown
+            .activate_action(
+                "monitor.reset_monitor_buttons",
+                Some(&glib::Variant::from(true)),
+            )
+            .expect("Could not activate reset action");
+    });
+    settings.add(&mirror);
+}
+
+#[derive(Clone)]
+struct CustomModeRows {
+    expander: ExpanderRow,
+    width: SpinRow,
+    height: SpinRow,
+    refresh_rate: SpinRow,
+    modeline: EntryRow,
+    // set while the rows are filled from a modeline
+    updating: Rc<Cell<bool>>,
+}
+
+pub fn add_custom_mode_option(
+    monitor_index: usize,
+    monitors: Rc<RefCell<Vec<Monitor>>>,
+    settings: &PreferencesGroup,
+    drawing_area: DrawingArea,
+) {
+    let custom_mode;
+    let fallback;
+    let enabled;
+    {
+        let monitors = monitors.borrow();
+        let monitor = monitors.get(monitor_index).unwrap();
+        if !monitor.features.custom_modes || !monitor.enabled {
+            return;
+        }
+        enabled = monitor.custom_mode.is_set();
+        if enabled {
+            custom_mode = monitor.custom_mode.clone();
+            // disabling the custom mode returns to the highest regular mode
+            fallback = monitor.available_modes.first().map(|mode| {
+                let (refresh_rate, id) = mode.refresh_rates.first().cloned().unwrap_or_default();
+                (mode.size, refresh_rate, id)
+            });
+        } else {
+            custom_mode = CustomMode {
+                width: monitor.size.0,
+                height: monitor.size.1,
+                refresh_rate: monitor.refresh_rate as f64 / 1000.0,
+                modeline: "".into(),
+            };
+            fallback = Some((monitor.size, monitor.refresh_rate, monitor.mode.clone()));
+        }
+    }
+
+    let expander = adw::ExpanderRow::builder()
+        .title("Custom Mode")
+        .subtitle("For monitors which do not report all of their modes")
+        .show_enable_switch(true)
+        .enable_expansion(enabled)
+        .build();
+    expander.set_tooltip_markup(Some("Uses a mode which is not reported by the monitor.\nUnsupported modes may result in a black screen, the changes are reverted if they are not confirmed."));
+    let size_row = |title: &str, value: i32| {
+        let adjustment =
+            gtk::Adjustment::new(value as f64, 1.0, MAX_MODE_SIZE as f64, 1.0, 0.0, 0.0);
+        let row = adw::SpinRow::new(Some(&adjustment), 1.0, 0);
+        row.set_title(title);
+        row
+    };
+    let width = size_row("Width", custom_mode.width);
+    let height = size_row("Height", custom_mode.height);
+    let adjustment = gtk::Adjustment::new(
+        custom_mode.refresh_rate,
+        1.0,
+        MAX_REFRESH_RATE,
+        0.001,
+        0.0,
+        0.0,
+    );
+    let refresh_rate = adw::SpinRow::new(Some(&adjustment), 0.001, 3);
+    refresh_rate.set_title("Refresh-Rate");
+    let modeline = adw::EntryRow::builder()
+        .title("Modeline")
+        .text(&custom_mode.modeline)
+        .show_apply_button(true)
+        .build();
+    modeline.set_tooltip_markup(Some("Optional xorg modeline, width, height and refresh-rate are taken from the modeline.\nNot every compositor supports modelines."));
+    expander.add_row(&width);
+    expander.add_row(&height);
+    expander.add_row(&refresh_rate);
+    expander.add_row(&modeline);
+
+    let rows = CustomModeRows {
+        expander: expander.clone(),
+        width,
+        height,
+        refresh_rate,
+        modeline,
+        updating: Rc::new(Cell::new(false)),
+    };
+    let update = Rc::new(move |rows: &CustomModeRows| {
+        if rows.updating.get() {
+            return;
+        }
+        {
+            let mut monitors = monitors.borrow_mut();
+            let monitor = monitors.get_mut(monitor_index).unwrap();
+            let original_monitor = monitor.clone();
+            if rows.expander.enables_expansion() {
+                let custom_mode = CustomMode {
+                    width: rows.width.value() as i32,
+                    height: rows.height.value() as i32,
+                    refresh_rate: rows.refresh_rate.value(),
+                    modeline: rows.modeline.text().trim().to_string(),
+                };
+                if let Err(error) = custom_mode.validate() {
+                    rows.expander.set_subtitle(&error);
+                    rows.expander.add_css_class("error");
+                    return;
+                }
+                monitor.size = Size(custom_mode.width, custom_mode.height);
+                monitor.refresh_rate = to_millihertz(custom_mode.refresh_rate);
+                monitor.custom_mode = custom_mode;
+            } else {
+                monitor.custom_mode = CustomMode::default();
+                if let Some((size, refresh_rate, id)) = fallback.clone() {
+                    monitor.size = size;
+                    monitor.refresh_rate = refresh_rate;
+                    monitor.mode = id;
+                }
+            }
+            let (width, height) = monitor.handle_scaled_transform();
+            monitor.drag_information.width = width;
+            monitor.drag_information.height = height;
+            rearrange_monitors(original_monitor, monitors);
+        }
+        rows.expander
+            .set_subtitle("For monitors which do not report all of their modes");
+        rows.expander.remove_css_class("error");
+        drawing_area.queue_draw();
+        rows.expander
+            .activate_action(
+                "monitor.reset_monitor_buttons",
+                Some(&glib::Variant::from(true)),
+            )
+            .expect("Could not activate reset action");
+    });
+
+    let expander_rows = rows.clone();
+    let expander_update = update.clone();
+    expander.connect_enable_expansion_notify(move |_| expander_update(&expander_rows));
+    for spin_row in [&rows.width, &rows.height, &rows.refresh_rate] {
+        let spin_rows = rows.clone();
+        let spin_update = update.clone();
+        spin_row.connect_value_notify(move |_| spin_update(&spin_rows));
+    }
+    let modeline_rows = rows.clone();
+    rows.modeline.connect_apply(move |entry| {
+        let text = entry.text().trim().to_string();
+        if !text.is_empty() {
+            match CustomMode::from_modeline(&text) {
+                Ok(custom_mode) => {
+                    modeline_rows.updating.set(true);
+                    modeline_rows.width.set_value(custom_mode.width as f64);
+                    modeline_rows.height.set_value(custom_mode.height as f64);
+                    modeline_rows
+                        .refresh_rate
+                        .set_value(custom_mode.refresh_rate);
+                    modeline_rows.updating.set(false);
+                }
+                Err(error) => {
+                    modeline_rows.expander.set_subtitle(&error);
+                    modeline_rows.expander.add_css_class("error");
+                    return;
+                }
+            }
+        }
+        update(&modeline_rows);
+    });
+    settings.add(&expander);
+}
@@ -1,13 +1,8 @@
 use std::{
-    cell::{RefCell, RefMut},
+    cell::{Cell, RefCell, RefMut},
     cmp,
     f64::consts,
     rc::Rc,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
-    thread,
     time::Duration,
 };
 
@@ -22,7 +17,6 @@ use dbus::{blocking::Connection, Error};
 use glib::object::CastNone;
 use gtk::{
     gdk::prelude::SurfaceExt,
-    gio,
     prelude::{BoxExt, DrawingAreaExtManual, GdkCairoContextExt, NativeExt, WidgetExt},
     DrawingArea, StringList, StringObject,
 };
@@ -32,17 +26,19 @@ use re_set_lib::{utils::config::get_config_value, ERROR};
 use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
 
 use crate::{
-    backend::utils::get_wl_backend,
+    backend::mirror::{align_mirrors, mirror_source},
     r#const::{BASE, DBUS_PATH, INTERFACE},
     utils::{
-        get_environment, get_monitor_data, is_gnome, AlertWrapper, Monitor,
-        SnapDirectionHorizontal, SnapDirectionVertical, GNOME, HYPRLAND, KDE,
+        format_refresh_rate, get_monitor_data, is_nonfunctional_scale, to_millihertz, CustomMode,
+        Monitor, SnapDirectionHorizontal, SnapDirectionVertical, ValidationError, CAPABILITIES,
     },
 };
 
 use super::{
     general::{
-        add_enabled_monitor_option, add_primary_monitor_option, add_vrr_monitor_option,
+        add_brightness_monitor_option, add_custom_mode_option, add_enabled_monitor_option,
+        add_hdr_monitor_options, add_max_render_time_option, add_mirror_monitor_option,
+        add_primary_monitor_option, add_subpixel_monitor_option, add_vrr_monitor_option,
         arbitrary_add_scaling_adjustment,
     },
     gnome::{g_add_scaling_adjustment, reload_scale},
@@ -62,26 +58,69 @@ pub fn apply_monitor_clicked(
     revert: bool,
     persistent: bool,
 ) {
-    let previous_state = Arc::new(AtomicBool::new(false));
-    let previous_state_ref = previous_state.clone();
     let conn = Connection::new_session().unwrap();
     let proxy = conn.with_proxy(BASE, DBUS_PATH, Duration::from_millis(1000));
-    let res: Result<(), Error> = if revert {
+    if !revert {
+        let validation: Result<(bool, Vec<ValidationError>), Error> = proxy.method_call(
+            INTERFACE,
+            "ValidateMonitors",
+            (monitor_ref.borrow().clone(),),
+        );
+        // the configuration is still applied when the daemon can't validate it
+        if let Ok((false, errors)) = validation {
+            let message = errors
+                .iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<String>>()
+                .join(", ");
+            settings_ref
+                .activate_action("win.banner", Some(&glib::Variant::from(message)))
+                .expect("Could not show banner");
+            return;
+        }
+    }
+    // the daemon reverts the configuration on its own unless it is confirmed within the timeout,
+    // 0 uses the timeout of the config, reverts and the GNOME popup don't need to be confirmed
+    let res: Result<u32, Error> = if revert {
         if persistent {
             proxy.method_call(INTERFACE, "SaveMonitors", (fallback.borrow().clone(),))
         } else {
             proxy.method_call(INTERFACE, "SetMonitors", (fallback.borrow().clone(),))
         }
+        .map(|()| 0)
+    } else if persistent && CAPABILITIES.confirms_save {
+        proxy
+            .method_call(INTERFACE, "SaveMonitors", (monitor_ref.borrow().clone(),))
+            .map(|()| 0)
     } else if persistent {
-        proxy.method_call(INTERFACE, "SaveMonitors", (monitor_ref.borrow().clone(),))
+        proxy
+            .method_call(
+                INTERFACE,
+                "SaveMonitorsWithTimeout",
+                (monitor_ref.borrow().clone(), 0u32),
+            )
+            .map(|(timeout,)| timeout)
     } else {
-        proxy.method_call(INTERFACE, "SetMonitors", (monitor_ref.borrow().clone(),))
+        proxy
+            .method_call(
+                INTERFACE,
+                "SetMonitorsWithTimeout",
+                (monitor_ref.borrow().clone(), 0u32),
+            )
+            .map(|(timeout,)| timeout)
     };
-    if let Err(_error) = res {
+    // the daemon reports the reason, e.g. a rejected configuration or a missing tool
+    if let Err(error) = &res {
         ERROR!(
-            format!("Could not apply monitor configuration {}", _error),
+            format!("Could not apply monitor configuration {}", error),
             ErrorLevel::Recoverable
         );
+        let message = error
+            .message()
+            .unwrap_or("Could not apply monitor configuration");
+        settings_ref
+            .activate_action("win.banner", Some(&glib::Variant::from(message)))
+            .expect("Could not show banner");
     }
     if let Some(child) = settings_ref.first_child() {
         settings_ref.remove(&child);
@@ -98,13 +137,13 @@ pub fn apply_monitor_clicked(
         index,
         drawing_ref,
     ));
-    if persistent {
+    if persistent && res.is_ok() {
         get_config_value("Monitor", "save_warning", |value| {
             if let Some(warning) = value.as_bool() {
                 if warning {
                     settings_ref.activate_action(
                         "win.banner",
-                        Some(&glib::Variant::from("When using hyprland, make sure to include the created file in your config to make the changes permanent." ))
+                        Some(&glib::Variant::from("If your compositor uses a separate file for the saved configuration, make sure to include it in your config to make the changes permanent." ))
                     ).expect("Could not show banner");
                 }
             }
@@ -118,53 +157,101 @@ pub fn apply_monitor_clicked(
         )
         .expect("Could not execute reset action");
 
-    if !revert {
-        // Gnome has their own popup, hence two popups would appear -> solution, disable ours
-        if persistent && is_gnome() {
+    if revert || res.is_err() {
+        return;
+    }
+    // Gnome has their own popup, hence two popups would appear -> solution, disable ours
+    if persistent && CAPABILITIES.confirms_save {
+        return;
+    }
+    show_confirm_dialog(
+        monitor_ref,
+        fallback,
+        settings_ref,
+        res.unwrap(),
+        persistent,
+    );
+}
+
+/// Asks whether the applied configuration should be kept, the daemon reverts it on its own once
+/// the timeout has passed.
+pub fn show_confirm_dialog(
+    monitor_ref: Rc<RefCell<Vec<Monitor>>>,
+    fallback: Rc<RefCell<Vec<Monitor>>>,
+    settings_ref: &gtk::Box,
+    timeout: u32,
+    persistent: bool,
+) {
+    let popup = adw::AlertDialog::new(
+        Some("Confirm Configuration"),
+        Some(&confirm_message(timeout)),
+    );
+    popup.add_responses(&[("revert", "Revert"), ("confirm", "Confirm")]);
+    popup.set_response_appearance("revert", adw::ResponseAppearance::Destructive);
+    popup.set_default_response(Some("revert"));
+    popup.set_close_response("revert");
+
+    // set once the dialog is answered or the daemon reverted the configuration
+    let finished = Rc::new(Cell::new(false));
+
+    let finished_ref = finished.clone();
+    popup.connect_response(Some("confirm"), move |dialog, _| {
+        finished_ref.set(true);
+        let conn = Connection::new_session().unwrap();
+        let proxy = conn.with_proxy(BASE, DBUS_PATH, Duration::from_millis(1000));
+        let res: Result<(bool,), Error> = proxy.method_call(INTERFACE, "ConfirmMonitors", ());
+        if let Ok((true,)) = res {
+            fallback.replace(monitor_ref.borrow().clone());
+        } else {
+            ERROR!(
+                "Configuration was reverted before it was confirmed",
+                ErrorLevel::Recoverable
+            );
+        }
+        dialog.close();
+    });
+
+    let settings = settings_ref.clone();
+    let finished_ref = finished.clone();
+    popup.connect_response(Some("revert"), move |dialog, _| {
+        if finished_ref.replace(true) {
             return;
         }
-        let popup = adw::AlertDialog::new(Some("Confirm Configuration"), Some("Is this configuration correct?\n
-         Please confirm if this is the case, otherwise the configuration will automatically be reverted."));
-        popup.add_responses(&[("revert", "Revert"), ("confirm", "Confirm")]);
-        popup.set_response_appearance("revert", adw::ResponseAppearance::Destructive);
-        popup.set_default_response(Some("revert"));
-        popup.set_close_response("revert");
-
-        let settings = settings_ref.clone();
-        popup.connect_response(Some("confirm"), move |dialog, _| {
-            fallback.replace(monitor_ref.borrow().clone());
-            dialog.close();
-        });
-        popup.connect_response(Some("revert"), move |dialog, _| {
-            previous_state.store(true, Ordering::SeqCst);
-            settings
-                .activate_action(
-                    "monitor.revert_monitors",
-                    Some(&glib::Variant::from((true, false))),
-                )
-                .expect("Could not activate revert action");
-            dialog.close();
-        });
+        settings
+            .activate_action(
+                "monitor.revert_monitors",
+                Some(&glib::Variant::from((true, persistent))),
+            )
+            .expect("Could not activate revert action");
+        dialog.close();
+    });
 
-        let settings = settings_ref.clone();
-        let thread_settings = AlertWrapper {
-            popup: popup.clone(),
-        };
+    // the daemon reverts on its own, the new configuration is shown via MonitorsChanged
+    let mut remaining = timeout;
+    let countdown = popup.clone();
+    glib::timeout_add_seconds_local(1, move || {
+        if finished.get() {
+            return glib::ControlFlow::Break;
+        }
+        remaining = remaining.saturating_sub(1);
+        if remaining == 0 {
+            finished.set(true);
+            countdown.close();
+            return glib::ControlFlow::Break;
+        }
+        countdown.set_body(&confirm_message(remaining));
+        glib::ControlFlow::Continue
+    });
 
-        gio::spawn_blocking(move || {
-            thread::sleep(Duration::from_millis(5000));
-            if previous_state_ref.load(Ordering::SeqCst) {
-                return;
-            }
-            glib::spawn_future(async move {
-                glib::idle_add_once(move || {
-                    thread_settings.action();
-                });
-            });
-        });
+    popup.present(settings_ref);
+}
 
-        popup.present(&settings);
-    }
+fn confirm_message(seconds: u32) -> String {
+    format!(
+        "Is this configuration correct?\n
+Please confirm if this is the case, otherwise the configuration will automatically be reverted in {} seconds.",
+        seconds
+    )
 }
 
 pub fn reset_monitor_clicked(
@@ -222,12 +309,27 @@ pub fn get_monitor_settings_group(
     let enabled_ref = clicked_monitor.clone();
     add_enabled_monitor_option(monitor_index, enabled_ref, &settings, drawing_area.clone());
 
+    let mirror_ref = clicked_monitor.clone();
+    add_mirror_monitor_option(monitor_index, mirror_ref, &settings, drawing_area.clone());
+
     let primary_ref = clicked_monitor.clone();
     add_primary_monitor_option(monitor_index, primary_ref, &settings);
 
     let vrr_ref = clicked_monitor.clone();
     add_vrr_monitor_option(monitor_index, vrr_ref, &settings);
 
+    let subpixel_ref = clicked_monitor.clone();
+    add_subpixel_monitor_option(monitor_index, subpixel_ref, &settings);
+
+    let max_render_time_ref = clicked_monitor.clone();
+    add_max_render_time_option(monitor_index, max_render_time_ref, &settings);
+
+    let hdr_ref = clicked_monitor.clone();
+    add_hdr_monitor_options(monitor_index, hdr_ref, &settings);
+
+    let brightness_ref = clicked_monitor.clone();
+    add_brightness_monitor_option(monitor_index, brightness_ref, &settings);
+
     let scaling_ref = clicked_monitor.clone();
     let scaling = add_scale_adjustment(
         monitor.scale,
@@ -328,6 +430,7 @@ pub fn get_monitor_settings_group(
             let highest = refresh_rates.first().unwrap();
             monitor.mode = String::from(&mode.id);
             monitor.refresh_rate = highest.0;
+            monitor.custom_mode = CustomMode::default();
             let new_size_x: i32 = x.parse().unwrap();
             let new_size_y: i32 = y.parse().unwrap();
             let original_monitor = monitor.clone();
@@ -342,7 +445,7 @@ pub fn get_monitor_settings_group(
 
         let mut converted_rates = Vec::new();
         for rate in refresh_rates.into_iter() {
-            let string_rate = rate.0.to_string() + "Hz";
+            let string_rate = format_refresh_rate(rate.0) + " Hz";
             if uses_ids {
                 if !converted_rates.contains(&string_rate) {
                     converted_rates.push(string_rate);
@@ -400,7 +503,7 @@ pub fn get_monitor_settings_group(
     let mut index = 0;
     let mut iter = 0;
     for refresh_rate in refresh_rates.into_iter() {
-        let rate = refresh_rate.0.to_string() + "Hz";
+        let rate = format_refresh_rate(refresh_rate.0) + " Hz";
         if monitor.uses_mode_id && converted_rates.contains(&rate) {
             // id users might see duplicate entries otherwise
             continue;
@@ -422,15 +525,16 @@ pub fn get_monitor_settings_group(
     refresh_rate.connect_selected_item_notify(move |dropdown| {
         let mut monitors = refresh_rate_ref.borrow_mut();
         let monitor = monitors.get_mut(monitor_index).unwrap();
-        let selected = dropdown
-            .selected_item()
-            .and_downcast_ref::<StringObject>()
-            .unwrap()
-            .string()
-            .to_string()
-            .trim_end_matches("Hz")
-            .parse()
-            .unwrap();
+        let selected = to_millihertz(
+            dropdown
+                .selected_item()
+                .and_downcast_ref::<StringObject>()
+                .unwrap()
+                .string()
+                .trim_end_matches(" Hz")
+                .parse()
+                .unwrap(),
+        );
         if monitor.uses_mode_id {
             for mode in monitor.available_modes.iter() {
                 if mode.size.0 == monitor.size.0 && mode.size.1 == monitor.size.1 {
@@ -445,6 +549,7 @@ pub fn get_monitor_settings_group(
             }
         }
         monitor.refresh_rate = selected;
+        monitor.custom_mode = CustomMode::default();
         dropdown
             .activate_action(
                 "monitor.reset_monitor_buttons",
@@ -454,6 +559,14 @@ pub fn get_monitor_settings_group(
     });
     settings.add(&refresh_rate);
 
+    let custom_mode_ref = clicked_monitor.clone();
+    add_custom_mode_option(
+        monitor_index,
+        custom_mode_ref,
+        &settings,
+        drawing_area.clone(),
+    );
+
     settings
 }
 
@@ -469,7 +582,9 @@ pub fn rearrange_monitors(original_monitor: Monitor, mut monitors: RefMut<'_, Ve
     // and set the rightmost side for overlapped monitors
     for monitor in monitors.iter_mut() {
         let is_original = monitor.id == original_monitor.id;
-        if is_gnome() && ((is_original && !original_monitor.enabled) || !monitor.enabled) {
+        if CAPABILITIES.compact_layout
+            && ((is_original && !original_monitor.enabled) || !monitor.enabled)
+        {
             // no need to check for monitors that are disabled on gnome -> they do not affect
             // arrangement
             continue;
@@ -502,7 +617,7 @@ pub fn rearrange_monitors(original_monitor: Monitor, mut monitors: RefMut<'_, Ve
     for monitor in monitors.iter_mut() {
         let is_original = monitor.id == original_monitor.id;
         let (width, height) = monitor.handle_scaled_transform();
-        if is_gnome() {
+        if CAPABILITIES.compact_layout {
             monitor.offset.1 -= top;
             monitor.offset.0 -= left;
             if is_original && !original_monitor.enabled {
@@ -529,10 +644,16 @@ pub fn rearrange_monitors(original_monitor: Monitor, mut monitors: RefMut<'_, Ve
     // opposite order
     // second: bool flag to indicate overlap
     let mut overlaps = vec![(false, false); monitors.len()];
+    // mirrored monitors always overlap with their source
+    let (mirrored, _, _) = get_mirror_groups(&monitors);
     // check for overlaps
     for (index, monitor) in monitors.iter().enumerate() {
         for (other_index, other_monitor) in monitors.iter().enumerate() {
-            if monitor.id == other_monitor.id || overlaps[other_index].0 {
+            if monitor.id == other_monitor.id
+                || overlaps[other_index].0
+                || mirrored[index]
+                || mirrored[other_index]
+            {
                 continue;
             }
             let (width, height) = other_monitor.handle_scaled_transform();
@@ -570,41 +691,23 @@ pub fn add_scale_adjustment(
     settings: &PreferencesGroup,
     drawing_area: DrawingArea,
 ) -> Scale {
-    // Different environments allow differing values
-    // Hyprland allows arbitrary scales, Gnome offers a set of supported scales per monitor mode
-    match get_environment().as_str() {
-        HYPRLAND => Scale::Arbitrary(arbitrary_add_scaling_adjustment(
-            scale,
-            monitor_index,
-            scaling_ref,
-            settings,
-            drawing_area,
-        )),
-        GNOME | "ubuntu:GNOME" => Scale::Defined(g_add_scaling_adjustment(
-            scale,
-            monitor_index,
-            scaling_ref,
-            settings,
-            drawing_area,
-        )),
-        KDE => Scale::Arbitrary(arbitrary_add_scaling_adjustment(
+    // Gnome offers a set of supported scales per monitor mode, other environments allow any scale
+    if CAPABILITIES.arbitrary_scales {
+        return Scale::Arbitrary(arbitrary_add_scaling_adjustment(
             scale,
             monitor_index,
             scaling_ref,
             settings,
             drawing_area,
-        )),
-        _ => match get_wl_backend().as_str() {
-            "WLR" | "KWIN" => Scale::Arbitrary(arbitrary_add_scaling_adjustment(
-                scale,
-                monitor_index,
-                scaling_ref,
-                settings,
-                drawing_area,
-            )),
-            _ => unreachable!(),
-        },
+        ));
     }
+    Scale::Defined(g_add_scaling_adjustment(
+        scale,
+        monitor_index,
+        scaling_ref,
+        settings,
+        drawing_area,
+    ))
 }
 
 pub fn drawing_callback(
@@ -626,6 +729,10 @@ pub fn drawing_callback(
         area.set_height_request(max_height);
         area.height_request();
 
+        // mirrored monitors are drawn together with their source
+        align_mirrors(&mut monitor_data.borrow_mut());
+        let (mirrored, labels, group_clicked) = get_mirror_groups(&monitor_data.borrow());
+
         // logic to ensure max width and height with offsets and sized do not overflow drawing area
         let mut max_monitor_width = 0;
         let mut max_monitor_height = 0;
@@ -666,7 +773,7 @@ pub fn drawing_callback(
         let height_offset =
             (max_height - (max_monitor_height / factor) - (min_monitor_height / factor)) / 2;
 
-        for monitor in monitor_data.borrow_mut().iter_mut() {
+        for (index, monitor) in monitor_data.borrow_mut().iter_mut().enumerate() {
             // handle transform which could invert height and width
             let (width, height) = monitor.handle_scaled_transform();
             let offset_x = monitor.drag_information.drag_x + monitor.offset.0;
@@ -677,6 +784,10 @@ pub fn drawing_callback(
             monitor.drag_information.factor = factor;
             monitor.drag_information.border_offset_x = width_offset;
             monitor.drag_information.border_offset_y = height_offset;
+            if mirrored[index] {
+                continue;
+            }
+            let clicked = group_clicked[index];
 
             let offset_x = width_offset + offset_x / factor;
             let offset_y = height_offset + offset_y / factor;
@@ -687,7 +798,7 @@ pub fn drawing_callback(
             let rec = gtk::gdk::Rectangle::new(offset_x + 5, offset_y + 5, width - 5, height - 5);
             if monitor.drag_information.drag_active {
                 context.set_source_color(&draggin_color);
-            } else if monitor.drag_information.clicked {
+            } else if clicked {
                 context.set_source_color(&clicked_color);
             } else {
                 context.set_source_color(&color);
@@ -753,7 +864,7 @@ pub fn drawing_callback(
             context.stroke().expect("Could not fill context");
 
             // text
-            if monitor.drag_information.drag_active || monitor.drag_information.clicked {
+            if monitor.drag_information.drag_active || clicked {
                 context.set_source_color(&selected_text_color);
             }
             // define text to be scaled with monitor size
@@ -763,7 +874,7 @@ pub fn drawing_callback(
             context.set_font_size(size);
             context.move_to((offset_x + 10) as f64, offset_y as f64 + gap + TOP_GAP);
             context
-                .show_text(&monitor.name.clone())
+                .show_text(&labels[index])
                 .expect("Could not draw text");
             context.move_to(
                 (offset_x + 10) as f64,
@@ -790,6 +901,32 @@ pub fn drawing_callback(
     });
 }
 
+/// Returns for each monitor whether it is drawn as part of its source, the label of the drawn
+/// monitors and whether any monitor of the group is clicked.
+pub fn get_mirror_groups(monitors: &[Monitor]) -> (Vec<bool>, Vec<String>, Vec<bool>) {
+    let mut mirrored = vec![false; monitors.len()];
+    let mut labels: Vec<String> = monitors.iter().map(|val| val.name.clone()).collect();
+    let mut group_clicked: Vec<bool> = monitors
+        .iter()
+        .map(|val| val.drag_information.clicked)
+        .collect();
+    for (index, monitor) in monitors.iter().enumerate() {
+        let source = mirror_source(monitor, monitors);
+        if source.is_none() {
+            continue;
+        }
+        let source = source.unwrap();
+        let source_index = monitors
+            .iter()
+            .position(|val| val.name == source.name)
+            .unwrap();
+        mirrored[index] = true;
+        labels[source_index] += &format!(" + {}", monitor.name);
+        group_clicked[source_index] |= monitor.drag_information.clicked;
+    }
+    (mirrored, labels, group_clicked)
+}
+
 pub fn monitor_drag_start(
     x: f64,
     y: f64,
@@ -797,42 +934,53 @@ pub fn monitor_drag_start(
     settings_box_ref: &gtk::Box,
     drawing_area: &DrawingArea,
 ) {
-    let mut iter = -1;
-    let mut previous = -1;
+    let iter;
     {
         let mut monitors = start_ref.borrow_mut();
-        for (index, monitor) in monitors.iter_mut().enumerate() {
-            let x = x as i32;
-            let y = y as i32;
-            if monitor.is_coordinate_within(x, y) {
-                if monitor.enabled {
-                    monitor.drag_information.drag_active = true;
-                }
-                monitor.drag_information.clicked = true;
-                monitor.drag_information.origin_x = monitor.offset.0;
-                monitor.drag_information.origin_y = monitor.offset.1;
-                if let Some(child) = settings_box_ref.first_child() {
-                    settings_box_ref.remove(&child);
-                }
-                iter = index as i32;
-            } else if monitor.drag_information.clicked {
-                previous = index as i32;
-            }
-        }
-        if iter == -1 {
+        let x = x as i32;
+        let y = y as i32;
+        let candidates: Vec<usize> = monitors
+            .iter()
+            .enumerate()
+            .filter(|(_, monitor)| monitor.is_coordinate_within(x, y))
+            .map(|(index, _)| index)
+            .collect();
+        if candidates.is_empty() {
             return;
         }
-        if previous != -1 {
-            monitors
-                .get_mut(previous as usize)
-                .unwrap()
-                .drag_information
-                .clicked = false;
+        // mirrored monitors share the same area, clicking again selects the next monitor
+        let previous = monitors
+            .iter()
+            .position(|monitor| monitor.drag_information.clicked);
+        iter = match candidates.iter().position(|index| Some(*index) == previous) {
+            Some(position) => candidates[(position + 1) % candidates.len()],
+            None => *candidates.last().unwrap(),
+        };
+        // mirrored monitors follow their source, hence the source is dragged instead
+        let dragged = match mirror_source(&monitors[iter], &monitors) {
+            Some(source) => monitors
+                .iter()
+                .position(|monitor| monitor.name == source.name)
+                .unwrap(),
+            None => iter,
+        };
+        if let Some(previous) = previous {
+            monitors[previous].drag_information.clicked = false;
+        }
+        monitors[iter].drag_information.clicked = true;
+        let monitor = monitors.get_mut(dragged).unwrap();
+        if monitor.enabled {
+            monitor.drag_information.drag_active = true;
+        }
+        monitor.drag_information.origin_x = monitor.offset.0;
+        monitor.drag_information.origin_y = monitor.offset.1;
+        if let Some(child) = settings_box_ref.first_child() {
+            settings_box_ref.remove(&child);
         }
     }
     settings_box_ref.append(&get_monitor_settings_group(
         start_ref.clone(),
-        iter as usize,
+        iter,
         drawing_area,
     ));
 }
@@ -846,7 +994,7 @@ pub fn monitor_drag_update(
     for monitor in update_ref.borrow_mut().iter_mut() {
         let x = x as i32;
         let y = y as i32;
-        if is_gnome() && !monitor.enabled {
+        if CAPABILITIES.compact_layout && !monitor.enabled {
             continue;
         }
         if monitor.drag_information.drag_active {
@@ -903,9 +1051,11 @@ pub fn monitor_drag_end(
         return;
     }
     let iter = iter as usize;
+    let (mirrored, _, _) = get_mirror_groups(&monitor_data.borrow());
     for (i, monitor) in monitor_data.borrow_mut().iter_mut().enumerate() {
-        if i == iter {
+        if i == iter || mirrored[i] {
             // continue if the same monitor is used -> no point in calculating
+            // mirrored monitors are moved together with their source
             continue;
         }
         // define other monitor endpoints
@@ -1108,7 +1258,7 @@ pub fn monitor_drag_end(
     monitor.drag_information.drag_x = 0;
     monitor.drag_information.drag_y = 0;
 
-    if is_gnome() {
+    if CAPABILITIES.compact_layout {
         let mut left_side = i32::MAX;
         let mut top_side = i32::MAX;
         for monitor in monitors.iter_mut() {
@@ -1218,13 +1368,6 @@ pub fn scaling_update(
         .expect("Could not activate reset action");
 }
 
-// fractional scaling can only be done when the scale divides the resolution to a whole
-// number.
-// Example: 1080 / 1.5 -> 720. E.g. the factor 1.5 will also resolve to a whole number.
-fn is_nonfunctional_scale(width: i32, height: i32, scale: f64) -> bool {
-    width as f64 % scale != 0.0 && height as f64 % scale != 0.0 && scale != 1.0
-}
-
 pub fn search_nearest_scale(
     amount: usize,
     search_scale: &mut f64,
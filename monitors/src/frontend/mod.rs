@@ -16,7 +16,7 @@ use gtk::{
 };
 use re_set_lib::utils::{gtk::utils::create_title, plugin::SidebarInfo};
 
-use crate::utils::{get_environment, get_monitor_data, is_gnome};
+use crate::utils::{get_monitor_data, CAPABILITIES};
 
 use self::{
     general::add_save_button,
@@ -24,11 +24,17 @@ use self::{
         apply_monitor_clicked, drawing_callback, get_monitor_settings_group, monitor_drag_end,
         monitor_drag_start, monitor_drag_update, reset_monitor_clicked,
     },
+    night_light::get_night_light_group,
+    profiles::add_profile_picker,
+    watcher::watch_monitor_changes,
 };
 
 pub mod general;
 pub mod gnome;
 pub mod handlers;
+pub mod night_light;
+pub mod profiles;
+pub mod watcher;
 
 const NAME: &str = "Monitors";
 
@@ -95,7 +101,9 @@ pub extern "C" fn frontend_data() -> (SidebarInfo, Vec<gtk::Box>) {
     let settings_box_ref_apply = settings_box.clone();
     let settings_box_ref_save = settings_box.clone();
     let settings_box_ref_reset = settings_box.clone();
+    let settings_box_ref_profile = settings_box.clone();
     let settings_box_ref_action = settings_box.clone();
+    let settings_box_ref_watch = settings_box.clone();
 
     // NOTE: intentional use of deprecated logic as there is no currently available alternative
     // Gnome also uses the same functionality to get the same color for drawing the monitors
@@ -129,8 +137,10 @@ pub extern "C" fn frontend_data() -> (SidebarInfo, Vec<gtk::Box>) {
     let drawing_ref_apply = drawing_area.clone();
     let drawing_ref_save = drawing_area.clone();
     let drawing_ref_reset = drawing_area.clone();
+    let drawing_ref_profile = drawing_area.clone();
     let drawing_ref_end = drawing_area.clone();
     let drawing_ref_action = drawing_area.clone();
+    let drawing_ref_watch = drawing_area.clone();
 
     let data = get_monitor_data();
     let monitor_data = Rc::new(RefCell::new(data.clone()));
@@ -141,6 +151,8 @@ pub extern "C" fn frontend_data() -> (SidebarInfo, Vec<gtk::Box>) {
     let fallback_save_ref = fall_back_monitor_data.clone();
     let fallback_apply_ref = fall_back_monitor_data.clone();
     let fallback_action_ref = fall_back_monitor_data.clone();
+    let fallback_profile_ref = fall_back_monitor_data.clone();
+    let fallback_watch_ref = fall_back_monitor_data.clone();
     let start_ref = monitor_data.clone();
     let clicked_ref = monitor_data.clone();
     let update_ref = monitor_data.clone();
@@ -167,6 +179,14 @@ pub extern "C" fn frontend_data() -> (SidebarInfo, Vec<gtk::Box>) {
         config_buttons.clone(),
     );
 
+    add_profile_picker(
+        monitor_data.clone(),
+        fallback_profile_ref,
+        settings_box_ref_profile,
+        drawing_ref_profile,
+        config_buttons.clone(),
+    );
+
     let reset_ref = monitor_data.clone();
     reset.connect_clicked(move |button| {
         reset_monitor_clicked(
@@ -179,9 +199,8 @@ pub extern "C" fn frontend_data() -> (SidebarInfo, Vec<gtk::Box>) {
 
     {
         let mut monitors_borrow = monitor_data.borrow_mut();
-        let monitor = monitors_borrow.get_mut(0);
-        if monitor.is_some() {
-            monitor.unwrap().drag_information.clicked = true;
+        if let Some(monitor) = monitors_borrow.get_mut(0) {
+            monitor.drag_information.clicked = true;
         }
     }
 
@@ -191,6 +210,13 @@ pub extern "C" fn frontend_data() -> (SidebarInfo, Vec<gtk::Box>) {
         &drawing_area,
     ));
 
+    watch_monitor_changes(
+        monitor_data.clone(),
+        fallback_watch_ref,
+        settings_box_ref_watch,
+        drawing_ref_watch,
+    );
+
     drawing_callback(
         &drawing_area,
         border_color,
@@ -212,9 +238,7 @@ pub extern "C" fn frontend_data() -> (SidebarInfo, Vec<gtk::Box>) {
         }
     });
 
-    let env = get_environment();
-    let env = env.as_str();
-    let disallow_gaps = is_gnome() || env == "KDE";
+    let disallow_gaps = !CAPABILITIES.gaps;
     let gesture = GestureDrag::builder().build();
     let drawing_ref_drag_start = drawing_area.clone();
     gesture.connect_drag_begin(move |_drag, x, y| {
@@ -272,6 +296,7 @@ pub extern "C" fn frontend_data() -> (SidebarInfo, Vec<gtk::Box>) {
     main_box.append(&top_row);
     main_box.append(&drawing_frame);
     main_box.append(&settings_box);
+    main_box.append(&get_night_light_group());
 
     drawing_area.queue_draw();
 
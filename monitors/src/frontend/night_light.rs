@@ -0,0 +1,210 @@
+// This file handles the night light settings, changes are sent to the daemon right away
+use std::{cell::RefCell, rc::Rc, thread, time::Duration};
+
+use adw::{
+    prelude::{ComboRowExt, EntryRowExt, ExpanderRowExt, PreferencesGroupExt, PreferencesRowExt},
+    ComboRow, EntryRow, ExpanderRow, PreferencesGroup, SpinRow,
+};
+use dbus::{blocking::Connection, Error};
+use gtk::{
+    prelude::{EditableExt, WidgetExt},
+    StringList,
+};
+use re_set_lib::ERROR;
+#[cfg(debug_assertions)]
+use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
+
+use crate::{
+    r#const::{BASE, DBUS_PATH, INTERFACE},
+    utils::{NightLight, MIN_TEMPERATURE, NEUTRAL_TEMPERATURE, NIGHT_LIGHT_FIXED, NIGHT_LIGHT_SUN},
+};
+
+/// Parses a time in the form of hh:mm into minutes after midnight.
+pub fn parse_time(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    Some(hours * 60 + minutes)
+}
+
+pub fn format_time(minutes: u32) -> String {
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+pub fn get_night_light_group() -> PreferencesGroup {
+    let group = PreferencesGroup::new();
+    let night_light = get_night_light();
+    if night_light.is_none() {
+        group.set_visible(false);
+        return group;
+    }
+    let night_light = Rc::new(RefCell::new(night_light.unwrap()));
+    let settings = night_light.borrow().clone();
+
+    let expander = ExpanderRow::new();
+    expander.set_title("Night Light");
+    expander.set_subtitle("Lowers the color temperature of all monitors at night");
+    expander.set_show_enable_switch(true);
+    expander.set_enable_expansion(settings.enabled);
+    let enabled_ref = night_light.clone();
+    expander.connect_enable_expansion_notify(move |state| {
+        enabled_ref.borrow_mut().enabled = state.enables_expansion();
+        send_night_light(&enabled_ref);
+    });
+
+    let temperature_adjustment = gtk::Adjustment::new(
+        settings.temperature as f64,
+        MIN_TEMPERATURE as f64,
+        NEUTRAL_TEMPERATURE as f64,
+        100.0,
+        0.0,
+        0.0,
+    );
+    let temperature = SpinRow::new(Some(&temperature_adjustment), 100.0, 0);
+    temperature.set_title("Temperature");
+    temperature.set_tooltip_markup(Some(
+        "Color temperature in kelvin, lower values result in warmer colors.",
+    ));
+    let temperature_ref = night_light.clone();
+    temperature.connect_value_notify(move |state| {
+        temperature_ref.borrow_mut().temperature = state.value() as u32;
+        send_night_light(&temperature_ref);
+    });
+    expander.add_row(&temperature);
+
+    let schedules = StringList::new(&["Fixed Times", "Sunset to Sunrise"]);
+    let schedule = ComboRow::new();
+    schedule.set_title("Schedule");
+    schedule.set_model(Some(&schedules));
+    schedule.set_selected(if settings.schedule == NIGHT_LIGHT_SUN {
+        1
+    } else {
+        0
+    });
+    expander.add_row(&schedule);
+
+    let start = time_row(
+        "Start",
+        settings.start,
+        night_light.clone(),
+        |night_light, time| night_light.start = time,
+    );
+    let end = time_row(
+        "End",
+        settings.end,
+        night_light.clone(),
+        |night_light, time| night_light.end = time,
+    );
+    expander.add_row(&start);
+    expander.add_row(&end);
+
+    let latitude = coordinate_row(
+        "Latitude",
+        settings.latitude,
+        90.0,
+        night_light.clone(),
+        |night_light, value| night_light.latitude = value,
+    );
+    let longitude = coordinate_row(
+        "Longitude",
+        settings.longitude,
+        180.0,
+        night_light.clone(),
+        |night_light, value| night_light.longitude = value,
+    );
+    expander.add_row(&latitude);
+    expander.add_row(&longitude);
+
+    let uses_sun = settings.schedule == NIGHT_LIGHT_SUN;
+    start.set_visible(!uses_sun);
+    end.set_visible(!uses_sun);
+    latitude.set_visible(uses_sun);
+    longitude.set_visible(uses_sun);
+    let schedule_ref = night_light.clone();
+    schedule.connect_selected_notify(move |state| {
+        let uses_sun = state.selected() == 1;
+        start.set_visible(!uses_sun);
+        end.set_visible(!uses_sun);
+        latitude.set_visible(uses_sun);
+        longitude.set_visible(uses_sun);
+        schedule_ref.borrow_mut().schedule = if uses_sun {
+            NIGHT_LIGHT_SUN.into()
+        } else {
+            NIGHT_LIGHT_FIXED.into()
+        };
+        send_night_light(&schedule_ref);
+    });
+
+    group.add(&expander);
+    group
+}
+
+// invalid times are marked and not sent
+fn time_row(
+    title: &str,
+    minutes: u32,
+    night_light: Rc<RefCell<NightLight>>,
+    update: fn(&mut NightLight, u32),
+) -> EntryRow {
+    let row = EntryRow::new();
+    row.set_title(title);
+    row.set_text(&format_time(minutes));
+    row.set_show_apply_button(true);
+    row.connect_apply(move |state| {
+        let time = parse_time(&state.text());
+        if time.is_none() {
+            state.add_css_class("error");
+            return;
+        }
+        state.remove_css_class("error");
+        update(&mut night_light.borrow_mut(), time.unwrap());
+        send_night_light(&night_light);
+    });
+    row
+}
+
+fn coordinate_row(
+    title: &str,
+    value: f64,
+    limit: f64,
+    night_light: Rc<RefCell<NightLight>>,
+    update: fn(&mut NightLight, f64),
+) -> SpinRow {
+    let adjustment = gtk::Adjustment::new(value, -limit, limit, 0.1, 0.0, 0.0);
+    let row = SpinRow::new(Some(&adjustment), 0.1, 4);
+    row.set_title(title);
+    row.connect_value_notify(move |state| {
+        update(&mut night_light.borrow_mut(), state.value());
+        send_night_light(&night_light);
+    });
+    row
+}
+
+fn send_night_light(night_light: &Rc<RefCell<NightLight>>) {
+    let night_light = night_light.borrow().clone();
+    thread::spawn(move || set_night_light(night_light));
+}
+
+fn get_night_light() -> Option<NightLight> {
+    let conn = Connection::new_session().unwrap();
+    let proxy = conn.with_proxy(BASE, DBUS_PATH, Duration::from_millis(1000));
+    let res: Result<(NightLight,), Error> = proxy.method_call(INTERFACE, "GetNightLight", ());
+    if let Err(_error) = res {
+        ERROR!(
+            format!("Could not fetch night light settings {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return None;
+    }
+    Some(res.unwrap().0)
+}
+
+fn set_night_light(night_light: NightLight) -> bool {
+    let conn = Connection::new_session().unwrap();
+    let proxy = conn.with_proxy(BASE, DBUS_PATH, Duration::from_millis(1000));
+    let res: Result<(bool,), Error> = proxy.method_call(INTERFACE, "SetNightLight", (night_light,));
+    res.map(|val| val.0).unwrap_or(false)
+}
@@ -0,0 +1,471 @@
+use std::{cell::RefCell, fs, rc::Rc, time::Duration};
+
+use adw::prelude::*;
+use dbus::{blocking::Connection, Error};
+use glib::object::CastNone;
+use gtk::{gio, Align, DrawingArea, Orientation};
+use re_set_lib::ERROR;
+#[cfg(debug_assertions)]
+use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
+
+use crate::{
+    r#const::{BASE, DBUS_PATH, INTERFACE},
+    utils::{get_monitor_data, Monitor},
+};
+
+use super::handlers::{apply_monitor_clicked, get_monitor_settings_group, show_confirm_dialog};
+
+#[derive(Clone)]
+struct ProfileContext {
+    monitors: Rc<RefCell<Vec<Monitor>>>,
+    fallback: Rc<RefCell<Vec<Monitor>>>,
+    settings_box: gtk::Box,
+    drawing_area: DrawingArea,
+    list: gtk::ListBox,
+    kanshi_list: gtk::ListBox,
+    kanshi_label: gtk::Label,
+    popover: gtk::Popover,
+}
+
+pub fn add_profile_picker(
+    monitor_ref: Rc<RefCell<Vec<Monitor>>>,
+    fallback_ref: Rc<RefCell<Vec<Monitor>>>,
+    settings_box: gtk::Box,
+    drawing_area: DrawingArea,
+    apply_row: gtk::Box,
+) -> gtk::MenuButton {
+    let popover = gtk::Popover::new();
+    let content = gtk::Box::new(Orientation::Vertical, 5);
+
+    let list = gtk::ListBox::builder()
+        .selection_mode(gtk::SelectionMode::None)
+        .css_classes(vec!["boxed-list"])
+        .build();
+    list.set_placeholder(Some(&gtk::Label::new(Some("No saved profiles"))));
+    content.append(&list);
+
+    let save_row = gtk::Box::new(Orientation::Horizontal, 5);
+    let entry = gtk::Entry::builder()
+        .placeholder_text("Profile name")
+        .hexpand(true)
+        .build();
+    let save = gtk::Button::builder().label("Save").build();
+    save.set_tooltip_markup(Some(
+        "Stores the current configuration under this name, existing profiles are overwritten",
+    ));
+    save_row.append(&entry);
+    save_row.append(&save);
+    content.append(&save_row);
+
+    // layout files can be shared between machines, see LayoutDocument
+    let file_row = gtk::Box::new(Orientation::Horizontal, 5);
+    file_row.set_homogeneous(true);
+    let import = gtk::Button::builder().label("Import").build();
+    import.set_tooltip_markup(Some(
+        "Shows the layout of a file, the layout is only applied once it is confirmed",
+    ));
+    let export = gtk::Button::builder().label("Export").build();
+    export.set_tooltip_markup(Some("Stores the active configuration in a file"));
+    file_row.append(&import);
+    file_row.append(&export);
+    content.append(&file_row);
+
+    // existing kanshi profiles can be previewed and applied, they are never modified here
+    let kanshi_label = gtk::Label::builder()
+        .label("kanshi")
+        .halign(Align::Start)
+        .css_classes(vec!["heading"])
+        .visible(false)
+        .build();
+    let kanshi_list = gtk::ListBox::builder()
+        .selection_mode(gtk::SelectionMode::None)
+        .css_classes(vec!["boxed-list"])
+        .visible(false)
+        .build();
+    content.append(&kanshi_label);
+    content.append(&kanshi_list);
+    popover.set_child(Some(&content));
+
+    let context = ProfileContext {
+        monitors: monitor_ref,
+        fallback: fallback_ref,
+        settings_box,
+        drawing_area,
+        list,
+        kanshi_list,
+        kanshi_label,
+        popover: popover.clone(),
+    };
+
+    let save_context = context.clone();
+    save.connect_clicked(move |_| {
+        let name = entry.text().to_string();
+        if name.trim().is_empty() {
+            return;
+        }
+        let monitors = save_context.monitors.borrow().clone();
+        if !save_monitor_profile(&name, monitors) {
+            save_context
+                .settings_box
+                .activate_action(
+                    "win.banner",
+                    Some(&glib::Variant::from("Could not save monitor profile.")),
+                )
+                .expect("Could not show banner");
+        }
+        entry.set_text("");
+        reload_profiles(&save_context);
+    });
+
+    let import_context = context.clone();
+    import.connect_clicked(move |_| {
+        import_context.popover.popdown();
+        import_layout_clicked(&import_context);
+    });
+
+    let export_context = context.clone();
+    export.connect_clicked(move |_| {
+        export_context.popover.popdown();
+        export_layout_clicked(&export_context);
+    });
+
+    let show_context = context.clone();
+    popover.connect_show(move |_| {
+        reload_profiles(&show_context);
+    });
+
+    let button = gtk::MenuButton::builder()
+        .label("Profiles")
+        .hexpand_set(false)
+        .halign(Align::End)
+        .popover(&popover)
+        .build();
+    button.set_tooltip_markup(Some("Save, apply or delete named monitor layouts"));
+    apply_row.append(&button);
+    button
+}
+
+fn reload_profiles(context: &ProfileContext) {
+    while let Some(child) = context.list.first_child() {
+        context.list.remove(&child);
+    }
+    for name in get_profile_names() {
+        let row = adw::ActionRow::builder().title(&name).build();
+
+        let apply = gtk::Button::builder()
+            .icon_name("object-select-symbolic")
+            .valign(Align::Center)
+            .css_classes(vec!["flat"])
+            .build();
+        apply.set_tooltip_markup(Some("Apply profile"));
+        let apply_context = context.clone();
+        let apply_name = name.clone();
+        apply.connect_clicked(move |_| {
+            apply_context.popover.popdown();
+            apply_profile_clicked(&apply_name, &apply_context);
+        });
+
+        let delete = gtk::Button::builder()
+            .icon_name("user-trash-symbolic")
+            .valign(Align::Center)
+            .css_classes(vec!["flat"])
+            .build();
+        delete.set_tooltip_markup(Some("Delete profile"));
+        let delete_context = context.clone();
+        delete.connect_clicked(move |_| {
+            if !delete_monitor_profile(&name) {
+                show_banner(&delete_context, "Could not delete monitor profile.");
+            }
+            reload_profiles(&delete_context);
+        });
+
+        row.add_suffix(&apply);
+        row.add_suffix(&delete);
+        context.list.append(&row);
+    }
+    reload_kanshi_profiles(context);
+}
+
+fn reload_kanshi_profiles(context: &ProfileContext) {
+    while let Some(child) = context.kanshi_list.first_child() {
+        context.kanshi_list.remove(&child);
+    }
+    let profiles = get_kanshi_profiles();
+    context.kanshi_label.set_visible(!profiles.is_empty());
+    context.kanshi_list.set_visible(!profiles.is_empty());
+    for (name, monitors) in profiles {
+        let row = adw::ActionRow::builder().title(&name).build();
+
+        let preview = gtk::Button::builder()
+            .icon_name("view-reveal-symbolic")
+            .valign(Align::Center)
+            .css_classes(vec!["flat"])
+            .build();
+        preview.set_tooltip_markup(Some("Show profile without applying it"));
+        let preview_context = context.clone();
+        let preview_monitors = monitors.clone();
+        preview.connect_clicked(move |_| {
+            preview_context.popover.popdown();
+            show_monitors(&preview_context, preview_monitors.clone());
+            preview_context
+                .drawing_area
+                .activate_action(
+                    "monitor.reset_monitor_buttons",
+                    Some(&glib::Variant::from(true)),
+                )
+                .expect("Could not execute reset action");
+        });
+
+        let apply = gtk::Button::builder()
+            .icon_name("object-select-symbolic")
+            .valign(Align::Center)
+            .css_classes(vec!["flat"])
+            .build();
+        apply.set_tooltip_markup(Some("Apply profile"));
+        let apply_context = context.clone();
+        apply.connect_clicked(move |_| {
+            apply_context.popover.popdown();
+            show_monitors(&apply_context, monitors.clone());
+            apply_monitor_clicked(
+                apply_context.monitors.clone(),
+                apply_context.fallback.clone(),
+                &apply_context.settings_box,
+                &apply_context.drawing_area,
+                false,
+                false,
+            );
+        });
+
+        row.add_suffix(&preview);
+        row.add_suffix(&apply);
+        context.kanshi_list.append(&row);
+    }
+}
+
+// replaces the shown monitors while keeping the selected monitor if possible
+fn show_monitors(context: &ProfileContext, monitors: Vec<Monitor>) {
+    if let Some(child) = context.settings_box.first_child() {
+        context.settings_box.remove(&child);
+    }
+    let mut index = 0;
+    for (i, monitor) in context.monitors.borrow().iter().enumerate() {
+        if monitor.drag_information.clicked {
+            index = i;
+        }
+    }
+    context.monitors.replace(monitors);
+    let index = index.min(context.monitors.borrow().len().saturating_sub(1));
+    // the settings and the canvas show the clicked monitor
+    for (i, monitor) in context.monitors.borrow_mut().iter_mut().enumerate() {
+        monitor.drag_information.clicked = i == index;
+    }
+    context.settings_box.append(&get_monitor_settings_group(
+        context.monitors.clone(),
+        index,
+        &context.drawing_area,
+    ));
+    context.drawing_area.queue_draw();
+}
+
+fn apply_profile_clicked(name: &str, context: &ProfileContext) {
+    let timeout = apply_monitor_profile(name);
+    if timeout.is_none() {
+        context
+            .settings_box
+            .activate_action(
+                "win.banner",
+                Some(&glib::Variant::from("Could not apply monitor profile.")),
+            )
+            .expect("Could not show banner");
+        return;
+    }
+    show_monitors(context, get_monitor_data());
+    context
+        .drawing_area
+        .activate_action(
+            "monitor.reset_monitor_buttons",
+            Some(&glib::Variant::from(false)),
+        )
+        .expect("Could not execute reset action");
+    // the previous configuration is kept as fallback until the profile is confirmed
+    show_confirm_dialog(
+        context.monitors.clone(),
+        context.fallback.clone(),
+        &context.settings_box,
+        timeout.unwrap(),
+        false,
+    );
+}
+
+fn layout_file_dialog(title: &str) -> gtk::FileDialog {
+    let filter = gtk::FileFilter::new();
+    filter.set_name(Some("Monitor layouts"));
+    filter.add_pattern("*.json");
+    gtk::FileDialog::builder()
+        .title(title)
+        .modal(true)
+        .initial_name("monitor_layout.json")
+        .default_filter(&filter)
+        .build()
+}
+
+fn show_banner(context: &ProfileContext, message: &str) {
+    context
+        .settings_box
+        .activate_action("win.banner", Some(&glib::Variant::from(message)))
+        .expect("Could not show banner");
+}
+
+fn import_layout_clicked(context: &ProfileContext) {
+    let window = context.settings_box.root().and_downcast::<gtk::Window>();
+    let import_context = context.clone();
+    layout_file_dialog("Import Layout").open(
+        window.as_ref(),
+        gio::Cancellable::NONE,
+        move |file| {
+            // dismissing the dialog is reported as error as well
+            let path = file.ok().and_then(|file| file.path());
+            if path.is_none() {
+                return;
+            }
+            let path = path.unwrap();
+            let layout = fs::read_to_string(&path);
+            if let Err(_error) = layout {
+                ERROR!(
+                    format!("Could not read layout {}", _error),
+                    ErrorLevel::Recoverable
+                );
+                show_banner(&import_context, "Could not read the layout file.");
+                return;
+            }
+            match import_layout(layout.unwrap()) {
+                Ok(monitors) => {
+                    show_monitors(&import_context, monitors);
+                    import_context
+                        .drawing_area
+                        .activate_action(
+                            "monitor.reset_monitor_buttons",
+                            Some(&glib::Variant::from(true)),
+                        )
+                        .expect("Could not execute reset action");
+                }
+                Err(error) => show_banner(
+                    &import_context,
+                    error.message().unwrap_or("Could not import the layout."),
+                ),
+            }
+        },
+    );
+}
+
+fn export_layout_clicked(context: &ProfileContext) {
+    let window = context.settings_box.root().and_downcast::<gtk::Window>();
+    let export_context = context.clone();
+    layout_file_dialog("Export Layout").save(
+        window.as_ref(),
+        gio::Cancellable::NONE,
+        move |file| {
+            let path = file.ok().and_then(|file| file.path());
+            if path.is_none() {
+                return;
+            }
+            let path = path.unwrap();
+            let layout = export_layout();
+            if let Err(error) = layout {
+                show_banner(
+                    &export_context,
+                    error.message().unwrap_or("Could not export the layout."),
+                );
+                return;
+            }
+            if let Err(_error) = fs::write(&path, layout.unwrap()) {
+                ERROR!(
+                    format!("Could not write layout {}", _error),
+                    ErrorLevel::Recoverable
+                );
+                show_banner(&export_context, "Could not write the layout file.");
+            }
+        },
+    );
+}
+
+// name of the profile and the monitors with the profile applied
+type KanshiProfiles = Vec<(String, Vec<Monitor>)>;
+
+fn get_kanshi_profiles() -> KanshiProfiles {
+    let conn = Connection::new_session().unwrap();
+    let proxy = conn.with_proxy(BASE, DBUS_PATH, Duration::from_millis(1000));
+    let res: Result<(KanshiProfiles,), Error> =
+        proxy.method_call(INTERFACE, "GetKanshiProfiles", ());
+    if let Err(_error) = res {
+        ERROR!(
+            format!("Could not fetch kanshi profiles {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return Vec::new();
+    }
+    res.unwrap().0
+}
+
+fn get_profile_names() -> Vec<String> {
+    let conn = Connection::new_session().unwrap();
+    let proxy = conn.with_proxy(BASE, DBUS_PATH, Duration::from_millis(1000));
+    let res: Result<(Vec<String>,), Error> = proxy.method_call(INTERFACE, "ListProfiles", ());
+    if let Err(_error) = res {
+        ERROR!(
+            format!("Could not fetch monitor profiles {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return Vec::new();
+    }
+    res.unwrap().0
+}
+
+fn save_monitor_profile(name: &str, monitors: Vec<Monitor>) -> bool {
+    let conn = Connection::new_session().unwrap();
+    let proxy = conn.with_proxy(BASE, DBUS_PATH, Duration::from_millis(1000));
+    let res: Result<(bool,), Error> = proxy.method_call(INTERFACE, "SaveProfile", (name, monitors));
+    res.map(|val| val.0).unwrap_or(false)
+}
+
+// returns the timeout after which the profile is reverted unless it is confirmed
+fn apply_monitor_profile(name: &str) -> Option<u32> {
+    let conn = Connection::new_session().unwrap();
+    let proxy = conn.with_proxy(BASE, DBUS_PATH, Duration::from_millis(5000));
+    let res: Result<(bool, u32), Error> =
+        proxy.method_call(INTERFACE, "ApplyProfile", (name, 0u32));
+    if let Err(_error) = res {
+        ERROR!(
+            format!("Could not apply monitor profile {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return None;
+    }
+    let (found, timeout) = res.unwrap();
+    if !found {
+        return None;
+    }
+    Some(timeout)
+}
+
+fn delete_monitor_profile(name: &str) -> bool {
+    let conn = Connection::new_session().unwrap();
+    let proxy = conn.with_proxy(BASE, DBUS_PATH, Duration::from_millis(1000));
+    let res: Result<(bool,), Error> = proxy.method_call(INTERFACE, "DeleteProfile", (name,));
+    res.map(|val| val.0).unwrap_or(false)
+}
+
+fn import_layout(layout: String) -> Result<Vec<Monitor>, Error> {
+    let conn = Connection::new_session().unwrap();
+    let proxy = conn.with_proxy(BASE, DBUS_PATH, Duration::from_millis(1000));
+    let res: Result<(Vec<Monitor>,), Error> =
+        proxy.method_call(INTERFACE, "ImportLayout", (layout,));
+    res.map(|val| val.0)
+}
+
+fn export_layout() -> Result<String, Error> {
+    let conn = Connection::new_session().unwrap();
+    let proxy = conn.with_proxy(BASE, DBUS_PATH, Duration::from_millis(1000));
+    let res: Result<(String,), Error> = proxy.method_call(INTERFACE, "ExportLayout", ());
+    res.map(|val| val.0)
+}
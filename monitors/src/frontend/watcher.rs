@@ -0,0 +1,159 @@
+// This file handles monitor changes reported by the backend while ReSet is open
+use std::{cell::RefCell, rc::Rc};
+
+use gtk::{
+    gio,
+    prelude::{BoxExt, WidgetExt},
+    DrawingArea,
+};
+use re_set_lib::ERROR;
+#[cfg(debug_assertions)]
+use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
+
+use crate::{
+    backend::{hotplug::monitor_identity, profiles::ProfileMonitor},
+    r#const::{BASE, DBUS_PATH, INTERFACE},
+    utils::{get_monitor_data, Monitor},
+};
+
+use super::handlers::get_monitor_settings_group;
+
+fn same_monitor(first: &Monitor, second: &Monitor) -> bool {
+    first.name == second.name && monitor_identity(first) == monitor_identity(second)
+}
+
+// only values which can be applied are compared, drag information is irrelevant here
+fn same_configuration(first: &Monitor, second: &Monitor) -> bool {
+    ProfileMonitor::from_regular_monitor(first) == ProfileMonitor::from_regular_monitor(second)
+}
+
+/// Merges the monitors reported by the backend with the monitors shown in the UI.
+/// A monitor that is still connected keeps its unapplied edits, these are values which differ
+/// from both the previously reported and the newly reported configuration.
+/// Returns the merged monitors and whether any unapplied edits were kept.
+pub fn merge_monitor_changes(
+    edited: &[Monitor],
+    previous: &[Monitor],
+    current: &[Monitor],
+) -> (Vec<Monitor>, bool) {
+    let mut kept_edits = false;
+    let mut merged = Vec::with_capacity(current.len());
+    for monitor in current {
+        let edited_monitor = edited.iter().find(|val| same_monitor(val, monitor));
+        if edited_monitor.is_none() {
+            merged.push(monitor.clone());
+            continue;
+        }
+        let edited_monitor = edited_monitor.unwrap();
+        let is_edit = !same_configuration(edited_monitor, monitor)
+            && !previous
+                .iter()
+                .any(|val| same_monitor(val, monitor) && same_configuration(edited_monitor, val));
+        if is_edit {
+            // the modes and features might have changed as well, e.g. with a different cable
+            let mut kept = edited_monitor.clone();
+            kept.id = monitor.id;
+            kept.available_modes = monitor.available_modes.clone();
+            kept.uses_mode_id = monitor.uses_mode_id;
+            kept.features = monitor.features;
+            merged.push(kept);
+            kept_edits = true;
+        } else {
+            let mut updated = monitor.clone();
+            updated.drag_information.clicked = edited_monitor.drag_information.clicked;
+            merged.push(updated);
+        }
+    }
+    (merged, kept_edits)
+}
+
+pub fn watch_monitor_changes(
+    monitor_ref: Rc<RefCell<Vec<Monitor>>>,
+    fallback_ref: Rc<RefCell<Vec<Monitor>>>,
+    settings_box: gtk::Box,
+    drawing_area: DrawingArea,
+) {
+    let conn = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE);
+    if let Err(_error) = conn {
+        ERROR!(
+            format!("Could not watch monitor changes {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return;
+    }
+    let previous_ref = Rc::new(RefCell::new(monitor_ref.borrow().clone()));
+    conn.unwrap().signal_subscribe(
+        Some(BASE),
+        Some(INTERFACE),
+        Some("MonitorsChanged"),
+        Some(DBUS_PATH),
+        None,
+        gio::DBusSignalFlags::NONE,
+        move |_, _, _, _, _, _| {
+            // the signal only notifies about the change, fetching the monitors again avoids
+            // converting the variant by hand
+            let current = get_monitor_data();
+            if current.is_empty() {
+                return;
+            }
+            monitors_changed(
+                current,
+                &monitor_ref,
+                &fallback_ref,
+                &previous_ref,
+                &settings_box,
+                &drawing_area,
+            );
+        },
+    );
+}
+
+fn monitors_changed(
+    current: Vec<Monitor>,
+    monitor_ref: &Rc<RefCell<Vec<Monitor>>>,
+    fallback_ref: &Rc<RefCell<Vec<Monitor>>>,
+    previous_ref: &Rc<RefCell<Vec<Monitor>>>,
+    settings_box: &gtk::Box,
+    drawing_area: &DrawingArea,
+) {
+    let (merged, kept_edits) =
+        merge_monitor_changes(&monitor_ref.borrow(), &previous_ref.borrow(), &current);
+
+    // the fallback is kept for monitors that are still connected, it might be needed to revert
+    // a configuration that is not confirmed yet
+    let fallback: Vec<Monitor> = current
+        .iter()
+        .map(|monitor| {
+            fallback_ref
+                .borrow()
+                .iter()
+                .find(|val| same_monitor(val, monitor))
+                .cloned()
+                .unwrap_or_else(|| monitor.clone())
+        })
+        .collect();
+    fallback_ref.replace(fallback);
+    previous_ref.replace(current);
+
+    let index = merged
+        .iter()
+        .position(|monitor| monitor.drag_information.clicked)
+        .unwrap_or(0);
+    monitor_ref.replace(merged);
+
+    if let Some(child) = settings_box.first_child() {
+        settings_box.remove(&child);
+    }
+    settings_box.append(&get_monitor_settings_group(
+        monitor_ref.clone(),
+        index,
+        drawing_area,
+    ));
+    drawing_area.queue_draw();
+    drawing_area
+        .activate_action(
+            "monitor.reset_monitor_buttons",
+            Some(&glib::Variant::from(kept_edits)),
+        )
+        .expect("Could not execute reset action");
+}
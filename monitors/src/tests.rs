@@ -1,23 +1,95 @@
 use std::time::Duration;
 #[cfg(test)]
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::RefCell,
+    io::{Read, Write},
+    os::unix::net::UnixListener,
+    path::PathBuf,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
 
 use dbus::{blocking::Connection, Error};
 use re_set_lib::utils::plugin::PluginTestError;
+#[cfg(test)]
+use re_set_lib::utils::plugin_setup::CrossWrapper;
+#[cfg(test)]
+use reset_common::command::{CommandRunner, FixtureCommandRunner, HostCommandRunner};
 
 use crate::utils::{is_gnome, Monitor};
 #[cfg(test)]
 use crate::{
     backend::{
-        gnome::{gnome_features, GnomeLogicalMonitor, GnomeMode, GnomeMonitor, GnomeMonitorConfig},
-        hyprland::{HyprMonitor, HYPRFEATURES},
-        kde::{KDEMode, KDEMonitor, KDE_FEATURES},
+        brightness::{
+            ddc_get_vcp_request, ddc_parse_vcp_reply, ddc_set_vcp_request, from_percent,
+            gamma_ramp, temperature_to_rgb, to_percent,
+        },
+        edid::{apply_edids, parse_edid, read_edids},
+        general::MonitorBackend,
+        gnome::GnomeBackend,
+        gnome::{
+            g_apply_monitor_config_with, g_get_monitor_information_with, gnome_features,
+            GnomeLogicalMonitor, GnomeMode, GnomeMonitor, GnomeMonitorConfig,
+        },
+        hotplug::{fingerprint, store_layout_in},
+        hyprland::{
+            hy_get_monitor_information_with, is_monitor_event,
+            monitor_to_commands as hypr_monitor_to_commands, HyprMonitor, HyprlandBackend,
+            HYPRFEATURES,
+        },
+        hyprland_ipc::{batch_errors, hypr_batch, hypr_request},
+        kanshi::{
+            kanshi_profile_to_monitors, merge_kanshi_config, monitor_to_kanshi_output,
+            parse_kanshi_config, read_kanshi_config,
+        },
+        kde::{
+            convert_modes_to_kscreen_string, kde_get_monitor_information_with,
+            kde_save_monitor_config_with, KDEMode, KDEMonitor, KdeBackend, KDE_FEATURES,
+        },
+        kwin::{kwin_apply_monitor_configuration, kwin_get_monitor_information},
+        layout::{export_layout, import_layout, parse_layout, LAYOUT_VERSION},
+        mirror::{apply_mirror_fallback, detect_mirrors},
+        night_light::{
+            current_temperature, gnome_night_light_settings, is_night, is_valid_night_light,
+            kde_night_light_settings, sun_times,
+        },
+        profiles::{read_profiles, write_profiles, MonitorProfile},
+        revert::{revert_timeout, start_revert_timer, RevertData},
+        setup_dbus_interface,
+        sway::{
+            command_errors, monitor_to_commands, sway_request, sway_watch_outputs, SwayBackend,
+            SwayOutput, SWAYFEATURES,
+        },
+        validation::{validate_configuration, validate_monitors},
+        wlr::{wlr_apply_monitor_configuration, wlr_get_monitor_information},
     },
     frontend::handlers::monitor_drag_end,
     frontend::handlers::search_nearest_scale,
+    frontend::night_light::{format_time, parse_time},
+    frontend::watcher::merge_monitor_changes,
     utils::AvailableMode,
-    utils::{DragInformation, Offset, Size},
+    utils::{
+        format_refresh_rate, BackendCapabilities, CustomMode, DragInformation, EdidInfo,
+        HdrSettings, MonitorData, MonitorError, MonitorFeatures, NightLight, Offset, Size,
+        INVALID_GAP, INVALID_MODE, INVALID_NO_ENABLED_MONITOR, INVALID_OVERLAP, INVALID_SCALE,
+        NIGHT_LIGHT_SUN,
+    },
+};
+#[cfg(test)]
+mod mock_mutter;
+#[cfg(test)]
+mod mock_wayland;
+#[cfg(test)]
+use mock_mutter::{
+    MockLogicalMonitor, MockMutter, MockMutterMode, MockMutterMonitor, MockMutterState, MockValue,
+    METHOD_PERSISTENT, METHOD_TEMPORARY, METHOD_VERIFY,
 };
+#[cfg(test)]
+use mock_wayland::{AppliedHead, MockCompositor, MockHead, MockMode};
 
 #[test]
 fn single_overlap() {
@@ -129,7 +201,7 @@ fn convert_kde_monitor() {
         features: KDE_FEATURES,
         ..Default::default()
     };
-    assert_eq!(monitor, kde_monitor.convert_to_regular_monitor());
+    assert_eq!(Some(monitor), kde_monitor.convert_to_regular_monitor());
 }
 
 #[test]
@@ -213,6 +285,516 @@ fn search_upper_scale() {
     // Ok
 }
 
+#[test]
+fn apply_profile_by_identity() {
+    let stored = Monitor {
+        name: "DP-1".into(),
+        make: "Dell".into(),
+        model: "U2720Q".into(),
+        serial: "ABC".into(),
+        enabled: true,
+        size: Size(1920, 1080),
+        refresh_rate: 60000,
+        scale: 1.5,
+        offset: Offset(1920, 0),
+        ..Default::default()
+    };
+    let profile = MonitorProfile::from_regular_monitors("desk", &[stored]);
+    // same monitor, now connected via a dock on a different connector
+    let current = Monitor {
+        name: "DP-5".into(),
+        make: "Dell".into(),
+        model: "U2720Q".into(),
+        serial: "ABC".into(),
+        enabled: true,
+        size: Size(3840, 2160),
+        refresh_rate: 60000,
+        scale: 1.0,
+        uses_mode_id: true,
+        available_modes: vec![
+            AvailableMode {
+                id: "0".into(),
+                size: Size(3840, 2160),
+                refresh_rates: vec![(60000, "0".into())],
+                supported_scales: vec![],
+            },
+            AvailableMode {
+                id: "1".into(),
+                size: Size(1920, 1080),
+                refresh_rates: vec![(60000, "1".into())],
+                supported_scales: vec![],
+            },
+        ],
+        ..Default::default()
+    };
+    let applied = profile.apply_to_monitors(&[current]);
+    let monitor = applied.first().unwrap();
+    assert_eq!(monitor.name, "DP-5");
+    assert_eq!(monitor.size, Size(1920, 1080));
+    assert_eq!(monitor.mode, "1");
+    assert_eq!(monitor.scale, 1.5);
+    assert_eq!(monitor.offset, Offset(1920, 0));
+}
+
+#[test]
+fn apply_profile_subpixel_and_render_time() {
+    let stored = Monitor {
+        name: "DP-1".into(),
+        subpixel: "bgr".into(),
+        max_render_time: 4,
+        ..Default::default()
+    };
+    let profile = MonitorProfile::from_regular_monitors("desk", &[stored]);
+    let current = Monitor {
+        name: "DP-1".into(),
+        subpixel: "rgb".into(),
+        features: SWAYFEATURES,
+        ..Default::default()
+    };
+    let applied = profile.apply_to_monitors(std::slice::from_ref(&current));
+    assert_eq!(applied[0].subpixel, "bgr");
+    assert_eq!(applied[0].max_render_time, 4);
+
+    // only applied when the environment supports them
+    let current = Monitor {
+        features: MonitorFeatures::default(),
+        ..current
+    };
+    let applied = profile.apply_to_monitors(std::slice::from_ref(&current));
+    assert_eq!(applied[0].subpixel, "rgb");
+    assert_eq!(applied[0].max_render_time, 0);
+}
+
+#[test]
+fn read_profiles_errors() {
+    let path = std::env::temp_dir().join(format!("reset-profiles-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(read_profiles(&path), Ok(Vec::new()));
+    let profiles = vec![MonitorProfile::from_regular_monitors(
+        "desk",
+        &create_kanshi_monitors(),
+    )];
+    write_profiles(&path, &profiles).unwrap();
+    assert_eq!(read_profiles(&path), Ok(profiles));
+
+    // a corrupt file is reported instead of being treated as empty, which would remove all
+    // profiles on the next save
+    std::fs::write(&path, "[{").unwrap();
+    let res = read_profiles(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(res, Err(MonitorError::Parse(_))));
+}
+
+#[test]
+fn store_layout_keeps_unreadable_layouts() {
+    let path = std::env::temp_dir().join(format!("reset-layouts-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let monitors = create_kanshi_monitors();
+    store_layout_in(&path, &monitors).unwrap();
+    store_layout_in(&path, &monitors[..1]).unwrap();
+    assert_eq!(read_profiles(&path).unwrap().len(), 2);
+
+    std::fs::write(&path, "[{").unwrap();
+    let res = store_layout_in(&path, &monitors);
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(res, Err(MonitorError::Parse(_))));
+    assert_eq!(content, "[{");
+}
+
+#[test]
+fn fingerprint_ignores_order() {
+    let laptop = Monitor {
+        name: "eDP-1".into(),
+        make: "BOE".into(),
+        model: "0x0BCA".into(),
+        ..Default::default()
+    };
+    let external = Monitor {
+        name: "DP-3".into(),
+        make: "Dell".into(),
+        model: "U2720Q".into(),
+        serial: "ABC".into(),
+        ..Default::default()
+    };
+    assert_eq!(
+        fingerprint(&[laptop.clone(), external.clone()]),
+        fingerprint(&[external, laptop.clone()])
+    );
+    assert_ne!(
+        fingerprint(std::slice::from_ref(&laptop)),
+        fingerprint(&[laptop.clone(), laptop])
+    );
+}
+
+#[test]
+fn hyprland_monitor_events() {
+    assert!(is_monitor_event("monitoradded>>DP-1"));
+    assert!(is_monitor_event("monitoraddedv2>>1,DP-1,Dell U2720Q"));
+    assert!(is_monitor_event("monitorremoved>>HDMI-A-1"));
+    assert!(is_monitor_event("configreloaded>>"));
+    assert!(!is_monitor_event("focusedmon>>DP-1,2"));
+    assert!(!is_monitor_event("workspace>>monitoradded"));
+}
+
+#[test]
+fn merge_keeps_unapplied_edits() {
+    let first = Monitor {
+        id: 1,
+        name: "DP-1".into(),
+        scale: 1.0,
+        ..Default::default()
+    };
+    let second = Monitor {
+        id: 2,
+        name: "DP-2".into(),
+        scale: 1.0,
+        offset: Offset(1920, 0),
+        ..Default::default()
+    };
+    let previous = vec![first.clone(), second.clone()];
+    let mut edited = previous.clone();
+    edited[0].scale = 2.0;
+
+    // second monitor was unplugged, first monitor changed its offset externally
+    let mut current = vec![first.clone()];
+    current[0].id = 5;
+    current[0].offset = Offset(100, 0);
+    let (merged, kept_edits) = merge_monitor_changes(&edited, &previous, &current);
+    assert!(kept_edits);
+    assert_eq!(merged.len(), 1);
+    assert_eq!(merged[0].id, 5);
+    assert_eq!(merged[0].scale, 2.0);
+
+    // subpixel and max render time are edits as well
+    let mut edited = previous.clone();
+    edited[0].subpixel = "bgr".into();
+    edited[0].max_render_time = 4;
+    let (merged, kept_edits) = merge_monitor_changes(&edited, &previous, &current);
+    assert!(kept_edits);
+    assert_eq!(merged[0].subpixel, "bgr");
+    assert_eq!(merged[0].max_render_time, 4);
+
+    // without edits the new configuration is used as is
+    let (merged, kept_edits) = merge_monitor_changes(&previous, &previous, &current);
+    assert!(!kept_edits);
+    assert_eq!(merged[0].offset, Offset(100, 0));
+}
+
+// stand-in for the hyprland request socket, answers a single request with the given reply
+#[cfg(test)]
+fn hyprland_socket_stand_in(
+    name: &str,
+    reply: &'static str,
+) -> (PathBuf, thread::JoinHandle<String>) {
+    let path = std::env::temp_dir().join(format!(
+        "reset-monitors-{}-{}.sock",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buffer = [0; 8192];
+        let len = stream.read(&mut buffer).unwrap();
+        stream.write_all(reply.as_bytes()).unwrap();
+        String::from_utf8_lossy(&buffer[..len]).to_string()
+    });
+    (path, handle)
+}
+
+#[test]
+fn hyprland_socket_request() {
+    let (path, handle) = hyprland_socket_stand_in("request", "[{\"id\": 0}]");
+    let reply = hypr_request(&path, "j/monitors").unwrap();
+    assert_eq!(handle.join().unwrap(), "j/monitors");
+    assert_eq!(reply, "[{\"id\": 0}]");
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn hyprland_socket_batch() {
+    let (path, handle) = hyprland_socket_stand_in("batch", "ok\n\ninvalid monitor rule\n\n");
+    let commands = vec![
+        String::from("keyword monitor DP-1,1920x1080@60,0x0,1.000000,transform,0"),
+        String::from("keyword monitor DP-2,invalid"),
+        String::from("keyword monitor HDMI-A-1,disabled"),
+    ];
+    let replies = hypr_batch(&path, &commands).unwrap();
+    assert_eq!(
+        handle.join().unwrap(),
+        format!("[[BATCH]]{}", commands.join(";"))
+    );
+    let errors = batch_errors(&commands, &replies);
+    assert_eq!(errors.len(), 2);
+    assert_eq!(
+        errors[0],
+        (commands[1].clone(), String::from("invalid monitor rule"))
+    );
+    assert_eq!(errors[1], (commands[2].clone(), String::from("no reply")));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn convert_sway_output() {
+    let json = r#"{
+        "name": "DP-1",
+        "make": "Dell Inc.",
+        "model": "DELL U2720Q",
+        "serial": "ABC123",
+        "active": true,
+        "scale": 1.5,
+        "subpixel_hinting": "rgb",
+        "transform": "90",
+        "modes": [
+            {"width": 3840, "height": 2160, "refresh": 59997},
+            {"width": 3840, "height": 2160, "refresh": 29981},
+            {"width": 1920, "height": 1080, "refresh": 60000}
+        ],
+        "current_mode": {"width": 3840, "height": 2160, "refresh": 59997},
+        "rect": {"x": 1920, "y": 0, "width": 2560, "height": 1440},
+        "adaptive_sync_status": "enabled",
+        "max_render_time": 7
+    }"#;
+    let output: SwayOutput = serde_json::from_str(json).unwrap();
+    let monitor = output.convert_to_regular_monitor(0);
+    assert_eq!(monitor.size, Size(3840, 2160));
+    assert_eq!(monitor.refresh_rate, 59997);
+    assert_eq!(monitor.mode, "3840x2160@59.997Hz");
+    assert_eq!(monitor.transform, 1);
+    assert_eq!(monitor.offset, Offset(1920, 0));
+    assert!(monitor.vrr);
+    assert_eq!(monitor.subpixel, "rgb");
+    assert_eq!(monitor.max_render_time, 7);
+    assert_eq!(monitor.available_modes.len(), 2);
+    assert_eq!(monitor.available_modes[0].size, Size(3840, 2160));
+    assert_eq!(monitor.available_modes[0].refresh_rates[0].0, 59997);
+}
+
+#[test]
+fn sway_output_commands() {
+    let monitors = vec![
+        Monitor {
+            name: "DP-1".into(),
+            enabled: true,
+            size: Size(1920, 1080),
+            refresh_rate: 59940,
+            scale: 1.0,
+            transform: 5,
+            vrr: true,
+            offset: Offset(0, 0),
+            available_modes: vec![AvailableMode {
+                size: Size(1920, 1080),
+                refresh_rates: vec![
+                    (60000, "1920x1080@60.000Hz".into()),
+                    (59940, "1920x1080@59.940Hz".into()),
+                ],
+                ..Default::default()
+            }],
+            subpixel: "bgr".into(),
+            ..Default::default()
+        },
+        Monitor {
+            name: "HDMI-A-1".into(),
+            enabled: false,
+            ..Default::default()
+        },
+    ];
+    assert_eq!(
+        monitor_to_commands(&monitors),
+        vec![
+            String::from("output \"DP-1\" enable mode 1920x1080@59.940Hz position 0 0 scale 1.000000 transform flipped-90 adaptive_sync on subpixel bgr max_render_time off"),
+            String::from("output \"HDMI-A-1\" disable"),
+        ]
+    );
+}
+
+#[test]
+fn sway_socket_request() {
+    let path =
+        std::env::temp_dir().join(format!("reset-monitors-sway-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut header = [0; 14];
+        stream.read_exact(&mut header).unwrap();
+        let len = u32::from_ne_bytes(header[6..10].try_into().unwrap()) as usize;
+        let message_type = u32::from_ne_bytes(header[10..14].try_into().unwrap());
+        let mut payload = vec![0; len];
+        stream.read_exact(&mut payload).unwrap();
+        let reply = b"[{\"success\": true}, {\"success\": false, \"error\": \"Unknown output\"}]";
+        let mut message = b"i3-ipc".to_vec();
+        message.extend_from_slice(&(reply.len() as u32).to_ne_bytes());
+        message.extend_from_slice(&message_type.to_ne_bytes());
+        message.extend_from_slice(reply);
+        stream.write_all(&message).unwrap();
+        (message_type, String::from_utf8(payload).unwrap())
+    });
+    let commands = vec![
+        String::from("output \"DP-1\" disable"),
+        String::from("output \"DP-9\" disable"),
+    ];
+    let reply = sway_request(&path, 0, &commands.join(";")).unwrap();
+    assert_eq!(handle.join().unwrap(), (0, commands.join(";")));
+    let errors = command_errors(&commands, &reply);
+    assert_eq!(
+        errors,
+        vec![(commands[1].clone(), String::from("Unknown output"))]
+    );
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn sway_output_events() {
+    let path = std::env::temp_dir().join(format!(
+        "reset-monitors-sway-events-{}.sock",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+    let message = |message_type: u32, payload: &[u8]| {
+        let mut message = b"i3-ipc".to_vec();
+        message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+        message.extend_from_slice(&message_type.to_ne_bytes());
+        message.extend_from_slice(payload);
+        message
+    };
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut header = [0; 14];
+        stream.read_exact(&mut header).unwrap();
+        let len = u32::from_ne_bytes(header[6..10].try_into().unwrap()) as usize;
+        let mut payload = vec![0; len];
+        stream.read_exact(&mut payload).unwrap();
+        stream
+            .write_all(&message(2, b"{\"success\": true}"))
+            .unwrap();
+        // output and workspace events, the connection is closed afterwards
+        stream
+            .write_all(&message(0x80000001, b"{\"change\": \"unspecified\"}"))
+            .unwrap();
+        stream
+            .write_all(&message(0x80000000, b"{\"change\": \"focus\"}"))
+            .unwrap();
+        stream
+            .write_all(&message(0x80000001, b"{\"change\": \"unspecified\"}"))
+            .unwrap();
+        String::from_utf8(payload).unwrap()
+    });
+    let mut events = 0;
+    assert!(sway_watch_outputs(&path, || events += 1).is_err());
+    assert_eq!(handle.join().unwrap(), "[\"output\"]");
+    assert_eq!(events, 2);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[cfg(test)]
+const KANSHI_CONFIG: &str = r#"# laptop only
+profile laptop {
+    output eDP-1 enable scale 2
+}
+
+profile docked {
+    output eDP-1 disable
+    output "Dell Inc. DELL U2720Q ABC123" enable position 0,0 # external
+}
+
+output "Some Global" scale 1
+"#;
+
+#[cfg(test)]
+fn create_kanshi_monitors() -> Vec<Monitor> {
+    vec![
+        Monitor {
+            name: "eDP-1".into(),
+            enabled: false,
+            ..Default::default()
+        },
+        Monitor {
+            name: "DP-3".into(),
+            make: "Dell Inc.".into(),
+            model: "DELL U2720Q".into(),
+            serial: "ABC123".into(),
+            enabled: true,
+            size: Size(3840, 2160),
+            refresh_rate: 60000,
+            scale: 1.5,
+            ..Default::default()
+        },
+    ]
+}
+
+#[test]
+fn parse_kanshi_profiles() {
+    let profiles = parse_kanshi_config(KANSHI_CONFIG);
+    assert_eq!(profiles.len(), 2);
+    assert_eq!(profiles[0].name, Some(String::from("laptop")));
+    assert_eq!(profiles[1].outputs.len(), 2);
+    assert_eq!(
+        profiles[1].outputs[1].criteria,
+        "Dell Inc. DELL U2720Q ABC123"
+    );
+    assert_eq!(
+        profiles[1].outputs[1].arguments,
+        vec!["enable", "position", "0,0"]
+    );
+    assert!(KANSHI_CONFIG[profiles[1].start..profiles[1].end].starts_with("profile docked {"));
+    assert!(KANSHI_CONFIG[profiles[1].start..profiles[1].end].ends_with('}'));
+}
+
+#[test]
+fn merge_kanshi_replaces_matching_profile() {
+    let merged = merge_kanshi_config(KANSHI_CONFIG, &create_kanshi_monitors());
+    assert!(
+        merged.starts_with("# laptop only\nprofile laptop {\n    output eDP-1 enable scale 2\n}\n")
+    );
+    assert!(merged.contains("profile docked {\n    output \"eDP-1\" disable\n    output \"Dell Inc. DELL U2720Q ABC123\" enable mode 3840x2160@60Hz position 0,0 scale 1.500000 transform normal adaptive_sync off\n}"));
+    assert!(merged.ends_with("\n\noutput \"Some Global\" scale 1\n"));
+    assert_eq!(parse_kanshi_config(&merged).len(), 2);
+}
+
+#[test]
+fn merge_kanshi_appends_new_profile() {
+    let mut monitors = create_kanshi_monitors();
+    monitors.remove(0);
+    monitors[0].name = "HDMI-A-1".into();
+    monitors[0].enabled = false;
+    let merged = merge_kanshi_config(KANSHI_CONFIG, &monitors);
+    assert!(merged.starts_with(KANSHI_CONFIG));
+    assert!(merged.ends_with(
+        "\nprofile reset_HDMI-A-1 {\n    output \"Dell Inc. DELL U2720Q ABC123\" disable\n}\n"
+    ));
+    assert_eq!(parse_kanshi_config(&merged).len(), 3);
+}
+
+#[test]
+fn merge_kanshi_keeps_wildcards_and_directives() {
+    let config = "profile docked {\n    output eDP-1 disable\n    output \"Dell Inc. *\" enable\n    exec notify-send \"docked\" # notify\n}\n";
+    let profiles = parse_kanshi_config(config);
+    assert_eq!(profiles[0].directives, vec!["exec notify-send \"docked\""]);
+    assert!(kanshi_profile_to_monitors(&profiles[0], &create_kanshi_monitors()).is_some());
+
+    // the profile used by kanshi is replaced instead of appending one that is never picked
+    let merged = merge_kanshi_config(config, &create_kanshi_monitors());
+    assert_eq!(parse_kanshi_config(&merged).len(), 1);
+    assert!(merged.contains("    output \"Dell Inc. *\" enable mode 3840x2160@60Hz "));
+    assert!(merged.ends_with("    exec notify-send \"docked\"\n}\n"));
+}
+
+#[test]
+fn read_kanshi_config_errors() {
+    let path = std::env::temp_dir().join(format!("reset-kanshi-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(read_kanshi_config(&path), Ok(String::new()));
+    // an unreadable config must not be overwritten as if it was empty
+    std::fs::write(&path, [0xff, 0xfe]).unwrap();
+    let res = read_kanshi_config(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(res, Err(MonitorError::Io(_))));
+}
+
 pub fn dbus_end_point() -> Result<(), PluginTestError> {
     let conn = Connection::new_session().unwrap();
     let proxy = conn.with_proxy(
@@ -301,3 +883,1373 @@ fn create_monitor_pair(offset: Offset) -> Rc<RefCell<Vec<Monitor>>> {
     monitor_drag_end(monitors.clone(), None, false);
     monitors
 }
+
+#[test]
+fn convert_kanshi_profile() {
+    let mut current = create_kanshi_monitors();
+    current[1].available_modes = vec![AvailableMode {
+        id: "3840x2160".into(),
+        size: Size(3840, 2160),
+        refresh_rates: vec![
+            (60000, "60".into()),
+            (59940, "59.94".into()),
+            (30000, "30".into()),
+        ],
+        supported_scales: Vec::new(),
+    }];
+    current[1].refresh_rate = 30000;
+    let profiles = parse_kanshi_config(
+        "profile {\n    output eDP-* enable scale 2\n    output \"Dell Inc. *\" mode 3840x2160@59.94Hz position 1280,0 transform 90\n}\n",
+    );
+    let monitors = kanshi_profile_to_monitors(&profiles[0], &current).unwrap();
+    assert!(monitors[0].enabled);
+    assert_eq!(monitors[0].scale, 2.0);
+    // 59.94 Hz and 60 Hz are separate modes
+    assert_eq!(monitors[1].refresh_rate, 59940);
+    assert_eq!(monitors[1].offset.0, 1280);
+    assert_eq!(monitors[1].transform, 1);
+
+    // profiles for other setups are not usable
+    assert!(kanshi_profile_to_monitors(&parse_kanshi_config(KANSHI_CONFIG)[0], &current).is_none());
+}
+
+#[cfg(test)]
+fn create_mirrored_monitors() -> Vec<Monitor> {
+    vec![
+        Monitor {
+            name: "eDP-1".into(),
+            enabled: true,
+            size: Size(1920, 1080),
+            refresh_rate: 60000,
+            scale: 1.0,
+            offset: Offset(0, 0),
+            ..Default::default()
+        },
+        Monitor {
+            name: "HDMI-A-1".into(),
+            enabled: true,
+            size: Size(3840, 2160),
+            refresh_rate: 30000,
+            scale: 1.0,
+            offset: Offset(1920, 0),
+            available_modes: vec![
+                AvailableMode {
+                    id: "0".into(),
+                    size: Size(3840, 2160),
+                    refresh_rates: vec![(30000, "0".into())],
+                    supported_scales: Vec::new(),
+                },
+                AvailableMode {
+                    id: "1".into(),
+                    size: Size(1920, 1080),
+                    refresh_rates: vec![(75000, "1".into()), (60000, "2".into())],
+                    supported_scales: Vec::new(),
+                },
+            ],
+            uses_mode_id: true,
+            mirror: "eDP-1".into(),
+            ..Default::default()
+        },
+    ]
+}
+
+#[test]
+fn mirror_fallback_shares_source_area() {
+    let mut monitors = create_mirrored_monitors();
+    let converted = apply_mirror_fallback(&monitors);
+    assert_eq!(converted[1].offset, Offset(0, 0));
+    assert_eq!(converted[1].size, Size(1920, 1080));
+    assert_eq!(converted[1].refresh_rate, 60000);
+    assert_eq!(converted[1].mode, "2");
+
+    // without a matching mode, the scale is used to cover the same area
+    monitors[1].available_modes.pop();
+    let converted = apply_mirror_fallback(&monitors);
+    assert_eq!(converted[1].size, Size(3840, 2160));
+    assert_eq!(converted[1].scale, 2.0);
+
+    // the fallback is reported as mirror again
+    let mut detected = converted.clone();
+    for monitor in detected.iter_mut() {
+        monitor.mirror.clear();
+    }
+    detect_mirrors(&mut detected);
+    assert_eq!(detected[1].mirror, "eDP-1");
+    assert!(detected[0].mirror.is_empty());
+}
+
+#[test]
+fn hyprland_mirror_commands() {
+    let mut monitors = create_mirrored_monitors();
+    let commands = hypr_monitor_to_commands(&monitors);
+    assert!(commands[1].ends_with(",mirror,eDP-1"));
+    assert!(!commands[0].contains("mirror"));
+
+    // disabled sources can't be mirrored
+    monitors[0].enabled = false;
+    let commands = hypr_monitor_to_commands(&monitors);
+    assert!(!commands[1].contains("mirror"));
+}
+
+#[test]
+fn custom_mode_from_modeline() {
+    let custom_mode = CustomMode::from_modeline(
+        "Modeline \"1920x1080_60.00\"  173.00  1920 2048 2248 2576  1080 1083 1088 1120 -hsync +vsync",
+    )
+    .unwrap();
+    assert_eq!(custom_mode.width, 1920);
+    assert_eq!(custom_mode.height, 1080);
+    assert_eq!(custom_mode.refresh_rate, 59.963);
+    assert_eq!(
+        custom_mode.modeline,
+        "173.00 1920 2048 2248 2576 1080 1083 1088 1120 -hsync +vsync"
+    );
+    assert!(custom_mode.validate().is_ok());
+
+    assert!(CustomMode::from_modeline("173.00 1920 2048 2248 2576").is_err());
+    assert!(CustomMode::from_modeline("173.00 1920 1800 2248 2576 1080 1083 1088 1120").is_err());
+    assert!(
+        CustomMode::from_modeline("173.00 1920 2048 2248 2576 1080 1083 1088 1120 +fast").is_err()
+    );
+}
+
+#[test]
+fn custom_mode_validation() {
+    let mut custom_mode = CustomMode {
+        width: 2560,
+        height: 1080,
+        refresh_rate: 75.0,
+        modeline: "".into(),
+    };
+    assert!(custom_mode.validate().is_ok());
+    custom_mode.refresh_rate = 0.0;
+    assert!(custom_mode.validate().is_err());
+    custom_mode.refresh_rate = 75.0;
+    custom_mode.width = 0;
+    assert!(custom_mode.validate().is_err());
+
+    // modelines have to describe the same mode
+    custom_mode.width = 2560;
+    custom_mode.modeline = "173.00 1920 2048 2248 2576 1080 1083 1088 1120".into();
+    assert!(custom_mode.validate().is_err());
+}
+
+#[test]
+fn custom_mode_commands() {
+    let mut monitor = Monitor {
+        name: "DP-1".into(),
+        enabled: true,
+        size: Size(2560, 1080),
+        refresh_rate: 75000,
+        scale: 1.0,
+        custom_mode: CustomMode {
+            width: 2560,
+            height: 1080,
+            refresh_rate: 74.991,
+            modeline: "".into(),
+        },
+        ..Default::default()
+    };
+    let commands = monitor_to_commands(&[monitor.clone()]);
+    assert!(commands[0].starts_with("output \"DP-1\" enable mode --custom 2560x1080@74.991Hz "));
+    assert!(monitor_to_kanshi_output(&monitor).contains(" mode --custom 2560x1080@74.991Hz "));
+
+    monitor.custom_mode =
+        CustomMode::from_modeline("173.00 1920 2048 2248 2576 1080 1083 1088 1120 -hsync +vsync")
+            .unwrap();
+    let commands = monitor_to_commands(&[monitor.clone()]);
+    assert!(commands[0].contains(
+        " enable modeline 173.00 1920 2048 2248 2576 1080 1083 1088 1120 -hsync +vsync "
+    ));
+    let commands = hypr_monitor_to_commands(&[monitor.clone()]);
+    assert!(commands[0].starts_with(
+        "keyword monitor DP-1,modeline 173.00 1920 2048 2248 2576 1080 1083 1088 1120 -hsync +vsync,"
+    ));
+
+    // invalid custom modes are ignored
+    monitor.custom_mode.refresh_rate = 0.0;
+    let commands = monitor_to_commands(&[monitor]);
+    assert!(commands[0].contains(" enable mode 2560x1080@75Hz "));
+}
+
+#[test]
+fn fractional_refresh_rates() {
+    assert_eq!(format_refresh_rate(59940), "59.94");
+    assert_eq!(format_refresh_rate(60000), "60");
+    assert_eq!(format_refresh_rate(143856), "143.856");
+
+    let hypr_monitor = HyprMonitor {
+        name: "DP-1".into(),
+        width: 1920,
+        height: 1080,
+        refreshRate: 59.93999,
+        scale: 1.0,
+        availableModes: vec!["1920x1080@60.00Hz".into(), "1920x1080@59.94Hz".into()],
+        ..Default::default()
+    };
+    let monitor = hypr_monitor.convert_to_regular_monitor();
+    assert_eq!(monitor.refresh_rate, 59940);
+    assert_eq!(
+        monitor.available_modes[0].refresh_rates,
+        vec![(60000, "".into()), (59940, "".into())]
+    );
+    assert!(!monitor.custom_mode.is_set());
+    assert!(hypr_monitor_to_commands(&[monitor])[0]
+        .starts_with("keyword monitor DP-1,1920x1080@59.94,"));
+}
+
+#[test]
+fn kde_hdr_settings() {
+    let kde_monitor = KDEMonitor {
+        name: "DP-1".into(),
+        modes: vec![KDEMode::default()],
+        enabled: true,
+        hdr: Some(true),
+        wcg: Some(true),
+        sdr_brightness: Some(300),
+        ..Default::default()
+    };
+    let monitor = kde_monitor.convert_to_regular_monitor().unwrap();
+    assert!(monitor.features.hdr);
+    assert_eq!(
+        monitor.hdr,
+        HdrSettings {
+            enabled: true,
+            wide_color_gamut: true,
+            sdr_brightness: 300,
+            sdr_gamut_wideness: 0.0,
+        }
+    );
+    let args = convert_modes_to_kscreen_string(&[monitor]);
+    assert!(args.contains(&"output.DP-1.hdr.enable".into()));
+    assert!(args.contains(&"output.DP-1.wcg.enable".into()));
+    assert!(args.contains(&"output.DP-1.sdr-brightness.300".into()));
+
+    // monitors without hdr don't report the values
+    let monitor = KDEMonitor {
+        name: "DP-2".into(),
+        modes: vec![KDEMode::default()],
+        enabled: true,
+        ..Default::default()
+    }
+    .convert_to_regular_monitor()
+    .unwrap();
+    assert!(!monitor.features.hdr);
+    assert!(!convert_modes_to_kscreen_string(&[monitor])
+        .iter()
+        .any(|arg| arg.contains("hdr")));
+}
+
+#[test]
+fn hyprland_hdr_commands() {
+    let hypr_monitor = HyprMonitor {
+        name: "DP-1".into(),
+        width: 1920,
+        height: 1080,
+        refreshRate: 60.0,
+        scale: 1.0,
+        availableModes: vec!["1920x1080@60.00Hz".into()],
+        colorManagementPreset: "srgb".into(),
+        sdrBrightness: 1.5,
+        sdrSaturation: 1.25,
+        ..Default::default()
+    };
+    let mut monitor = hypr_monitor.convert_to_regular_monitor();
+    assert!(monitor.features.hdr);
+    assert!(!monitor.hdr.enabled);
+    assert_eq!(monitor.hdr.sdr_brightness, 300);
+    assert_eq!(monitor.hdr.sdr_gamut_wideness, 0.25);
+    assert!(hypr_monitor_to_commands(std::slice::from_ref(&monitor))[0].ends_with(",cm,srgb"));
+
+    monitor.hdr.enabled = true;
+    assert!(hypr_monitor_to_commands(&[monitor])[0]
+        .ends_with(",bitdepth,10,cm,hdr,sdrbrightness,1.50,sdrsaturation,1.25"));
+
+    // auto is the default and only uses a wide gamut with 10 bit
+    for (preset, enabled, wide_color_gamut) in [
+        ("auto", false, false),
+        ("edid", false, false),
+        ("wide", false, true),
+        ("dcip3", false, true),
+        ("hdredid", true, true),
+    ] {
+        let monitor = HyprMonitor {
+            name: "DP-1".into(),
+            availableModes: vec!["1920x1080@60.00Hz".into()],
+            colorManagementPreset: preset.into(),
+            ..Default::default()
+        }
+        .convert_to_regular_monitor();
+        assert_eq!(monitor.hdr.enabled, enabled, "{}", preset);
+        assert_eq!(monitor.hdr.wide_color_gamut, wide_color_gamut, "{}", preset);
+    }
+
+    // versions without color management don't receive any hdr arguments
+    let monitor = HyprMonitor {
+        name: "DP-2".into(),
+        ..Default::default()
+    }
+    .convert_to_regular_monitor();
+    assert!(!monitor.features.hdr);
+    assert!(!hypr_monitor_to_commands(&[monitor])[0].contains(",cm,"));
+}
+
+#[test]
+fn ddc_brightness_messages() {
+    assert_eq!(
+        ddc_get_vcp_request(0x10),
+        vec![0x51, 0x82, 0x01, 0x10, 0xAC]
+    );
+    assert_eq!(
+        ddc_set_vcp_request(0x10, 50),
+        vec![0x51, 0x84, 0x03, 0x10, 0x00, 0x32, 0x9A]
+    );
+
+    let reply = [
+        0x6E, 0x88, 0x02, 0x00, 0x10, 0x00, 0x00, 0x64, 0x00, 0x32, 0xF2,
+    ];
+    assert_eq!(ddc_parse_vcp_reply(&reply, 0x10), Some((50, 100)));
+    // wrong feature, unsupported feature and broken checksum
+    assert_eq!(ddc_parse_vcp_reply(&reply, 0x12), None);
+    let mut unsupported = reply;
+    unsupported[3] = 0x01;
+    assert_eq!(ddc_parse_vcp_reply(&unsupported, 0x10), None);
+    let mut broken = reply;
+    broken[10] = 0x00;
+    assert_eq!(ddc_parse_vcp_reply(&broken, 0x10), None);
+    assert_eq!(ddc_parse_vcp_reply(&reply[..5], 0x10), None);
+}
+
+#[test]
+fn brightness_conversion() {
+    assert_eq!(to_percent(120000, 120000), 100);
+    assert_eq!(to_percent(30, 100), 30);
+    assert_eq!(to_percent(0, 0), 100);
+    assert_eq!(from_percent(50, 937), 469);
+
+    let ramp = gamma_ramp(4, 100, 1.0, 6500);
+    assert_eq!(ramp.len(), 12);
+    assert_eq!(&ramp[..4], &[0, 21845, 43690, 65535]);
+    assert_eq!(&ramp[..4], &ramp[4..8]);
+    assert_eq!(&ramp[..4], &ramp[8..]);
+
+    let dimmed = gamma_ramp(4, 50, 1.0, 6500);
+    assert_eq!(dimmed[3], 32768);
+    let brightened = gamma_ramp(4, 100, 2.0, 6500);
+    assert!(brightened[1] > ramp[1]);
+    assert_eq!(brightened[3], 65535);
+}
+
+#[test]
+fn night_light_schedule() {
+    assert!(is_night(22 * 60, 20 * 60, 6 * 60));
+    assert!(is_night(60, 20 * 60, 6 * 60));
+    assert!(!is_night(12 * 60, 20 * 60, 6 * 60));
+    assert!(is_night(12 * 60, 9 * 60, 17 * 60));
+    assert!(!is_night(12 * 60, 6 * 60, 6 * 60));
+
+    // equinox at the equator, sunrise around 06:00 and sunset around 18:00 UTC
+    let (sunrise, sunset) = sun_times(80, 0.0, 0.0).unwrap();
+    assert!((sunrise - 360.0).abs() < 15.0);
+    assert!((sunset - 1080.0).abs() < 15.0);
+    // polar night
+    assert!(sun_times(355, 80.0, 0.0).is_none());
+
+    let mut night_light = NightLight {
+        enabled: true,
+        ..Default::default()
+    };
+    assert_eq!(current_temperature(&night_light, 22 * 60, 1, 0), 4000);
+    assert_eq!(current_temperature(&night_light, 12 * 60, 1, 0), 6500);
+    night_light.schedule = NIGHT_LIGHT_SUN.into();
+    // 15° east is one hour ahead of UTC, the local times are the same as in UTC at 0°
+    night_light.longitude = 15.0;
+    assert_eq!(current_temperature(&night_light, 12 * 60, 80, 60), 6500);
+    assert_eq!(current_temperature(&night_light, 20 * 60, 80, 60), 4000);
+    night_light.enabled = false;
+    assert_eq!(current_temperature(&night_light, 20 * 60, 80, 60), 6500);
+
+    assert!(is_valid_night_light(&NightLight::default()));
+    night_light.start = 24 * 60;
+    assert!(!is_valid_night_light(&night_light));
+
+    assert_eq!(parse_time("06:30"), Some(390));
+    assert_eq!(parse_time("24:00"), None);
+    assert_eq!(format_time(390), "06:30");
+    assert_eq!(temperature_to_rgb(6500), (1.0, 1.0, 1.0));
+    let (red, green, blue) = temperature_to_rgb(3000);
+    assert_eq!(red, 1.0);
+    assert!(green < 1.0 && blue < green);
+}
+
+#[test]
+fn native_night_light_settings() {
+    let night_light = NightLight {
+        enabled: true,
+        temperature: 3500,
+        start: 21 * 60 + 30,
+        end: 7 * 60,
+        ..Default::default()
+    };
+    let gnome = gnome_night_light_settings(&night_light);
+    assert!(gnome.contains(&("night-light-enabled", "true".into())));
+    assert!(gnome.contains(&("night-light-temperature", "3500".into())));
+    assert!(gnome.contains(&("night-light-schedule-automatic", "false".into())));
+    assert!(gnome.contains(&("night-light-schedule-from", "21.50".into())));
+    let kde = kde_night_light_settings(&night_light);
+    assert!(kde.contains(&("Mode", "Times".into())));
+    assert!(kde.contains(&("EveningBeginFixed", "2130".into())));
+    assert!(kde.contains(&("MorningBeginFixed", "0700".into())));
+}
+
+#[cfg(test)]
+fn create_validation_monitors() -> Vec<Monitor> {
+    let features = MonitorFeatures {
+        fractional_scaling: true,
+        ..Default::default()
+    };
+    vec![
+        Monitor {
+            name: "eDP-1".into(),
+            enabled: true,
+            size: Size(1920, 1080),
+            refresh_rate: 60000,
+            scale: 1.0,
+            offset: Offset(0, 0),
+            features,
+            ..Default::default()
+        },
+        Monitor {
+            name: "DP-1".into(),
+            enabled: true,
+            size: Size(2560, 1440),
+            refresh_rate: 144000,
+            scale: 1.0,
+            offset: Offset(1920, 0),
+            features,
+            available_modes: vec![AvailableMode {
+                id: "2560x1440".into(),
+                size: Size(2560, 1440),
+                refresh_rates: vec![(144000, "144".into()), (60000, "60".into())],
+                supported_scales: Vec::new(),
+            }],
+            ..Default::default()
+        },
+    ]
+}
+
+#[test]
+fn validate_layout() {
+    let mut monitors = create_validation_monitors();
+    assert!(validate_monitors(&monitors, true).is_empty());
+
+    monitors[1].offset = Offset(1000, 0);
+    let errors = validate_monitors(&monitors, true);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, INVALID_OVERLAP);
+    assert_eq!(errors[0].monitor, "DP-1");
+
+    // gaps are only rejected by environments that require adjacent monitors
+    monitors[1].offset = Offset(2000, 0);
+    assert!(validate_monitors(&monitors, false).is_empty());
+    let errors = validate_monitors(&monitors, true);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, INVALID_GAP);
+    // the backend decides whether gaps are allowed
+    assert!(validate_configuration(&HyprlandBackend, &monitors).is_empty());
+    assert_eq!(
+        validate_configuration(&GnomeBackend, &monitors)[0].code,
+        INVALID_GAP
+    );
+
+    // corners touching is not enough
+    monitors[1].offset = Offset(1920, 1080);
+    assert_eq!(validate_monitors(&monitors, true)[0].code, INVALID_GAP);
+
+    // the logical size is used, scaled monitors take up less space
+    monitors[1].offset = Offset(1280, 0);
+    monitors[0].scale = 1.5;
+    assert!(validate_monitors(&monitors, true).is_empty());
+
+    // mirrored monitors share the area of their source
+    monitors[1].offset = Offset(0, 0);
+    monitors[1].mirror = "eDP-1".into();
+    assert!(validate_monitors(&monitors, true).is_empty());
+
+    for monitor in monitors.iter_mut() {
+        monitor.enabled = false;
+    }
+    let errors = validate_monitors(&monitors, true);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, INVALID_NO_ENABLED_MONITOR);
+}
+
+#[test]
+fn validate_scale_and_mode() {
+    let mut monitors = create_validation_monitors();
+    // 1920 / 1.7 and 1080 / 1.7 are not whole numbers
+    monitors[0].scale = 1.7;
+    let errors = validate_monitors(&monitors, false);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, INVALID_SCALE);
+    assert_eq!(errors[0].monitor, "eDP-1");
+    monitors[0].scale = 0.0;
+    assert_eq!(validate_monitors(&monitors, false)[0].code, INVALID_SCALE);
+    monitors[0].scale = 1.25;
+    monitors[0].features.fractional_scaling = false;
+    assert_eq!(validate_monitors(&monitors, false)[0].code, INVALID_SCALE);
+    monitors[0].scale = 2.0;
+    assert!(validate_monitors(&monitors, false).is_empty());
+
+    monitors[1].refresh_rate = 75000;
+    let errors = validate_monitors(&monitors, false);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, INVALID_MODE);
+    assert_eq!(errors[0].monitor, "DP-1");
+
+    // GNOME only allows the scales reported for the mode
+    monitors[1].refresh_rate = 60000;
+    monitors[1].available_modes[0].supported_scales = vec![1.0, 1.25, 2.0];
+    monitors[1].scale = 1.5;
+    assert_eq!(validate_monitors(&monitors, false)[0].code, INVALID_SCALE);
+    monitors[1].scale = 1.25;
+    assert!(validate_monitors(&monitors, false).is_empty());
+}
+
+#[test]
+fn monitor_error_dbus_names() {
+    let error = MonitorError::ApplyFailed("DP-1: invalid mode".into());
+    assert_eq!(
+        error.dbus_name(),
+        "org.Xetibo.ReSet.Monitors.Error.ApplyFailed"
+    );
+    assert!(error.to_string().contains("DP-1: invalid mode"));
+    assert_eq!(
+        MonitorError::MissingTool("hyprctl".into()).dbus_name(),
+        "org.Xetibo.ReSet.Monitors.Error.MissingTool"
+    );
+    let io_error = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied");
+    assert!(matches!(MonitorError::from(io_error), MonitorError::Io(_)));
+    let parse_error = serde_json::from_str::<Vec<u32>>("{").unwrap_err();
+    assert!(matches!(
+        MonitorError::from(parse_error),
+        MonitorError::Parse(_)
+    ));
+}
+
+#[test]
+fn backend_capabilities() {
+    let backends: Vec<(Box<dyn MonitorBackend>, _)> = vec![
+        (Box::new(HyprlandBackend), HYPRFEATURES),
+        (Box::new(KdeBackend), KDE_FEATURES),
+        (Box::new(SwayBackend), SWAYFEATURES),
+    ];
+    for (backend, features) in backends {
+        assert_eq!(backend.features(), features);
+        assert_eq!(backend.capabilities().features, features);
+        assert!(backend.caches_monitors());
+    }
+    // mutter needs the current serial for every configuration
+    assert!(!GnomeBackend.caches_monitors());
+}
+
+#[test]
+fn backend_environment_capabilities() {
+    let gnome = GnomeBackend.capabilities();
+    assert!(gnome.persistent_save && gnome.confirms_save && gnome.compact_layout);
+    assert!(!gnome.gaps && !gnome.arbitrary_scales);
+
+    let kde = KdeBackend.capabilities();
+    assert!(!kde.persistent_save && !kde.gaps && kde.arbitrary_scales);
+    assert!(!kde.reports_vrr);
+
+    let hyprland = HyprlandBackend.capabilities();
+    assert!(hyprland.gaps && hyprland.arbitrary_scales && hyprland.sdr_gamut_wideness);
+    assert!(!hyprland.reports_vrr && !hyprland.compact_layout);
+
+    let sway = SwayBackend.capabilities();
+    assert!(sway.persistent_save && sway.gaps && sway.reports_vrr);
+    assert!(!sway.native_mirroring && !sway.confirms_save);
+}
+
+#[test]
+fn backend_capabilities_dbus_roundtrip() {
+    let capabilities = HyprlandBackend.capabilities();
+    let msg = dbus::Message::new_method_call(
+        "org.Xetibo.ReSet.Daemon",
+        "/org/Xetibo/ReSet/Monitors",
+        "org.Xetibo.ReSet.Monitors",
+        "GetCapabilities",
+    )
+    .unwrap()
+    .append1(capabilities);
+    assert_eq!(
+        msg.iter_init().signature().to_string(),
+        "(bbbbbbbb(bbbbbbb))"
+    );
+    assert_eq!(msg.read1::<BackendCapabilities>().unwrap(), capabilities);
+}
+
+// collects the messages sent by the crossroads instance
+#[cfg(test)]
+#[derive(Default)]
+struct RecordingSender(std::sync::Mutex<Vec<dbus::Message>>);
+
+#[cfg(test)]
+impl dbus::channel::Sender for RecordingSender {
+    fn send(&self, msg: dbus::Message) -> Result<u32, ()> {
+        self.0.lock().unwrap().push(msg);
+        Ok(0)
+    }
+}
+
+#[test]
+fn monitors_changed_signal_of_daemon_connection() {
+    let mut cross = dbus_crossroads::Crossroads::new();
+    {
+        let wrapper = std::sync::RwLock::new(CrossWrapper::new(&mut cross));
+        let mut wrapper = wrapper.write().unwrap();
+        let interface = setup_dbus_interface(&mut wrapper);
+        let data = MonitorData {
+            backend: Arc::new(RecordingBackend::default()),
+            monitors: create_kanshi_monitors(),
+            connection: None,
+            serial: 0,
+            changed: Arc::new(AtomicBool::new(true)),
+            brightness: Default::default(),
+            night_light: Default::default(),
+            revert: Default::default(),
+        };
+        wrapper.insert::<MonitorData>("Monitors", &[interface], data);
+    }
+    let mut msg = dbus::Message::new_method_call(
+        "org.Xetibo.ReSet.Daemon",
+        "/org/Xetibo/ReSet/Plugins/Monitors",
+        "org.Xetibo.ReSet.Monitors",
+        "EmitMonitorsChanged",
+    )
+    .unwrap();
+    msg.set_no_reply(true);
+    let sender = RecordingSender::default();
+    cross.handle_message(msg, &sender).unwrap();
+
+    // the signal is sent via the connection handling the call, which is the one of the daemon
+    let sent = sender.0.lock().unwrap();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].msg_type(), dbus::MessageType::Signal);
+    assert_eq!(&*sent[0].member().unwrap(), "MonitorsChanged");
+    assert_eq!(&*sent[0].interface().unwrap(), "org.Xetibo.ReSet.Monitors");
+    assert_eq!(
+        &*sent[0].path().unwrap(),
+        "/org/Xetibo/ReSet/Plugins/Monitors"
+    );
+    // the monitors are fetched again, as the watcher marked them as changed
+    assert!(sent[0].read1::<Vec<Monitor>>().unwrap().is_empty());
+}
+
+#[cfg(test)]
+fn create_mock_heads() -> Vec<MockHead> {
+    let mode = |width, height, refresh, preferred| MockMode {
+        width,
+        height,
+        refresh,
+        preferred,
+    };
+    vec![
+        MockHead {
+            name: "DP-1".into(),
+            make: "Dell".into(),
+            model: "U2720Q".into(),
+            serial: "1234".into(),
+            enabled: true,
+            modes: vec![
+                mode(3840, 2160, 60000, true),
+                mode(2560, 1440, 59951, false),
+            ],
+            current_mode: 0,
+            position: (0, 0),
+            transform: 0,
+            scale: 1.5,
+            vrr: false,
+        },
+        MockHead {
+            name: "HDMI-A-1".into(),
+            make: "LG".into(),
+            model: "27GL850".into(),
+            serial: "5678".into(),
+            enabled: true,
+            modes: vec![
+                mode(2560, 1440, 144000, true),
+                mode(2560, 1440, 60000, false),
+            ],
+            current_mode: 1,
+            position: (2560, 0),
+            transform: 1,
+            scale: 1.0,
+            vrr: false,
+        },
+    ]
+}
+
+#[cfg(test)]
+fn find_monitor<'a>(monitors: &'a [Monitor], name: &str) -> &'a Monitor {
+    monitors
+        .iter()
+        .find(|monitor| monitor.name == name)
+        .unwrap()
+}
+
+#[cfg(test)]
+fn check_mock_monitors(monitors: &[Monitor]) {
+    assert_eq!(monitors.len(), 2);
+    let dell = find_monitor(monitors, "DP-1");
+    assert!(dell.enabled);
+    assert_eq!(dell.size, Size(3840, 2160));
+    assert_eq!(dell.refresh_rate, 60000);
+    assert_eq!(dell.scale, 1.5);
+    assert_eq!(dell.available_modes.len(), 2);
+    let lg = find_monitor(monitors, "HDMI-A-1");
+    assert_eq!(lg.offset, Offset(2560, 0));
+    assert_eq!(lg.refresh_rate, 60000);
+    assert_eq!(lg.transform, 1);
+    // both refresh rates share the same size
+    assert_eq!(lg.available_modes.len(), 1);
+    assert_eq!(lg.available_modes[0].refresh_rates.len(), 2);
+}
+
+// moves the second monitor below the first one with the faster mode
+#[cfg(test)]
+fn rearrange_mock_monitors(monitors: &mut [Monitor]) {
+    for monitor in monitors.iter_mut() {
+        if monitor.name != "HDMI-A-1" {
+            continue;
+        }
+        let mode = &monitor.available_modes[0];
+        let (_, id) = mode
+            .refresh_rates
+            .iter()
+            .find(|(refresh_rate, _)| *refresh_rate == 144000)
+            .unwrap();
+        monitor.mode = id.clone();
+        monitor.offset = Offset(0, 1440);
+        monitor.transform = 0;
+        monitor.scale = 2.0;
+    }
+}
+
+#[cfg(test)]
+fn check_applied_head(applied: &[AppliedHead]) {
+    let head = applied.iter().find(|head| head.name == "HDMI-A-1").unwrap();
+    assert!(head.enabled);
+    assert_eq!(head.mode, Some((2560, 1440, 144000)));
+    assert_eq!(head.position, Some((0, 1440)));
+    assert_eq!(head.transform, Some(0));
+    assert_eq!(head.scale, Some(2.0));
+}
+
+#[test]
+fn wlr_mock_compositor() {
+    let (compositor, conn) = MockCompositor::start(create_mock_heads(), false);
+    let conn = Some(Arc::new(conn));
+    let mut monitors = wlr_get_monitor_information(conn.clone());
+    check_mock_monitors(&monitors);
+
+    rearrange_mock_monitors(&mut monitors);
+    assert!(wlr_apply_monitor_configuration(conn, &monitors).is_ok());
+    let applied = compositor.applied();
+    assert_eq!(applied.len(), 1);
+    assert_eq!(applied[0].len(), 2);
+    check_applied_head(&applied[0]);
+}
+
+#[test]
+fn wlr_mock_compositor_rejects() {
+    let (compositor, conn) = MockCompositor::start(create_mock_heads(), true);
+    let conn = Some(Arc::new(conn));
+    let monitors = wlr_get_monitor_information(conn.clone());
+    let res = wlr_apply_monitor_configuration(conn, &monitors);
+    assert!(matches!(res, Err(MonitorError::ApplyFailed(_))));
+    assert_eq!(compositor.applied().len(), 1);
+}
+
+#[test]
+fn kwin_mock_compositor() {
+    let (compositor, conn) = MockCompositor::start(create_mock_heads(), false);
+    let conn = Some(Arc::new(conn));
+    let mut monitors = kwin_get_monitor_information(conn.clone());
+    check_mock_monitors(&monitors);
+    assert_eq!(find_monitor(&monitors, "DP-1").serial, "1234");
+
+    rearrange_mock_monitors(&mut monitors);
+    assert!(kwin_apply_monitor_configuration(conn.clone(), &monitors).is_ok());
+    check_applied_head(&compositor.applied()[0]);
+
+    let (compositor, conn) = MockCompositor::start(create_mock_heads(), true);
+    let conn = Some(Arc::new(conn));
+    let monitors = kwin_get_monitor_information(conn.clone());
+    let res = kwin_apply_monitor_configuration(conn, &monitors);
+    assert!(matches!(res, Err(MonitorError::ApplyFailed(_))));
+    assert_eq!(compositor.applied().len(), 1);
+}
+
+#[cfg(test)]
+fn create_mutter_state() -> MockMutterState {
+    let mode = |id: &str, width, height, refresh_rate, preferred| MockMutterMode {
+        id: id.into(),
+        width,
+        height,
+        refresh_rate,
+        preferred,
+        supported_scales: vec![1.0, 2.0],
+    };
+    let monitor = |connector: &str, modes: Vec<MockMutterMode>| MockMutterMonitor {
+        connector: connector.into(),
+        vendor: "ReSet".into(),
+        product: connector.into(),
+        serial: "0".into(),
+        current_mode: modes[0].id.clone(),
+        modes,
+        properties: vec![("display-name".into(), MockValue::Str(connector.into()))],
+    };
+    MockMutterState {
+        serial: 7,
+        monitors: vec![
+            monitor(
+                "eDP-1",
+                vec![
+                    mode("1920x1080@60.000", 1920, 1080, 60.0, true),
+                    mode("1280x720@60.000", 1280, 720, 60.0, false),
+                ],
+            ),
+            monitor(
+                "HDMI-1",
+                vec![
+                    mode("1920x1080@60.000", 1920, 1080, 60.0, true),
+                    mode("1920x1080@59.940", 1920, 1080, 59.94, false),
+                ],
+            ),
+            monitor(
+                "DP-1",
+                vec![
+                    mode("2560x1440@143.999", 2560, 1440, 143.999, true),
+                    mode("2560x1440@59.951", 2560, 1440, 59.951, false),
+                ],
+            ),
+        ],
+        // HDMI-1 mirrors eDP-1, DP-1 is disabled
+        logical_monitors: vec![MockLogicalMonitor {
+            x: 0,
+            y: 0,
+            scale: 1.0,
+            transform: 0,
+            primary: true,
+            monitors: vec!["eDP-1".into(), "HDMI-1".into()],
+        }],
+        properties: vec![
+            ("layout-mode".into(), MockValue::Uint(1)),
+            ("supports-mirroring".into(), MockValue::Bool(true)),
+        ],
+    }
+}
+
+#[test]
+fn mutter_get_current_state() {
+    let mutter = MockMutter::start(create_mutter_state());
+    let mut serial = 0;
+    let monitors = g_get_monitor_information_with(&mutter.connection(), &mut serial);
+    assert_eq!(serial, 7);
+    assert_eq!(monitors.len(), 3);
+    let laptop = find_monitor(&monitors, "eDP-1");
+    assert!(laptop.enabled && laptop.primary);
+    assert_eq!(laptop.mirror, "");
+    assert_eq!(laptop.mode, "1920x1080@60.000");
+    assert_eq!(laptop.available_modes[0].supported_scales, vec![1.0, 2.0]);
+    let hdmi = find_monitor(&monitors, "HDMI-1");
+    assert!(hdmi.enabled);
+    assert_eq!(hdmi.mirror, "eDP-1");
+    assert_eq!(hdmi.available_modes[0].refresh_rates.len(), 2);
+    let dp = find_monitor(&monitors, "DP-1");
+    assert!(!dp.enabled);
+    assert_eq!(dp.refresh_rate, 143999);
+}
+
+#[test]
+fn mutter_apply_methods() {
+    let mutter = MockMutter::start(create_mutter_state());
+    let conn = mutter.connection();
+    let mut serial = 0;
+    let mut monitors = g_get_monitor_information_with(&conn, &mut serial);
+    for monitor in monitors.iter_mut() {
+        if monitor.name == "DP-1" {
+            monitor.enabled = true;
+            monitor.offset = Offset(1920, 0);
+        }
+    }
+
+    // verify only checks the configuration
+    assert!(g_apply_monitor_config_with(&conn, METHOD_VERIFY, &monitors).is_ok());
+    assert_eq!(mutter.state().serial, 7);
+    assert_eq!(mutter.state().logical_monitors.len(), 1);
+
+    assert!(g_apply_monitor_config_with(&conn, METHOD_TEMPORARY, &monitors).is_ok());
+    let applied = mutter.applied();
+    assert_eq!(applied.len(), 2);
+    assert_eq!(applied[0].logical_monitors, applied[1].logical_monitors);
+    assert_eq!(applied[1].method, METHOD_TEMPORARY);
+    // the mirrored monitor shares the logical monitor of its source
+    let logical_monitors = &applied[1].logical_monitors;
+    assert_eq!(logical_monitors.len(), 2);
+    assert_eq!(
+        logical_monitors[0].monitors,
+        vec![
+            ("eDP-1".to_string(), "1920x1080@60.000".to_string()),
+            ("HDMI-1".to_string(), "1920x1080@60.000".to_string())
+        ]
+    );
+    assert_eq!((logical_monitors[1].x, logical_monitors[1].y), (1920, 0));
+    assert_eq!(logical_monitors[1].monitors[0].0, "DP-1");
+
+    let mut monitors = g_get_monitor_information_with(&conn, &mut serial);
+    assert_eq!(serial, 8);
+    assert!(find_monitor(&monitors, "DP-1").enabled);
+    for monitor in monitors.iter_mut() {
+        if monitor.name == "HDMI-1" {
+            monitor.enabled = false;
+        }
+    }
+    assert!(g_apply_monitor_config_with(&conn, METHOD_PERSISTENT, &monitors).is_ok());
+    let applied = mutter.applied();
+    assert_eq!(applied[2].method, METHOD_PERSISTENT);
+    assert_eq!(applied[2].serial, 8);
+    // disabled monitors are not part of any logical monitor
+    assert!(applied[2]
+        .logical_monitors
+        .iter()
+        .all(|logical_monitor| logical_monitor.monitors.len() == 1
+            && logical_monitor.monitors[0].0 != "HDMI-1"));
+    let monitors = g_get_monitor_information_with(&conn, &mut serial);
+    assert!(!find_monitor(&monitors, "HDMI-1").enabled);
+}
+
+#[test]
+fn mutter_serial_mismatch() {
+    let mutter = MockMutter::start(create_mutter_state());
+    let conn = mutter.connection();
+    let mut serial = 0;
+    let mut monitors = g_get_monitor_information_with(&conn, &mut serial);
+
+    // the configuration changed in the meantime, a stale serial is rejected by mutter
+    mutter.bump_serial();
+    let proxy = conn.with_proxy(
+        "org.gnome.Mutter.DisplayConfig",
+        "/org/gnome/Mutter/DisplayConfig",
+        Duration::from_millis(1000),
+    );
+    let res: Result<(), Error> = proxy.method_call(
+        "org.gnome.Mutter.DisplayConfig",
+        "ApplyMonitorsConfig",
+        GnomeMonitorConfig::from_regular_monitor(serial, METHOD_TEMPORARY, &monitors),
+    );
+    assert_eq!(
+        res.unwrap_err().name(),
+        Some("org.freedesktop.DBus.Error.AccessDenied")
+    );
+    assert!(mutter.applied().is_empty());
+
+    // the serial is fetched again right before applying
+    assert!(g_apply_monitor_config_with(&conn, METHOD_TEMPORARY, &monitors).is_ok());
+    assert_eq!(mutter.applied()[0].serial, 8);
+
+    for monitor in monitors.iter_mut() {
+        monitor.mode = "640x480@60.000".into();
+    }
+    let res = g_apply_monitor_config_with(&conn, METHOD_TEMPORARY, &monitors);
+    assert!(matches!(res, Err(MonitorError::ApplyFailed(message)) if message.contains("640x480")));
+}
+
+#[cfg(test)]
+const HYPRCTL_MONITORS: &str = include_str!("tests/fixtures/hyprctl_monitors.json");
+#[cfg(test)]
+const KSCREEN_DOCTOR: &str = include_str!("tests/fixtures/kscreen_doctor.json");
+
+#[test]
+fn host_command_runner() {
+    let runner = HostCommandRunner::default();
+    let output = runner.run("sh", &["-c", "echo out; echo err >&2; exit 3"]);
+    let output = output.unwrap();
+    assert!(!output.success);
+    assert_eq!(output.stdout, "out\n");
+    assert_eq!(output.stderr, "err\n");
+    let missing = runner.run("reset-missing-command", &[]).unwrap_err();
+    assert_eq!(missing.kind(), std::io::ErrorKind::NotFound);
+    let runner = HostCommandRunner {
+        timeout: Duration::from_millis(50),
+    };
+    let timeout = runner.run("sleep", &["5"]).unwrap_err();
+    assert_eq!(timeout.kind(), std::io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn hyprland_recorded_monitors() {
+    let runner = FixtureCommandRunner::new().with_output(
+        "hyprctl",
+        &["-j", "monitors", "all"],
+        HYPRCTL_MONITORS,
+    );
+    let monitors = hy_get_monitor_information_with(&runner, None);
+    assert_eq!(
+        runner.calls(),
+        vec![vec!["hyprctl", "-j", "monitors", "all"]]
+    );
+    assert_eq!(monitors.len(), 3);
+    let laptop = find_monitor(&monitors, "eDP-1");
+    assert_eq!(laptop.size, Size(2256, 1504));
+    assert_eq!(laptop.scale, 1.5);
+    let dell = find_monitor(&monitors, "DP-3");
+    assert_eq!(dell.serial, "8T6RN53");
+    assert_eq!(dell.offset, Offset(1504, -600));
+    // the refresh rate is matched to the rounded rate of the available modes
+    assert_eq!(dell.refresh_rate, 60000);
+    assert_eq!(dell.available_modes.len(), 3);
+    assert_eq!(find_monitor(&monitors, "HDMI-A-1").mirror, "eDP-1");
+
+    // without hyprctl and without a wayland connection there is nothing to fetch
+    let monitors = hy_get_monitor_information_with(&FixtureCommandRunner::new(), None);
+    assert!(monitors.is_empty());
+}
+
+#[test]
+fn kde_recorded_monitors() {
+    let runner = FixtureCommandRunner::new().with_output("kscreen-doctor", &["-j"], KSCREEN_DOCTOR);
+    let monitors = kde_get_monitor_information_with(&runner, None);
+    // disconnected outputs without modes are skipped
+    assert_eq!(monitors.len(), 2);
+    let dp = find_monitor(&monitors, "DP-1");
+    assert!(dp.primary && dp.vrr);
+    assert_eq!(dp.mode, "2");
+    assert_eq!(dp.refresh_rate, 59951);
+    assert_eq!(dp.scale, 1.25);
+    assert_eq!(dp.available_modes.len(), 2);
+    let hdmi = find_monitor(&monitors, "HDMI-A-1");
+    assert!(!hdmi.primary);
+    assert_eq!(hdmi.transform, 3);
+    assert_eq!(hdmi.offset, Offset(2048, 0));
+
+    // rejected configurations report the stderr of kscreen-doctor
+    let args = convert_modes_to_kscreen_string(&monitors);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let runner =
+        FixtureCommandRunner::new().with_failure("kscreen-doctor", &args, "mode not found\n");
+    let res = kde_save_monitor_config_with(&runner, None, &monitors);
+    assert!(matches!(res, Err(MonitorError::ApplyFailed(message)) if message == "mode not found"));
+    let runner = FixtureCommandRunner::new().with_output("kscreen-doctor", &args, "");
+    assert!(kde_save_monitor_config_with(&runner, None, &monitors).is_ok());
+}
+
+#[test]
+fn kde_invalid_output() {
+    // truncated output falls back to the protocol, which is not available without a connection
+    let runner =
+        FixtureCommandRunner::new().with_output("kscreen-doctor", &["-j"], &KSCREEN_DOCTOR[..100]);
+    assert!(kde_get_monitor_information_with(&runner, None).is_empty());
+
+    // outputs without a current mode are skipped
+    let monitor = KDEMonitor {
+        name: "DP-3".into(),
+        currentModeId: "7".into(),
+        modes: vec![KDEMode::default()],
+        ..Default::default()
+    };
+    assert!(monitor.convert_to_regular_monitor().is_none());
+}
+
+// records the configurations instead of applying them
+#[cfg(test)]
+#[derive(Debug, Default)]
+struct RecordingBackend {
+    applied: std::sync::Mutex<Vec<(Vec<Monitor>, bool)>>,
+}
+
+#[cfg(test)]
+impl MonitorBackend for RecordingBackend {
+    fn get(&self, _: Option<Arc<wayland_client::Connection>>, _: &mut u32) -> Vec<Monitor> {
+        Vec::new()
+    }
+
+    fn apply(
+        &self,
+        _: Option<Arc<wayland_client::Connection>>,
+        monitors: &[Monitor],
+    ) -> Result<(), MonitorError> {
+        self.applied
+            .lock()
+            .unwrap()
+            .push((monitors.to_vec(), false));
+        Ok(())
+    }
+
+    fn save(
+        &self,
+        _: Option<Arc<wayland_client::Connection>>,
+        monitors: &[Monitor],
+    ) -> Result<(), MonitorError> {
+        self.applied.lock().unwrap().push((monitors.to_vec(), true));
+        Ok(())
+    }
+
+    fn features(&self) -> MonitorFeatures {
+        SWAYFEATURES
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            features: SWAYFEATURES,
+            ..Default::default()
+        }
+    }
+
+    fn watch(&self, _: Option<Arc<wayland_client::Connection>>, _: Box<dyn FnMut()>) {}
+}
+
+#[cfg(test)]
+fn run_revert_timers(
+    revert: &Arc<std::sync::Mutex<RevertData>>,
+    generations: &[u64],
+) -> (Arc<RecordingBackend>, bool) {
+    let backend = Arc::new(RecordingBackend::default());
+    let changed = Arc::new(AtomicBool::new(false));
+    for generation in generations {
+        start_revert_timer(
+            backend.clone(),
+            None,
+            revert.clone(),
+            changed.clone(),
+            *generation,
+            Duration::from_millis(50),
+        );
+    }
+    thread::sleep(Duration::from_millis(500));
+    (backend, changed.load(Ordering::SeqCst))
+}
+
+#[test]
+fn revert_unconfirmed_configuration() {
+    let previous = create_kanshi_monitors();
+    let revert = Arc::new(std::sync::Mutex::new(RevertData::default()));
+    let generation = revert.lock().unwrap().start(previous.clone(), true);
+    let (backend, changed) = run_revert_timers(&revert, &[generation]);
+    assert_eq!(*backend.applied.lock().unwrap(), vec![(previous, true)]);
+    assert!(changed);
+    assert!(!revert.lock().unwrap().cancel());
+}
+
+#[test]
+fn revert_confirmed_configuration() {
+    let revert = Arc::new(std::sync::Mutex::new(RevertData::default()));
+    let generation = revert
+        .lock()
+        .unwrap()
+        .start(create_kanshi_monitors(), false);
+    assert!(revert.lock().unwrap().cancel());
+    let (backend, changed) = run_revert_timers(&revert, &[generation]);
+    assert!(backend.applied.lock().unwrap().is_empty());
+    assert!(!changed);
+}
+
+#[test]
+fn revert_replaced_configuration() {
+    // the last confirmed configuration is restored once, by the timer of the latest configuration
+    let previous = create_kanshi_monitors();
+    let revert = Arc::new(std::sync::Mutex::new(RevertData::default()));
+    let first = revert.lock().unwrap().start(previous.clone(), false);
+    let second = revert
+        .lock()
+        .unwrap()
+        .start(create_mirrored_monitors(), false);
+    let (backend, _) = run_revert_timers(&revert, &[first, second]);
+    assert_eq!(*backend.applied.lock().unwrap(), vec![(previous, false)]);
+    assert_eq!(revert_timeout(3), 3);
+}
+
+#[test]
+fn export_import_layout() {
+    let mut monitors = create_kanshi_monitors();
+    monitors[1].offset = Offset(1920, 0);
+    monitors[1].drag_information.clicked = true;
+    let layout = export_layout(&monitors).unwrap();
+    assert!(layout.contains("\"version\": 1"));
+    assert!(!layout.contains("drag_information"));
+    let document = parse_layout(&layout).unwrap();
+    assert_eq!(document.version, LAYOUT_VERSION);
+    assert!(!document.monitors[1].drag_information.clicked);
+    assert_eq!(document.monitors[1].offset, monitors[1].offset);
+
+    // the layout is applied onto the connected monitors, unknown monitors are kept as is
+    let mut current = create_kanshi_monitors();
+    current[1].name = "DP-5".into();
+    current.push(Monitor {
+        name: "HDMI-A-1".into(),
+        enabled: true,
+        ..Default::default()
+    });
+    let imported = import_layout(&layout, &current).unwrap();
+    assert_eq!(imported.len(), 3);
+    assert_eq!(imported[1].name, "DP-5");
+    assert_eq!(imported[1].offset, Offset(1920, 0));
+    assert_eq!(imported[2], current[2]);
+}
+
+#[test]
+fn import_invalid_layout() {
+    let future = r#"{ "version": 2, "monitors": [{ "name": "DP-1" }] }"#;
+    assert!(matches!(parse_layout(future), Err(MonitorError::Parse(_))));
+    let empty = r#"{ "version": 1, "monitors": [] }"#;
+    assert!(matches!(parse_layout(empty), Err(MonitorError::Parse(_))));
+    assert!(matches!(parse_layout("{"), Err(MonitorError::Parse(_))));
+    // values missing in older documents use their defaults
+    let partial = r#"{ "version": 1, "monitors": [{ "name": "DP-1", "enabled": true }] }"#;
+    let document = parse_layout(partial).unwrap();
+    assert_eq!(document.monitors[0].name, "DP-1");
+    assert_eq!(document.monitors[0].custom_mode, CustomMode::default());
+}
+
+#[cfg(test)]
+const EDID_DELL: &[u8] = include_bytes!("tests/fixtures/edid_dell.bin");
+#[cfg(test)]
+const EDID_PANEL: &[u8] = include_bytes!("tests/fixtures/edid_panel.bin");
+
+#[test]
+fn edid_with_cta_extension() {
+    let edid = parse_edid(EDID_DELL).unwrap();
+    assert_eq!(edid.manufacturer_id, "DEL");
+    assert_eq!(edid.manufacturer, "Dell");
+    assert_eq!(edid.product_code, 0xA0E4);
+    assert_eq!(edid.product_name, "DELL U2720Q");
+    assert_eq!(edid.serial, "F8KFX13");
+    assert_eq!(edid.info.physical_size, Size(597, 336));
+    assert_eq!(edid.info.native_size, Size(3840, 2160));
+    assert_eq!(edid.info.native_refresh_rate, 59997);
+    assert_eq!(edid.info.eotfs, vec!["SDR", "PQ"]);
+    assert_eq!(
+        edid.info.color_spaces,
+        vec!["BT2020YCC", "BT2020RGB", "DCI-P3"]
+    );
+    assert_eq!(edid.info.max_luminance, 400.0);
+    assert!((edid.info.max_frame_average_luminance - 351.25).abs() < 0.01);
+    assert!((edid.info.min_luminance - 0.119).abs() < 0.001);
+}
+
+#[test]
+fn edid_without_extension() {
+    let edid = parse_edid(EDID_PANEL).unwrap();
+    assert_eq!(edid.manufacturer, "BOE");
+    // panels only contain unspecified text descriptors
+    assert_eq!(edid.product_name, "");
+    assert_eq!(edid.serial, "");
+    assert_eq!(edid.info.physical_size, Size(344, 194));
+    assert_eq!(edid.info.native_size, Size(1920, 1080));
+    assert_eq!(edid.info.native_refresh_rate, 59934);
+    assert!(edid.info.eotfs.is_empty());
+    assert_eq!(edid.info.max_luminance, 0.0);
+
+    let mut corrupted = EDID_PANEL.to_vec();
+    corrupted[20] ^= 0xFF;
+    assert!(matches!(
+        parse_edid(&corrupted),
+        Err(MonitorError::Parse(_))
+    ));
+    assert!(parse_edid(&EDID_PANEL[..64]).is_err());
+    assert!(parse_edid(&[]).is_err());
+
+    // extensions with a broken checksum are ignored
+    let mut corrupted = EDID_DELL.to_vec();
+    corrupted[200] ^= 0xFF;
+    let edid = parse_edid(&corrupted).unwrap();
+    assert_eq!(edid.product_name, "DELL U2720Q");
+    assert!(edid.info.color_spaces.is_empty());
+}
+
+#[test]
+fn edid_connectors() {
+    let path = std::env::temp_dir().join(format!("reset-monitors-drm-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    for (connector, edid) in [
+        ("card1-DP-1", EDID_DELL),
+        ("card1-eDP-1", EDID_PANEL),
+        // disconnected
+        ("card1-HDMI-A-1", &[]),
+    ] {
+        std::fs::create_dir_all(path.join(connector)).unwrap();
+        std::fs::write(path.join(connector).join("edid"), edid).unwrap();
+    }
+    std::fs::create_dir_all(path.join("card1")).unwrap();
+    let edids = read_edids(&path);
+    std::fs::remove_dir_all(&path).unwrap();
+    assert_eq!(edids.len(), 2);
+    assert_eq!(edids["card1-DP-1"].product_name, "DELL U2720Q");
+    assert_eq!(edids["card1-eDP-1"].manufacturer, "BOE");
+
+    let mut monitors = vec![
+        Monitor {
+            name: "DP-1".into(),
+            make: "Dell Inc.".into(),
+            ..Default::default()
+        },
+        Monitor {
+            name: "eDP-1".into(),
+            ..Default::default()
+        },
+        Monitor {
+            name: "HDMI-A-1".into(),
+            ..Default::default()
+        },
+    ];
+    apply_edids(&mut monitors, &edids);
+    // values reported by the compositor are kept
+    assert_eq!(monitors[0].make, "Dell Inc.");
+    assert_eq!(monitors[0].model, "DELL U2720Q");
+    assert_eq!(monitors[0].serial, "F8KFX13");
+    assert_eq!(monitors[0].edid.physical_size, Size(597, 336));
+    assert_eq!(monitors[1].make, "BOE");
+    assert_eq!(monitors[1].model, "0x0896");
+    assert_eq!(monitors[1].serial, "");
+    assert_eq!(monitors[2].edid, EdidInfo::default());
+}
+
+#[test]
+fn edid_connectors_of_two_cards() {
+    let path = std::env::temp_dir().join(format!("reset-monitors-cards-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    for (connector, edid) in [("card0-DP-1", EDID_PANEL), ("card1-DP-1", EDID_DELL)] {
+        std::fs::create_dir_all(path.join(connector)).unwrap();
+        std::fs::write(path.join(connector).join("edid"), edid).unwrap();
+    }
+    let edids = read_edids(&path);
+    std::fs::remove_dir_all(&path).unwrap();
+    assert_eq!(edids.len(), 2);
+
+    // the identity reported by the compositor decides which card the monitor is connected to
+    let mut monitors = vec![
+        Monitor {
+            name: "DP-1".into(),
+            serial: "F8KFX13".into(),
+            ..Default::default()
+        },
+        Monitor {
+            name: "DP-1".into(),
+            model: "DELL U2720Q".into(),
+            ..Default::default()
+        },
+        // ambiguous, no EDID is better than the EDID of a different monitor
+        Monitor {
+            name: "DP-1".into(),
+            ..Default::default()
+        },
+    ];
+    apply_edids(&mut monitors, &edids);
+    assert_eq!(monitors[0].model, "DELL U2720Q");
+    assert_eq!(monitors[0].edid.physical_size, Size(597, 336));
+    assert_eq!(monitors[1].serial, "F8KFX13");
+    assert_eq!(monitors[2].make, "");
+    assert_eq!(monitors[2].edid, EdidInfo::default());
+}
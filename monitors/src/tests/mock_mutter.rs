@@ -0,0 +1,439 @@
+// This file offers a stand-in for org.gnome.Mutter.DisplayConfig on a private session bus.
+// The configuration is scripted, applied configurations are recorded and change the state like
+// mutter would, with the exception of the verify method.
+use std::{
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use dbus::{
+    arg::{PropMap, RefArg, Variant},
+    blocking::Connection,
+    channel::{Channel, MatchingReceiver},
+    message::MatchRule,
+    MethodErr,
+};
+use dbus_crossroads::{Crossroads, IfaceBuilder};
+
+const BASE: &str = "org.gnome.Mutter.DisplayConfig";
+const DBUS_PATH: &str = "/org/gnome/Mutter/DisplayConfig";
+const INTERFACE: &str = "org.gnome.Mutter.DisplayConfig";
+const PROCESS_TIMEOUT: Duration = Duration::from_millis(10);
+
+pub const METHOD_VERIFY: u32 = 0;
+pub const METHOD_TEMPORARY: u32 = 1;
+pub const METHOD_PERSISTENT: u32 = 2;
+
+#[derive(Debug, Clone)]
+pub enum MockValue {
+    Bool(bool),
+    Str(String),
+    Uint(u32),
+}
+
+impl MockValue {
+    fn to_variant(&self) -> Variant<Box<dyn RefArg>> {
+        match self {
+            MockValue::Bool(value) => Variant(Box::new(*value)),
+            MockValue::Str(value) => Variant(Box::new(value.clone())),
+            MockValue::Uint(value) => Variant(Box::new(*value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MockMutterMode {
+    pub id: String,
+    pub width: i32,
+    pub height: i32,
+    pub refresh_rate: f64,
+    pub preferred: bool,
+    pub supported_scales: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MockMutterMonitor {
+    pub connector: String,
+    pub vendor: String,
+    pub product: String,
+    pub serial: String,
+    pub modes: Vec<MockMutterMode>,
+    // id of the current mode, disabled monitors keep their last mode
+    pub current_mode: String,
+    pub properties: Vec<(String, MockValue)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MockLogicalMonitor {
+    pub x: i32,
+    pub y: i32,
+    pub scale: f64,
+    pub transform: u32,
+    pub primary: bool,
+    // connectors, the mode is taken from the monitor
+    pub monitors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MockMutterState {
+    pub serial: u32,
+    pub monitors: Vec<MockMutterMonitor>,
+    pub logical_monitors: Vec<MockLogicalMonitor>,
+    pub properties: Vec<(String, MockValue)>,
+}
+
+/// A logical monitor as it was received with ApplyMonitorsConfig.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AppliedLogicalMonitor {
+    pub x: i32,
+    pub y: i32,
+    pub scale: f64,
+    pub transform: u32,
+    pub primary: bool,
+    // connector and mode id
+    pub monitors: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AppliedConfig {
+    pub serial: u32,
+    pub method: u32,
+    pub logical_monitors: Vec<AppliedLogicalMonitor>,
+}
+
+type ReceivedLogicalMonitor = (i32, i32, f64, u32, bool, Vec<(String, String, PropMap)>);
+type SentMode = (String, i32, i32, f64, f64, Vec<f64>, PropMap);
+type SentMonitor = ((String, String, String, String), Vec<SentMode>, PropMap);
+type SentLogicalMonitor = (
+    i32,
+    i32,
+    f64,
+    u32,
+    bool,
+    Vec<(String, String, String, String)>,
+    PropMap,
+);
+
+#[derive(Default)]
+struct Shared {
+    state: MockMutterState,
+    applied: Vec<AppliedConfig>,
+}
+
+/// Runs a private dbus-daemon with the DisplayConfig service until it is dropped.
+pub struct MockMutter {
+    shared: Arc<Mutex<Shared>>,
+    address: String,
+    daemon: Child,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockMutter {
+    /// Requires dbus-daemon to be installed.
+    pub fn start(state: MockMutterState) -> Self {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address=1"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("dbus-daemon is required for the mutter tests");
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.as_mut().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        let address = address.trim().to_string();
+
+        let shared = Arc::new(Mutex::new(Shared {
+            state,
+            applied: Vec::new(),
+        }));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (ready_sender, ready) = mpsc::channel();
+        let shared_ref = shared.clone();
+        let stop_ref = stop.clone();
+        let address_ref = address.clone();
+        let thread = thread::spawn(move || {
+            let conn = connect(&address_ref);
+            conn.request_name(BASE, false, true, false).unwrap();
+            let mut cr = Crossroads::new();
+            let token = cr.register(INTERFACE, setup_display_config);
+            cr.insert(DBUS_PATH, &[token], shared_ref);
+            conn.start_receive(
+                MatchRule::new_method_call(),
+                Box::new(move |msg, conn| {
+                    let _ = cr.handle_message(msg, conn);
+                    true
+                }),
+            );
+            ready_sender.send(()).unwrap();
+            while !stop_ref.load(Ordering::SeqCst) {
+                if conn.process(PROCESS_TIMEOUT).is_err() {
+                    break;
+                }
+            }
+        });
+        ready.recv().unwrap();
+        MockMutter {
+            shared,
+            address,
+            daemon,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// Returns a new connection to the private bus.
+    pub fn connection(&self) -> Connection {
+        connect(&self.address)
+    }
+
+    pub fn state(&self) -> MockMutterState {
+        self.shared.lock().unwrap().state.clone()
+    }
+
+    /// Returns every accepted configuration, including verified ones.
+    pub fn applied(&self) -> Vec<AppliedConfig> {
+        self.shared.lock().unwrap().applied.clone()
+    }
+
+    /// Simulates a configuration change outside of ReSet, e.g. a hotplug.
+    pub fn bump_serial(&self) {
+        self.shared.lock().unwrap().state.serial += 1;
+    }
+}
+
+impl Drop for MockMutter {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+fn connect(address: &str) -> Connection {
+    let mut channel = Channel::open_private(address).unwrap();
+    channel.register().unwrap();
+    Connection::from(channel)
+}
+
+fn to_prop_map(properties: &[(String, MockValue)]) -> PropMap {
+    properties
+        .iter()
+        .map(|(key, value)| (key.clone(), value.to_variant()))
+        .collect()
+}
+
+fn setup_display_config(b: &mut IfaceBuilder<Arc<Mutex<Shared>>>) {
+    b.method(
+        "GetCurrentState",
+        (),
+        ("serial", "monitors", "logical_monitors", "properties"),
+        move |_, shared: &mut Arc<Mutex<Shared>>, ()| {
+            let state = &shared.lock().unwrap().state;
+            Ok((
+                state.serial,
+                state.monitors.iter().map(sent_monitor).collect::<Vec<_>>(),
+                state
+                    .logical_monitors
+                    .iter()
+                    .map(|logical_monitor| sent_logical_monitor(state, logical_monitor))
+                    .collect::<Vec<_>>(),
+                to_prop_map(&state.properties),
+            ))
+        },
+    );
+    b.method(
+        "ApplyMonitorsConfig",
+        ("serial", "method", "logical_monitors", "properties"),
+        (),
+        move |_,
+              shared: &mut Arc<Mutex<Shared>>,
+              (serial, method, logical_monitors, _properties): (
+            u32,
+            u32,
+            Vec<ReceivedLogicalMonitor>,
+            PropMap,
+        )| {
+            let mut shared = shared.lock().unwrap();
+            let config = AppliedConfig {
+                serial,
+                method,
+                logical_monitors: logical_monitors
+                    .into_iter()
+                    .map(
+                        |(x, y, scale, transform, primary, monitors)| AppliedLogicalMonitor {
+                            x,
+                            y,
+                            scale,
+                            transform,
+                            primary,
+                            monitors: monitors
+                                .into_iter()
+                                .map(|(connector, mode, _)| (connector, mode))
+                                .collect(),
+                        },
+                    )
+                    .collect(),
+            };
+            verify_config(&shared.state, &config)?;
+            shared.applied.push(config.clone());
+            if method != METHOD_VERIFY {
+                apply_config(&mut shared.state, config);
+            }
+            Ok(())
+        },
+    );
+}
+
+fn sent_monitor(monitor: &MockMutterMonitor) -> SentMonitor {
+    let modes = monitor
+        .modes
+        .iter()
+        .map(|mode| {
+            let mut properties = Vec::new();
+            if mode.id == monitor.current_mode {
+                properties.push(("is-current".to_string(), MockValue::Bool(true)));
+            }
+            if mode.preferred {
+                properties.push(("is-preferred".to_string(), MockValue::Bool(true)));
+            }
+            (
+                mode.id.clone(),
+                mode.width,
+                mode.height,
+                mode.refresh_rate,
+                1.0,
+                mode.supported_scales.clone(),
+                to_prop_map(&properties),
+            )
+        })
+        .collect();
+    (
+        (
+            monitor.connector.clone(),
+            monitor.vendor.clone(),
+            monitor.product.clone(),
+            monitor.serial.clone(),
+        ),
+        modes,
+        to_prop_map(&monitor.properties),
+    )
+}
+
+fn sent_logical_monitor(
+    state: &MockMutterState,
+    logical_monitor: &MockLogicalMonitor,
+) -> SentLogicalMonitor {
+    let monitors = logical_monitor
+        .monitors
+        .iter()
+        .filter_map(|connector| {
+            state
+                .monitors
+                .iter()
+                .find(|monitor| monitor.connector == *connector)
+        })
+        .map(|monitor| {
+            (
+                monitor.connector.clone(),
+                monitor.vendor.clone(),
+                monitor.product.clone(),
+                monitor.serial.clone(),
+            )
+        })
+        .collect();
+    (
+        logical_monitor.x,
+        logical_monitor.y,
+        logical_monitor.scale,
+        logical_monitor.transform,
+        logical_monitor.primary,
+        monitors,
+        PropMap::new(),
+    )
+}
+
+// the same checks mutter runs before a configuration is accepted
+fn verify_config(state: &MockMutterState, config: &AppliedConfig) -> Result<(), MethodErr> {
+    if config.serial != state.serial {
+        return Err(MethodErr::from((
+            "org.freedesktop.DBus.Error.AccessDenied",
+            "The requested configuration is based on stale information",
+        )));
+    }
+    if config.method > METHOD_PERSISTENT {
+        return Err(MethodErr::invalid_arg("Invalid method"));
+    }
+    if config.logical_monitors.is_empty() {
+        return Err(MethodErr::invalid_arg("No logical monitors"));
+    }
+    for logical_monitor in config.logical_monitors.iter() {
+        let mut size = None;
+        for (connector, mode_id) in logical_monitor.monitors.iter() {
+            let monitor = state
+                .monitors
+                .iter()
+                .find(|monitor| monitor.connector == *connector)
+                .ok_or_else(|| MethodErr::invalid_arg(&format!("Invalid monitor {}", connector)))?;
+            let mode = monitor
+                .modes
+                .iter()
+                .find(|mode| mode.id == *mode_id)
+                .ok_or_else(|| MethodErr::invalid_arg(&format!("Invalid mode {}", mode_id)))?;
+            if size.is_some_and(|size| size != (mode.width, mode.height)) {
+                return Err(MethodErr::invalid_arg(
+                    "Monitors within a logical monitor need the same resolution",
+                ));
+            }
+            size = Some((mode.width, mode.height));
+        }
+    }
+    if !config
+        .logical_monitors
+        .iter()
+        .any(|logical_monitor| logical_monitor.primary)
+    {
+        return Err(MethodErr::invalid_arg("No primary logical monitor"));
+    }
+    Ok(())
+}
+
+fn apply_config(state: &mut MockMutterState, config: AppliedConfig) {
+    for logical_monitor in config.logical_monitors.iter() {
+        for (connector, mode_id) in logical_monitor.monitors.iter() {
+            let monitor = state
+                .monitors
+                .iter_mut()
+                .find(|monitor| monitor.connector == *connector)
+                .unwrap();
+            monitor.current_mode = mode_id.clone();
+        }
+    }
+    state.logical_monitors = config
+        .logical_monitors
+        .into_iter()
+        .map(|logical_monitor| MockLogicalMonitor {
+            x: logical_monitor.x,
+            y: logical_monitor.y,
+            scale: logical_monitor.scale,
+            transform: logical_monitor.transform,
+            primary: logical_monitor.primary,
+            monitors: logical_monitor
+                .monitors
+                .into_iter()
+                .map(|(connector, _)| connector)
+                .collect(),
+        })
+        .collect();
+    state.serial += 1;
+}
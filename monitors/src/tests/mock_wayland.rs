@@ -0,0 +1,558 @@
+// This file offers a minimal wayland compositor for tests.
+// It advertises zwlr_output_manager_v1, kde_output_device_v2 and kde_output_management_v2 with
+// scripted heads and modes, applied configurations are recorded instead of changing the heads.
+use std::{
+    os::unix::{
+        io::{AsRawFd, RawFd},
+        net::UnixStream,
+    },
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use wayland_protocols_plasma::{
+    output_device::v2::server::{
+        kde_output_device_mode_v2::KdeOutputDeviceModeV2,
+        kde_output_device_v2::{self, KdeOutputDeviceV2},
+    },
+    output_management::v2::server::{
+        kde_output_configuration_v2::{self, KdeOutputConfigurationV2},
+        kde_output_management_v2::{self, KdeOutputManagementV2},
+    },
+};
+use wayland_protocols_wlr::output_management::v1::server::{
+    zwlr_output_configuration_head_v1::{self, ZwlrOutputConfigurationHeadV1},
+    zwlr_output_configuration_v1::{self, ZwlrOutputConfigurationV1},
+    zwlr_output_head_v1::{self, ZwlrOutputHeadV1},
+    zwlr_output_manager_v1::{self, ZwlrOutputManagerV1},
+    zwlr_output_mode_v1::ZwlrOutputModeV1,
+};
+use wayland_server::{
+    protocol::wl_output::Transform, Client, DataInit, Dispatch, Display, DisplayHandle,
+    GlobalDispatch, New, Resource,
+};
+
+const POLL_TIMEOUT_MS: i32 = 10;
+
+#[derive(Debug, Clone, Default)]
+pub struct MockMode {
+    pub width: i32,
+    pub height: i32,
+    // millihertz, like in both protocols
+    pub refresh: i32,
+    pub preferred: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MockHead {
+    pub name: String,
+    pub make: String,
+    pub model: String,
+    pub serial: String,
+    pub enabled: bool,
+    pub modes: Vec<MockMode>,
+    pub current_mode: usize,
+    pub position: (i32, i32),
+    pub transform: i32,
+    pub scale: f64,
+    pub vrr: bool,
+}
+
+/// The configuration of a single head as it was received by the compositor.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AppliedHead {
+    pub name: String,
+    pub enabled: bool,
+    // width, height and refresh rate
+    pub mode: Option<(i32, i32, i32)>,
+    pub position: Option<(i32, i32)>,
+    pub transform: Option<i32>,
+    pub scale: Option<f64>,
+    pub primary: bool,
+}
+
+/// Runs the compositor on its own thread until it is dropped.
+pub struct MockCompositor {
+    applied: Arc<Mutex<Vec<Vec<AppliedHead>>>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockCompositor {
+    /// Starts the compositor and returns it along with a connected client.
+    /// When reject is set, every configuration is answered with failed.
+    pub fn start(heads: Vec<MockHead>, reject: bool) -> (Self, wayland_client::Connection) {
+        let mut display = Display::<State>::new().unwrap();
+        let handle = display.handle();
+        handle.create_global::<State, ZwlrOutputManagerV1, ()>(
+            ZwlrOutputManagerV1::interface().version,
+            (),
+        );
+        handle.create_global::<State, KdeOutputManagementV2, ()>(
+            KdeOutputManagementV2::interface().version,
+            (),
+        );
+        for index in 0..heads.len() {
+            handle.create_global::<State, KdeOutputDeviceV2, usize>(
+                KdeOutputDeviceV2::interface().version,
+                index,
+            );
+        }
+
+        let (client_socket, server_socket) = UnixStream::pair().unwrap();
+        let mut handle = display.handle();
+        handle.insert_client(server_socket, Arc::new(())).unwrap();
+        let client = wayland_client::Connection::from_socket(client_socket).unwrap();
+
+        let applied = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut state = State {
+            heads,
+            reject,
+            applied: applied.clone(),
+        };
+        let stop_ref = stop.clone();
+        let thread = thread::spawn(move || {
+            let fd = display.backend().poll_fd().as_raw_fd();
+            while !stop_ref.load(Ordering::SeqCst) {
+                wait_for_requests(fd);
+                if display.dispatch_clients(&mut state).is_err() {
+                    break;
+                }
+                let _ = display.flush_clients();
+            }
+        });
+        (
+            MockCompositor {
+                applied,
+                stop,
+                thread: Some(thread),
+            },
+            client,
+        )
+    }
+
+    /// Returns every configuration that was applied so far, including rejected ones.
+    pub fn applied(&self) -> Vec<Vec<AppliedHead>> {
+        self.applied.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockCompositor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn wait_for_requests(fd: RawFd) {
+    let mut poll_fd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    unsafe {
+        libc::poll(&mut poll_fd, 1, POLL_TIMEOUT_MS);
+    }
+}
+
+struct State {
+    heads: Vec<MockHead>,
+    reject: bool,
+    applied: Arc<Mutex<Vec<Vec<AppliedHead>>>>,
+}
+
+// heads and devices carry the index of their head, modes the index of their head and mode
+type ModeData = (usize, usize);
+
+// pending heads of a configuration
+type Pending = Mutex<Vec<AppliedHead>>;
+
+impl State {
+    fn mode(&self, mode: &impl Resource) -> Option<(i32, i32, i32)> {
+        let (head, mode) = mode.data::<ModeData>()?;
+        let mode = &self.heads[*head].modes[*mode];
+        Some((mode.width, mode.height, mode.refresh))
+    }
+
+    // KDE configurations address heads by their device
+    fn update_device(
+        &self,
+        pending: &Pending,
+        device: &KdeOutputDeviceV2,
+        update: impl FnOnce(&mut AppliedHead),
+    ) {
+        let index = device.data::<usize>().unwrap();
+        let name = &self.heads[*index].name;
+        let mut pending = pending.lock().unwrap();
+        let position = pending.iter().position(|head| head.name == *name);
+        let head = match position {
+            Some(position) => &mut pending[position],
+            None => {
+                pending.push(AppliedHead {
+                    name: name.clone(),
+                    ..Default::default()
+                });
+                pending.last_mut().unwrap()
+            }
+        };
+        update(head);
+    }
+
+    fn apply(&self, pending: &Pending) -> bool {
+        let pending = pending.lock().unwrap().clone();
+        self.applied.lock().unwrap().push(pending);
+        !self.reject
+    }
+}
+
+// child objects can not have a higher version than the object that announced them
+fn child_version<I: Resource>(parent: &impl Resource) -> u32 {
+    parent.version().min(I::interface().version)
+}
+
+impl GlobalDispatch<ZwlrOutputManagerV1, ()> for State {
+    fn bind(
+        state: &mut Self,
+        handle: &DisplayHandle,
+        client: &Client,
+        resource: New<ZwlrOutputManagerV1>,
+        _: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let manager = data_init.init(resource, ());
+        for index in 0..state.heads.len() {
+            send_wlr_head(state, handle, client, &manager, index);
+        }
+        manager.done(1);
+    }
+}
+
+impl GlobalDispatch<KdeOutputManagementV2, ()> for State {
+    fn bind(
+        _: &mut Self,
+        _: &DisplayHandle,
+        _: &Client,
+        resource: New<KdeOutputManagementV2>,
+        _: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl GlobalDispatch<KdeOutputDeviceV2, usize> for State {
+    fn bind(
+        state: &mut Self,
+        handle: &DisplayHandle,
+        client: &Client,
+        resource: New<KdeOutputDeviceV2>,
+        index: &usize,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let device = data_init.init(resource, *index);
+        send_kde_device(state, handle, client, &device, *index);
+    }
+}
+
+fn send_wlr_head(
+    state: &State,
+    handle: &DisplayHandle,
+    client: &Client,
+    manager: &ZwlrOutputManagerV1,
+    index: usize,
+) {
+    let head = client
+        .create_resource::<ZwlrOutputHeadV1, usize, State>(
+            handle,
+            child_version::<ZwlrOutputHeadV1>(manager),
+            index,
+        )
+        .unwrap();
+    manager.head(&head);
+    let mock = &state.heads[index];
+    head.name(mock.name.clone());
+    head.description(format!("{} {}", mock.make, mock.model));
+    let mut current = None;
+    for (mode_index, mode) in mock.modes.iter().enumerate() {
+        let object = client
+            .create_resource::<ZwlrOutputModeV1, ModeData, State>(
+                handle,
+                child_version::<ZwlrOutputModeV1>(&head),
+                (index, mode_index),
+            )
+            .unwrap();
+        head.mode(&object);
+        object.size(mode.width, mode.height);
+        object.refresh(mode.refresh);
+        if mode.preferred {
+            object.preferred();
+        }
+        if mode_index == mock.current_mode {
+            current = Some(object);
+        }
+    }
+    head.enabled(mock.enabled as i32);
+    if mock.enabled {
+        if let Some(current) = current {
+            head.current_mode(&current);
+        }
+        head.position(mock.position.0, mock.position.1);
+        head.transform(Transform::try_from(mock.transform as u32).unwrap());
+        head.scale(mock.scale);
+    }
+    // events the client did not bind are skipped
+    if head.version() >= zwlr_output_head_v1::EVT_MAKE_SINCE {
+        head.make(mock.make.clone());
+        head.model(mock.model.clone());
+        head.serial_number(mock.serial.clone());
+    }
+    if head.version() >= zwlr_output_head_v1::EVT_ADAPTIVE_SYNC_SINCE {
+        head.adaptive_sync(if mock.vrr {
+            zwlr_output_head_v1::AdaptiveSyncState::Enabled
+        } else {
+            zwlr_output_head_v1::AdaptiveSyncState::Disabled
+        });
+    }
+}
+
+fn send_kde_device(
+    state: &State,
+    handle: &DisplayHandle,
+    client: &Client,
+    device: &KdeOutputDeviceV2,
+    index: usize,
+) {
+    let mock = &state.heads[index];
+    device.geometry(
+        mock.position.0,
+        mock.position.1,
+        0,
+        0,
+        0,
+        mock.make.clone(),
+        mock.model.clone(),
+        mock.transform,
+    );
+    let mut current = None;
+    for (mode_index, mode) in mock.modes.iter().enumerate() {
+        let object = client
+            .create_resource::<KdeOutputDeviceModeV2, ModeData, State>(
+                handle,
+                child_version::<KdeOutputDeviceModeV2>(device),
+                (index, mode_index),
+            )
+            .unwrap();
+        device.mode(&object);
+        object.size(mode.width, mode.height);
+        object.refresh(mode.refresh);
+        if mode.preferred {
+            object.preferred();
+        }
+        if mode_index == mock.current_mode {
+            current = Some(object);
+        }
+    }
+    if let Some(current) = current {
+        device.current_mode(&current);
+    }
+    device.scale(mock.scale);
+    device.enabled(mock.enabled as i32);
+    device.serial_number(mock.serial.clone());
+    device.vrr_policy(if mock.vrr {
+        kde_output_device_v2::VrrPolicy::Always
+    } else {
+        kde_output_device_v2::VrrPolicy::Never
+    });
+    if device.version() >= kde_output_device_v2::EVT_NAME_SINCE {
+        device.name(mock.name.clone());
+    }
+    device.done();
+}
+
+impl Dispatch<ZwlrOutputManagerV1, ()> for State {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &ZwlrOutputManagerV1,
+        request: zwlr_output_manager_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let zwlr_output_manager_v1::Request::CreateConfiguration { id, .. } = request {
+            data_init.init(id, Pending::default());
+        }
+    }
+}
+
+impl Dispatch<ZwlrOutputConfigurationV1, Pending> for State {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        configuration: &ZwlrOutputConfigurationV1,
+        request: zwlr_output_configuration_v1::Request,
+        pending: &Pending,
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_output_configuration_v1::Request::EnableHead { id, head } => {
+                let index = head.data::<usize>().unwrap();
+                let mut pending = pending.lock().unwrap();
+                pending.push(AppliedHead {
+                    name: state.heads[*index].name.clone(),
+                    enabled: true,
+                    ..Default::default()
+                });
+                data_init.init(id, (configuration.clone(), pending.len() - 1));
+            }
+            zwlr_output_configuration_v1::Request::DisableHead { head } => {
+                let index = head.data::<usize>().unwrap();
+                pending.lock().unwrap().push(AppliedHead {
+                    name: state.heads[*index].name.clone(),
+                    ..Default::default()
+                });
+            }
+            zwlr_output_configuration_v1::Request::Apply => {
+                if state.apply(pending) {
+                    configuration.succeeded();
+                } else {
+                    configuration.failed();
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<ZwlrOutputConfigurationHeadV1, (ZwlrOutputConfigurationV1, usize)> for State {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &ZwlrOutputConfigurationHeadV1,
+        request: zwlr_output_configuration_head_v1::Request,
+        (configuration, index): &(ZwlrOutputConfigurationV1, usize),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+        let pending = configuration.data::<Pending>().unwrap();
+        let mut pending = pending.lock().unwrap();
+        let head = &mut pending[*index];
+        match request {
+            zwlr_output_configuration_head_v1::Request::SetMode { mode } => {
+                head.mode = state.mode(&mode)
+            }
+            zwlr_output_configuration_head_v1::Request::SetCustomMode {
+                width,
+                height,
+                refresh,
+            } => head.mode = Some((width, height, refresh)),
+            zwlr_output_configuration_head_v1::Request::SetPosition { x, y } => {
+                head.position = Some((x, y))
+            }
+            zwlr_output_configuration_head_v1::Request::SetTransform { transform } => {
+                head.transform = Some(u32::from(transform) as i32)
+            }
+            zwlr_output_configuration_head_v1::Request::SetScale { scale } => {
+                head.scale = Some(scale)
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<KdeOutputManagementV2, ()> for State {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &KdeOutputManagementV2,
+        request: kde_output_management_v2::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let kde_output_management_v2::Request::CreateConfiguration { id } = request {
+            data_init.init(id, Pending::default());
+        }
+    }
+}
+
+impl Dispatch<KdeOutputConfigurationV2, Pending> for State {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        configuration: &KdeOutputConfigurationV2,
+        request: kde_output_configuration_v2::Request,
+        pending: &Pending,
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            kde_output_configuration_v2::Request::Enable {
+                outputdevice,
+                enable,
+            } => state.update_device(pending, &outputdevice, |head| head.enabled = enable != 0),
+            kde_output_configuration_v2::Request::Mode { outputdevice, mode } => {
+                let mode = state.mode(&mode);
+                state.update_device(pending, &outputdevice, |head| head.mode = mode);
+            }
+            kde_output_configuration_v2::Request::Position { outputdevice, x, y } => {
+                state.update_device(pending, &outputdevice, |head| head.position = Some((x, y)))
+            }
+            kde_output_configuration_v2::Request::Transform {
+                outputdevice,
+                transform,
+            } => state.update_device(pending, &outputdevice, |head| {
+                head.transform = Some(transform)
+            }),
+            kde_output_configuration_v2::Request::Scale {
+                outputdevice,
+                scale,
+            } => state.update_device(pending, &outputdevice, |head| head.scale = Some(scale)),
+            kde_output_configuration_v2::Request::SetPrimaryOutput { output } => {
+                state.update_device(pending, &output, |head| head.primary = true)
+            }
+            kde_output_configuration_v2::Request::Apply => {
+                if state.apply(pending) {
+                    configuration.applied();
+                } else {
+                    configuration.failed();
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+// objects without requests that need to be answered
+macro_rules! ignore_requests {
+    ($($interface:ty: $data:ty),*) => {
+        $(
+            impl Dispatch<$interface, $data> for State {
+                fn request(
+                    _: &mut Self,
+                    _: &Client,
+                    _: &$interface,
+                    _: <$interface as Resource>::Request,
+                    _: &$data,
+                    _: &DisplayHandle,
+                    _: &mut DataInit<'_, Self>,
+                ) {
+                }
+            }
+        )*
+    };
+}
+
+ignore_requests!(
+    ZwlrOutputHeadV1: usize,
+    ZwlrOutputModeV1: ModeData,
+    KdeOutputDeviceV2: usize,
+    KdeOutputDeviceModeV2: ModeData
+);
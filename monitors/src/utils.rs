@@ -1,7 +1,14 @@
-use std::{fmt::Display, sync::Arc, time::Duration};
+use std::{
+    fmt::Display,
+    sync::{atomic::AtomicBool, Arc, Mutex},
+    time::Duration,
+};
 
 use crate::{
-    backend::utils::get_wl_backend,
+    backend::{
+        brightness::BrightnessData, general::MonitorBackend, revert::RevertData,
+        utils::get_wl_backend,
+    },
     r#const::{BASE, DBUS_PATH, INTERFACE, SUPPORTED_ENVIRONMENTS},
 };
 use dbus::{
@@ -9,17 +16,25 @@ use dbus::{
     blocking::Connection,
     Error, Signature,
 };
-use gtk::prelude::WidgetExt;
 
 use once_cell::sync::Lazy;
 use re_set_lib::ERROR;
 #[cfg(debug_assertions)]
 use re_set_lib::{utils::macros::ErrorLevel, write_log_to_file};
+// the types of the DBus interface are shared with reset-cli
+pub use reset_common::monitors::{
+    format_refresh_rate, to_millihertz, AvailableMode, CustomMode, DragInformation, EdidInfo,
+    HdrSettings, Monitor, MonitorFeatures, Offset, Size, DEFAULT_SDR_BRIGHTNESS, MAX_MODE_SIZE,
+    MAX_REFRESH_RATE, MAX_SDR_BRIGHTNESS, MIN_SDR_BRIGHTNESS,
+};
 
 pub static ENV: Lazy<String> = Lazy::new(get_environment);
+// fetched once, the backend does not change while ReSet is running
+pub static CAPABILITIES: Lazy<BackendCapabilities> = Lazy::new(get_capabilities);
 pub const GNOME: &str = "GNOME";
 pub const HYPRLAND: &str = "Hyprland";
 pub const KDE: &str = "KDE";
+pub const SWAY: &str = "sway";
 
 pub fn get_environment() -> String {
     let desktop = std::env::var("XDG_CURRENT_DESKTOP");
@@ -52,440 +67,382 @@ pub fn get_monitor_data() -> Vec<Monitor> {
     res.unwrap().0
 }
 
+pub fn get_capabilities() -> BackendCapabilities {
+    let res: Result<(BackendCapabilities, Vec<(String, MonitorFeatures)>), Error> =
+        Connection::new_session().and_then(|conn| {
+            let proxy = conn.with_proxy(BASE, DBUS_PATH, Duration::from_millis(1000));
+            proxy.method_call(INTERFACE, "GetCapabilities", ())
+        });
+    if let Err(_error) = res {
+        ERROR!(
+            format!("Could not fetch monitor capabilities {}", _error),
+            ErrorLevel::Recoverable
+        );
+        return BackendCapabilities::default();
+    }
+    res.unwrap().0
+}
+
 #[repr(C)]
 #[derive(Debug, Clone)]
 pub struct MonitorData {
+    pub backend: Arc<dyn MonitorBackend>,
     pub monitors: Vec<Monitor>,
     pub connection: Option<Arc<wayland_client::Connection>>,
     // needed for gnome
     pub serial: u32,
-}
-
-#[repr(C)]
-#[derive(Debug, Clone, Default, PartialEq)]
-pub struct DragInformation {
-    pub drag_x: i32,
-    pub drag_y: i32,
-    pub border_offset_x: i32,
-    pub border_offset_y: i32,
-    pub origin_x: i32,
-    pub origin_y: i32,
-    pub width: i32,
-    pub height: i32,
-    pub factor: i32,
-    pub drag_active: bool,
-    pub clicked: bool,
-    pub changed: bool,
-    pub prev_scale: f64,
-    pub drag_allowed: bool,
-    pub resolution_changed: bool,
-}
-
-#[repr(C)]
-#[derive(Debug, Clone, Default, Copy, PartialEq, Eq)]
-pub struct MonitorFeatures {
-    pub vrr: bool,
-    pub primary: bool,
-    pub fractional_scaling: bool,
-    pub hdr: bool,
-}
-
-impl<'a> Get<'a> for MonitorFeatures {
-    fn get(i: &mut arg::Iter<'a>) -> Option<Self> {
-        let (vrr, primary, fractional_scaling, hdr) = <(bool, bool, bool, bool)>::get(i)?;
-        Some(Self {
-            vrr,
-            primary,
-            fractional_scaling,
-            hdr,
-        })
-    }
-}
-
-impl Append for MonitorFeatures {
-    fn append_by_ref(&self, iter: &mut arg::IterAppend) {
-        iter.append_struct(|i| {
-            i.append(self.vrr);
-            i.append(self.primary);
-            i.append(self.fractional_scaling);
-            i.append(self.hdr);
-        });
-    }
-}
-
-impl Arg for MonitorFeatures {
-    const ARG_TYPE: arg::ArgType = ArgType::Struct;
-    fn signature() -> Signature<'static> {
-        unsafe { Signature::from_slice_unchecked("(bbbb)\0") }
-    }
-}
-
+    // set when the compositor reports a change that was not made via ReSet
+    pub changed: Arc<AtomicBool>,
+    // the gamma control is lost once the protocol objects are destroyed, hence they are kept here
+    pub brightness: Arc<Mutex<BrightnessData>>,
+    // shared with the scheduler of environments without a native night light
+    pub night_light: Arc<Mutex<NightLight>>,
+    // configuration that is restored unless the applied configuration is confirmed in time
+    pub revert: Arc<Mutex<RevertData>>,
+}
+
+/// Rules of the environment that apply to the whole configuration, the features of individual
+/// monitors are reported via MonitorFeatures.
 #[repr(C)]
-#[derive(Debug, Clone, Default, PartialEq)]
-pub struct Monitor {
-    pub id: u32,
-    pub enabled: bool,
-    pub name: String,
-    pub make: String,
-    pub model: String,
-    pub serial: String,
-    pub refresh_rate: u32,
-    pub scale: f64,
-    pub transform: u32,
-    pub vrr: bool,
-    pub primary: bool,
-    pub offset: Offset,
-    pub size: Size,
-    pub drag_information: DragInformation,
-    pub mode: String,
-    pub available_modes: Vec<AvailableMode>,
-    pub uses_mode_id: bool,
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BackendCapabilities {
+    // configurations can be saved persistently via SaveMonitors
+    pub persistent_save: bool,
+    // the compositor asks for a confirmation of persistent configurations on its own
+    pub confirms_save: bool,
+    // monitors can be placed with gaps in between
+    pub gaps: bool,
+    // the layout starts at the origin and only consists of enabled monitors, disabled monitors
+    // are placed next to it
+    pub compact_layout: bool,
+    // any scale can be used, otherwise only the supported scales of the mode
+    pub arbitrary_scales: bool,
+    // mirrored monitors are handled by the compositor instead of sharing their position
+    pub native_mirroring: bool,
+    // VRR support is reported per monitor, otherwise VRR is offered for every monitor
+    pub reports_vrr: bool,
+    // the gamut of SDR content can be widened while HDR is enabled
+    pub sdr_gamut_wideness: bool,
+    // features offered by the environment, individual monitors may offer less
     pub features: MonitorFeatures,
 }
 
-impl Monitor {
-    // unlucky
-    #[allow(clippy::too_many_arguments)]
-    pub fn new(
-        id: u32,
-        enabled: bool,
-        name: impl Into<String>,
-        make: impl Into<String>,
-        model: impl Into<String>,
-        serial: impl Into<String>,
-        refresh_rate: u32,
-        scale: f64,
-        transform: u32,
-        vrr: bool,
-        primary: bool,
-        offset_x: i32,
-        offset_y: i32,
-        width: i32,
-        height: i32,
-        available_modes: Vec<AvailableMode>,
-        uses_mode_id: bool,
-        features: MonitorFeatures,
-    ) -> Self {
-        Self {
-            id,
-            enabled,
-            name: name.into(),
-            make: make.into(),
-            model: model.into(),
-            serial: serial.into(),
-            refresh_rate,
-            scale,
-            transform,
-            vrr,
-            primary,
-            offset: Offset(offset_x, offset_y),
-            size: Size(width, height),
-            mode: "".into(),
-            drag_information: DragInformation::default(),
-            available_modes,
-            uses_mode_id,
-            features,
-        }
-    }
-
-    pub fn handle_transform(&self) -> (i32, i32) {
-        match self.transform {
-            0 => (self.size.0, self.size.1),
-            1 => (self.size.1, self.size.0),
-            2 => (self.size.0, self.size.1),
-            3 => (self.size.1, self.size.0),
-            4 => (self.size.0, self.size.1),
-            5 => (self.size.1, self.size.0),
-            6 => (self.size.0, self.size.1),
-            7 => (self.size.1, self.size.0),
-            _ => {
-                ERROR!("Received unsupported transform", ErrorLevel::Recoverable);
-                (self.size.0, self.size.1)
-            }
-        }
-    }
-
-    pub fn handle_scaled_transform(&self) -> (i32, i32) {
-        let (width, height) = self.handle_transform();
-        let (scaled_width, scaled_height) = if self.scale <= 0.0 {
-            (width as f64, height as f64)
-        } else {
-            (
-                (width as f64 / self.scale).round(),
-                (height as f64 / self.scale).round(),
-            )
-        };
-        (scaled_width as i32, scaled_height as i32)
-    }
-}
-
-impl Append for Monitor {
-    fn append_by_ref(&self, iter: &mut arg::IterAppend) {
-        iter.append_struct(|i| {
-            i.append(self.id);
-            i.append(self.enabled);
-            i.append((
-                self.name.clone(),
-                self.make.clone(),
-                self.model.clone(),
-                self.serial.clone(),
-            ));
-            i.append((self.refresh_rate, self.scale, self.transform));
-            i.append(self.vrr);
-            i.append(self.primary);
-            i.append(self.offset);
-            i.append(self.size);
-            i.append(self.mode.clone());
-            i.append(self.available_modes.clone());
-            i.append(self.uses_mode_id);
-            i.append(self.features);
-        });
-    }
-}
-
-impl<'a> Get<'a> for Monitor {
+impl<'a> Get<'a> for BackendCapabilities {
     fn get(i: &mut arg::Iter<'a>) -> Option<Self> {
         let (
-            id,
-            enabled,
-            (name, make, model, serial),
-            (refresh_rate, scale, transform),
-            vrr,
-            primary,
-            offset,
-            size,
-            mode,
-            available_modes,
-            uses_mode_id,
+            persistent_save,
+            confirms_save,
+            gaps,
+            compact_layout,
+            arbitrary_scales,
+            native_mirroring,
+            reports_vrr,
+            sdr_gamut_wideness,
             features,
         ) = <(
-            u32,
             bool,
-            (String, String, String, String),
-            (u32, f64, u32),
             bool,
             bool,
-            Offset,
-            Size,
-            String,
-            Vec<AvailableMode>,
+            bool,
+            bool,
+            bool,
+            bool,
             bool,
             MonitorFeatures,
         )>::get(i)?;
         Some(Self {
-            id,
-            enabled,
-            name,
-            make,
-            model,
-            serial,
-            refresh_rate,
-            scale,
-            transform,
-            vrr,
-            primary,
-            offset,
-            size,
-            mode,
-            drag_information: DragInformation::default(),
-            available_modes,
-            uses_mode_id,
+            persistent_save,
+            confirms_save,
+            gaps,
+            compact_layout,
+            arbitrary_scales,
+            native_mirroring,
+            reports_vrr,
+            sdr_gamut_wideness,
             features,
         })
     }
 }
 
-impl Arg for Monitor {
-    const ARG_TYPE: arg::ArgType = ArgType::Struct;
-    fn signature() -> Signature<'static> {
-        unsafe {
-            Signature::from_slice_unchecked("(ub(ssss)(udu)bb(ii)(ii)sa(s(ii)a(us)ad)b(bbbb))\0")
-        }
+impl Append for BackendCapabilities {
+    fn append_by_ref(&self, iter: &mut arg::IterAppend) {
+        iter.append_struct(|i| {
+            i.append(self.persistent_save);
+            i.append(self.confirms_save);
+            i.append(self.gaps);
+            i.append(self.compact_layout);
+            i.append(self.arbitrary_scales);
+            i.append(self.native_mirroring);
+            i.append(self.reports_vrr);
+            i.append(self.sdr_gamut_wideness);
+            i.append(self.features);
+        });
     }
 }
 
-impl Monitor {
-    /// These coordinates are calculated from the edge of the drawing box. Ensure the rest of the
-    /// window is also taken into account when passing parameters as it will otherwise evaluate to
-    /// false.
-    pub fn is_coordinate_within(&self, x: i32, y: i32) -> bool {
-        let offset_x = self.offset.0 / self.drag_information.factor;
-        let offset_y = self.offset.1 / self.drag_information.factor;
-        let height = self.drag_information.height / self.drag_information.factor;
-        let width = self.drag_information.width / self.drag_information.factor;
-        x >= self.drag_information.border_offset_x + offset_x
-            && x <= self.drag_information.border_offset_x + offset_x + width
-            && y >= self.drag_information.border_offset_y + offset_y
-            && y <= self.drag_information.border_offset_y + offset_y + height
-    }
-
-    /// Checks whether or not the currently dragged monitor has any overlap with existing monitors.
-    /// If this is the case, then the monitor should be reset to original position on drop.
-    pub fn intersect_horizontal(&self, offset_x: i32, other_width: i32) -> bool {
-        let (width, _) = self.handle_scaled_transform();
-        // current monitor left side is right of other right
-        let left = self.drag_information.border_offset_x + self.offset.0 >= offset_x + other_width;
-        // current monitor right is left of other left
-        let right = self.drag_information.border_offset_x + self.offset.0 + width <= offset_x;
-        !left && !right
-    }
-
-    pub fn intersect_vertical(&self, offset_y: i32, other_height: i32) -> bool {
-        let (_, height) = self.handle_scaled_transform();
-        // current monitor bottom is higher than other top
-        let bottom =
-            self.drag_information.border_offset_y + self.offset.1 >= offset_y + other_height;
-        // current monitor top is lower than other bottom
-        let top = self.drag_information.border_offset_y + self.offset.1 + height <= offset_y;
-        !bottom && !top
+impl Arg for BackendCapabilities {
+    const ARG_TYPE: arg::ArgType = ArgType::Struct;
+    fn signature() -> Signature<'static> {
+        unsafe { Signature::from_slice_unchecked("(bbbbbbbb(bbbbbbb))\0") }
     }
 }
 
+/// Brightness and gamma of a monitor, brightness is changed via the hardware when possible.
 #[repr(C)]
-#[derive(Debug, Clone, Copy, Default, PartialEq)]
-pub struct Offset(pub i32, pub i32);
-
-impl<'a> Get<'a> for Offset {
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MonitorBrightness {
+    // one of the BRIGHTNESS_* methods, empty when the brightness can't be changed
+    pub method: String,
+    // percent
+    pub brightness: u32,
+    // gamma is only available via the gamma control protocol
+    pub gamma_supported: bool,
+    pub gamma: f64,
+}
+
+pub const BRIGHTNESS_DDC: &str = "ddc";
+pub const BRIGHTNESS_BACKLIGHT: &str = "backlight";
+pub const BRIGHTNESS_GAMMA: &str = "gamma";
+// software dimming down to 0 would result in a black screen
+pub const MIN_BRIGHTNESS: u32 = 5;
+pub const MAX_BRIGHTNESS: u32 = 100;
+pub const MIN_GAMMA: f64 = 0.5;
+pub const MAX_GAMMA: f64 = 2.5;
+
+impl<'a> Get<'a> for MonitorBrightness {
     fn get(i: &mut arg::Iter<'a>) -> Option<Self> {
-        let (x, y) = <(i32, i32)>::get(i)?;
-        Some(Self(x, y))
+        let (method, brightness, gamma_supported, gamma) = <(String, u32, bool, f64)>::get(i)?;
+        Some(Self {
+            method,
+            brightness,
+            gamma_supported,
+            gamma,
+        })
     }
 }
 
-impl Append for Offset {
+impl Append for MonitorBrightness {
     fn append_by_ref(&self, iter: &mut arg::IterAppend) {
         iter.append_struct(|i| {
-            i.append(self.0);
-            i.append(self.1);
+            i.append(self.method.clone());
+            i.append(self.brightness);
+            i.append(self.gamma_supported);
+            i.append(self.gamma);
         });
     }
 }
 
-impl Arg for Offset {
+impl Arg for MonitorBrightness {
     const ARG_TYPE: arg::ArgType = ArgType::Struct;
     fn signature() -> Signature<'static> {
-        unsafe { Signature::from_slice_unchecked("(ii)\0") }
+        unsafe { Signature::from_slice_unchecked("(subd)\0") }
     }
 }
 
-impl Offset {
-    pub fn from_scale(x: i32, y: i32, scale: f64) -> Offset {
-        let scaled_x = x as f64 / scale;
-        let scaled_y = y as f64 / scale;
-        Offset(scaled_x as i32, scaled_y as i32)
+/// Night light settings, the colour temperature is lowered between start and end.
+#[repr(C)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NightLight {
+    pub enabled: bool,
+    // kelvin
+    pub temperature: u32,
+    // one of the NIGHT_LIGHT_* schedules
+    pub schedule: String,
+    // minutes after midnight in local time, only used for the fixed schedule
+    pub start: u32,
+    pub end: u32,
+    // only used for the sun schedule
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+pub const NIGHT_LIGHT_FIXED: &str = "fixed";
+pub const NIGHT_LIGHT_SUN: &str = "sun";
+pub const MIN_TEMPERATURE: u32 = 1000;
+pub const NEUTRAL_TEMPERATURE: u32 = 6500;
+pub const DEFAULT_NIGHT_TEMPERATURE: u32 = 4000;
+
+impl Default for NightLight {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            temperature: DEFAULT_NIGHT_TEMPERATURE,
+            schedule: NIGHT_LIGHT_FIXED.into(),
+            // 20:00 to 06:00
+            start: 20 * 60,
+            end: 6 * 60,
+            latitude: 0.0,
+            longitude: 0.0,
+        }
     }
 }
 
-#[repr(C)]
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Size(pub i32, pub i32);
-
-impl<'a> Get<'a> for Size {
+impl<'a> Get<'a> for NightLight {
     fn get(i: &mut arg::Iter<'a>) -> Option<Self> {
-        let (width, height) = <(i32, i32)>::get(i)?;
-        Some(Self(width, height))
+        let (enabled, temperature, schedule, start, end, latitude, longitude) =
+            <(bool, u32, String, u32, u32, f64, f64)>::get(i)?;
+        Some(Self {
+            enabled,
+            temperature,
+            schedule,
+            start,
+            end,
+            latitude,
+            longitude,
+        })
     }
 }
 
-impl Append for Size {
+impl Append for NightLight {
     fn append_by_ref(&self, iter: &mut arg::IterAppend) {
         iter.append_struct(|i| {
-            i.append(self.0);
-            i.append(self.1);
+            i.append(self.enabled);
+            i.append(self.temperature);
+            i.append(self.schedule.clone());
+            i.append(self.start);
+            i.append(self.end);
+            i.append(self.latitude);
+            i.append(self.longitude);
         });
     }
 }
 
-impl Arg for Size {
+impl Arg for NightLight {
     const ARG_TYPE: arg::ArgType = ArgType::Struct;
     fn signature() -> Signature<'static> {
-        unsafe { Signature::from_slice_unchecked("(ii)\0") }
+        unsafe { Signature::from_slice_unchecked("(busuudd)\0") }
     }
 }
 
+/// A problem of a monitor configuration, the code allows clients to handle specific problems.
 #[repr(C)]
-#[derive(Debug, Clone, Copy, Default, PartialEq)]
-pub struct Scale(pub u32, pub u32);
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationError {
+    // name of the affected monitor, empty for problems of the whole configuration
+    pub monitor: String,
+    // one of the INVALID_* codes
+    pub code: u32,
+    pub message: String,
+}
 
-impl<'a> Get<'a> for Scale {
+pub const INVALID_NO_ENABLED_MONITOR: u32 = 1;
+pub const INVALID_OVERLAP: u32 = 2;
+pub const INVALID_GAP: u32 = 3;
+pub const INVALID_SCALE: u32 = 4;
+pub const INVALID_MODE: u32 = 5;
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.monitor.is_empty() {
+            return write!(f, "{}", self.message);
+        }
+        write!(f, "{}: {}", self.monitor, self.message)
+    }
+}
+
+impl<'a> Get<'a> for ValidationError {
     fn get(i: &mut arg::Iter<'a>) -> Option<Self> {
-        let (x, y) = <(u32, u32)>::get(i)?;
-        Some(Self(x, y))
+        let (monitor, code, message) = <(String, u32, String)>::get(i)?;
+        Some(Self {
+            monitor,
+            code,
+            message,
+        })
     }
 }
 
-impl Append for Scale {
+impl Append for ValidationError {
     fn append_by_ref(&self, iter: &mut arg::IterAppend) {
         iter.append_struct(|i| {
-            i.append(self.0);
-            i.append(self.1);
+            i.append(self.monitor.clone());
+            i.append(self.code);
+            i.append(self.message.clone());
         });
     }
 }
 
-impl Arg for Scale {
+impl Arg for ValidationError {
     const ARG_TYPE: arg::ArgType = ArgType::Struct;
     fn signature() -> Signature<'static> {
-        unsafe { Signature::from_slice_unchecked("(uu)\0") }
+        unsafe { Signature::from_slice_unchecked("(sus)\0") }
     }
 }
 
-impl Display for Scale {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&format!("{}.{}", self.0, self.1))
+/// Errors of the monitor backends, each variant is reported with its own DBus error name.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MonitorError {
+    /// The configuration was rejected before it was sent to the compositor.
+    InvalidConfiguration(String),
+    /// The compositor rejected the configuration.
+    ApplyFailed(String),
+    /// A tool, socket or protocol required by the backend is not available.
+    MissingTool(String),
+    /// Reading or writing a configuration file failed.
+    Io(String),
+    /// A configuration file or a reply of the compositor could not be parsed.
+    Parse(String),
+    UnsupportedEnvironment,
+}
+
+impl MonitorError {
+    pub fn dbus_name(&self) -> &'static str {
+        match self {
+            MonitorError::InvalidConfiguration(_) => {
+                "org.Xetibo.ReSet.Monitors.Error.InvalidConfiguration"
+            }
+            MonitorError::ApplyFailed(_) => "org.Xetibo.ReSet.Monitors.Error.ApplyFailed",
+            MonitorError::MissingTool(_) => "org.Xetibo.ReSet.Monitors.Error.MissingTool",
+            MonitorError::Io(_) => "org.Xetibo.ReSet.Monitors.Error.Io",
+            MonitorError::Parse(_) => "org.Xetibo.ReSet.Monitors.Error.Parse",
+            MonitorError::UnsupportedEnvironment => {
+                "org.Xetibo.ReSet.Monitors.Error.UnsupportedEnvironment"
+            }
+        }
     }
 }
 
-#[repr(C)]
-#[derive(Debug, Clone, Default, PartialEq)]
-pub struct AvailableMode {
-    pub id: String,
-    pub size: Size,
-    pub refresh_rates: Vec<(u32, String)>,
-    pub supported_scales: Vec<f64>,
+impl Display for MonitorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MonitorError::InvalidConfiguration(message) => {
+                write!(f, "Invalid configuration: {}", message)
+            }
+            MonitorError::ApplyFailed(message) => {
+                write!(f, "Could not apply configuration: {}", message)
+            }
+            MonitorError::MissingTool(tool) => write!(f, "{} is not available", tool),
+            MonitorError::Io(message) => write!(f, "File operation failed: {}", message),
+            MonitorError::Parse(message) => write!(f, "Could not parse: {}", message),
+            MonitorError::UnsupportedEnvironment => write!(f, "Unsupported Environment"),
+        }
+    }
 }
 
-impl<'a> Get<'a> for AvailableMode {
-    fn get(i: &mut arg::Iter<'a>) -> Option<Self> {
-        let (id, size, refresh_rates, supported_scales) =
-            <(String, Size, Vec<(u32, String)>, Vec<f64>)>::get(i)?;
-        Some(Self {
-            id,
-            size,
-            refresh_rates,
-            supported_scales,
-        })
+impl From<std::io::Error> for MonitorError {
+    fn from(error: std::io::Error) -> Self {
+        MonitorError::Io(error.to_string())
     }
 }
 
-impl Append for AvailableMode {
-    fn append_by_ref(&self, iter: &mut arg::IterAppend) {
-        iter.append_struct(|i| {
-            i.append(self.id.clone());
-            i.append(self.size);
-            let sig = unsafe { Signature::from_slice_unchecked("(us)\0") };
-            i.append_array(&sig, |i| {
-                for refresh_rate in self.refresh_rates.iter() {
-                    i.append(refresh_rate);
-                }
-            });
-            let sig = unsafe { Signature::from_slice_unchecked("d\0") };
-            i.append_array(&sig, |i| {
-                for scale in self.supported_scales.iter() {
-                    i.append(scale);
-                }
-            });
-        });
+impl From<serde_json::Error> for MonitorError {
+    fn from(error: serde_json::Error) -> Self {
+        MonitorError::Parse(error.to_string())
     }
 }
 
-impl Arg for AvailableMode {
-    const ARG_TYPE: arg::ArgType = ArgType::Struct;
-    fn signature() -> Signature<'static> {
-        unsafe { Signature::from_slice_unchecked("(s(ii)a(us)ad)\0") }
+impl From<MonitorError> for dbus::MethodErr {
+    fn from(error: MonitorError) -> Self {
+        (error.dbus_name(), error.to_string()).into()
     }
 }
 
+// fractional scaling can only be done when the scale divides the resolution to a whole
+// number.
+// Example: 1080 / 1.5 -> 720. E.g. the factor 1.5 will also resolve to a whole number.
+pub fn is_nonfunctional_scale(width: i32, height: i32, scale: f64) -> bool {
+    width as f64 % scale != 0.0 && height as f64 % scale != 0.0 && scale != 1.0
+}
+
 #[derive(Eq, PartialEq, PartialOrd, Ord)]
 pub enum SnapDirectionHorizontal {
     RightRight(i32),
@@ -504,32 +461,7 @@ pub enum SnapDirectionVertical {
     None,
 }
 
-pub struct AlertWrapper {
-    pub popup: adw::AlertDialog,
-}
-
-unsafe impl Send for AlertWrapper {}
-unsafe impl Sync for AlertWrapper {}
-
-impl AlertWrapper {
-    pub fn action(&self) {
-        self.popup.activate_default();
-    }
-}
-
 pub fn is_gnome() -> bool {
     ENV.contains(GNOME)
 }
 
-pub fn is_hyprland() -> bool {
-    ENV.contains(HYPRLAND)
-}
-
-pub fn is_kde() -> bool {
-    ENV.contains(KDE)
-}
-
-pub fn is_flatpak() -> bool {
-    ENV.contains("container")
-}
-
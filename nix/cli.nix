@@ -0,0 +1,42 @@
+{ rustPlatform
+, rust-bin
+, pkg-config
+, dbus
+, lib
+, ...
+}:
+let
+  cargoToml = builtins.fromTOML (builtins.readFile ../cli/Cargo.toml);
+  lockFile = ../cli/Cargo.lock;
+in
+rustPlatform.buildRustPackage rec {
+  pname = cargoToml.package.name;
+  version = cargoToml.package.version;
+
+  # the DBus types are shared with the plugins in ../common
+  src = ../.;
+  cargoRoot = "cli";
+  buildAndTestSubdir = "cli";
+
+  buildInputs = [
+    dbus
+  ];
+
+  cargoLock = {
+    inherit lockFile;
+  };
+
+  nativeBuildInputs = [
+    pkg-config
+    rust-bin.nightly."2024-05-10".default
+  ];
+
+  meta = with lib; {
+    description = "A command-line client for the plugins of the ReSet settings application.";
+    homepage = "https://github.com/DashieTM/ReSet-Plugins";
+    changelog = "https://github.com/DashieTM/ReSet-Plugins/releases/tag/${version}";
+    license = licenses.gpl3;
+    maintainers = with maintainers; [ DashieTM ];
+    mainProgram = "reset-cli";
+  };
+}
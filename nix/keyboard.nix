@@ -23,7 +23,10 @@ rustPlatform.buildRustPackage rec {
   pname = cargoToml.package.name;
   version = cargoToml.package.version;
 
-  src = ../keyboard_plugin;
+  # the plugins depend on the shared crate in ../common
+  src = ../.;
+  cargoRoot = "keyboard_plugin";
+  buildAndTestSubdir = "keyboard_plugin";
 
   buildInputs = [
     pkg-config
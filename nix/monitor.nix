@@ -18,7 +18,10 @@ rustPlatform.buildRustPackage rec {
   pname = cargoToml.package.name;
   version = cargoToml.package.version;
 
-  src = ../monitors/.;
+  # the plugins depend on the shared crate in ../common
+  src = ../.;
+  cargoRoot = "monitors";
+  buildAndTestSubdir = "monitors";
 
   buildInputs = [
     pkg-config